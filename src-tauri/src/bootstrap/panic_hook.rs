@@ -12,6 +12,7 @@ use tracing::error;
 
 use super::internals;
 use crate::consts;
+use crate::utilities::redact::redact_authkeys;
 
 pub fn install() {
   let crashs_dir = consts::PLATFORM
@@ -128,11 +129,16 @@ impl Crash<'_> {
       .format(consts::CRASHS_TIME_FORMAT)
       .unwrap();
 
+    // The panic message may contain the gacha url with the authkey.
+    // Users attach the crash report to the issue, so redact them.
+    let report = format!("{self:#}");
+    let report = redact_authkeys(&report);
+
     let report_filename = format!("{}_Crash_Report_{datetime}.log", self.name);
     let report_path = dir.as_ref().join(&report_filename);
     let mut report_file = File::create(&report_path)?;
     writeln!(report_file, "{report_filename}\n")?;
-    report_file.write_fmt(format_args!("{self:#}"))?;
+    report_file.write_all(report.as_bytes())?;
     writeln!(report_file)?;
     report_file.flush()?;
 
@@ -162,8 +168,8 @@ impl Crash<'_> {
       error!(
         target: consts::PKG_NAME,
         message,
-        cause = ?self.cause,
-        explanation = ?self.explanation,
+        cause = ?redact_authkeys(self.cause),
+        explanation = ?redact_authkeys(&self.explanation),
         backtrace = %self.backtrace,
       );
     } else {
@@ -9,7 +9,7 @@ use tauri::{
   Builder as TauriBuilder, Emitter, Error as TauriError, Manager, Monitor, PhysicalPosition,
   PhysicalSize, Runtime, Theme, WebviewUrl, WindowEvent, generate_context, generate_handler,
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use super::ffi;
 use super::internals;
@@ -17,9 +17,13 @@ use super::singleton::Singleton;
 use super::tracing::Tracing;
 use super::updater::{UpdatedKind, Updater};
 use crate::business::GachaMetadata;
-use crate::database::{self, Database, KvMut};
-use crate::models::{ThemeData, WindowState};
-use crate::utilities::file_dialog;
+use crate::database::{self, Database, DatabaseState, KvMut};
+use crate::error::{Error, ErrorDetails};
+use crate::models::{
+  AutoSyncSchedule, EndpointOverrides, HttpClientSettings, ThemeData, WindowState,
+};
+use crate::utilities::rate_limiter::RateLimiterSettings;
+use crate::utilities::{file_dialog, http_client};
 use crate::{business, consts};
 
 struct WindowStateCache(Arc<Mutex<WindowState>>);
@@ -58,6 +62,70 @@ pub async fn start(singleton: Singleton, tracing: Tracing, database: Database) {
 
   let is_some_window_state = window_state.is_some();
 
+  info!("Loading gacha url rate limiter settings...");
+  match KvMut::from(&database, consts::KV_GACHA_URL_RATE_LIMITER)
+    .try_read_val_json::<RateLimiterSettings>()
+    .await
+    .expect("Error reading gacha url rate limiter settings from database")
+  {
+    Some(Ok(settings)) => {
+      business::set_gacha_url_rate_limiter_settings(settings);
+    }
+    Some(Err(error)) => {
+      warn!("Invalid gacha url rate limiter settings, use the default: {error}");
+    }
+    None => {}
+  }
+
+  info!("Loading http client settings...");
+  match KvMut::from(&database, consts::KV_HTTP_CLIENT_SETTINGS)
+    .try_read_val_json::<HttpClientSettings>()
+    .await
+    .expect("Error reading http client settings from database")
+  {
+    Some(Ok(settings)) => {
+      if let Err(error) = http_client::set_http_client_settings(settings) {
+        warn!("Invalid http client settings, use the default: {error}");
+      }
+    }
+    Some(Err(error)) => {
+      warn!("Invalid http client settings, use the default: {error}");
+    }
+    None => {}
+  }
+
+  info!("Loading endpoint overrides...");
+  match KvMut::from(&database, consts::KV_ENDPOINT_OVERRIDES)
+    .try_read_val_json::<EndpointOverrides>()
+    .await
+    .expect("Error reading endpoint overrides from database")
+  {
+    Some(Ok(overrides)) => {
+      if let Err(error) = business::set_endpoint_overrides(overrides) {
+        warn!("Invalid endpoint overrides, use the default: {error}");
+      }
+    }
+    Some(Err(error)) => {
+      warn!("Invalid endpoint overrides, use the default: {error}");
+    }
+    None => {}
+  }
+
+  info!("Loading auto sync schedule...");
+  match KvMut::from(&database, consts::KV_AUTO_SYNC_SCHEDULE)
+    .try_read_val_json::<AutoSyncSchedule>()
+    .await
+    .expect("Error reading auto sync schedule from database")
+  {
+    Some(Ok(schedule)) => {
+      business::set_auto_sync_schedule(schedule);
+    }
+    Some(Err(error)) => {
+      warn!("Invalid auto sync schedule, use the default: {error}");
+    }
+    None => {}
+  }
+
   fn update_window_state(state: &mut WindowState, window: &WebviewWindow) -> tauri::Result<()> {
     state.maximized = window.is_maximized()?;
 
@@ -195,6 +263,7 @@ pub async fn start(singleton: Singleton, tracing: Tracing, database: Database) {
         let _ = GachaMetadata::current();
 
         // Update Gacha metadata if needed
+        let database = Arc::clone(app.state::<Arc<Database>>().inner());
         tokio::spawn(async move {
           const RETRIES: u32 = 3;
           const MIN: Duration = Duration::from_secs(3);
@@ -202,7 +271,7 @@ pub async fn start(singleton: Singleton, tracing: Tracing, database: Database) {
 
           let backoff = exponential_backoff::Backoff::new(RETRIES, MIN, MAX);
           for duration in backoff {
-            if let Err(error) = GachaMetadata::update().await {
+            if let Err(error) = business::update_gacha_metadata(database.clone()).await {
               if let Some(duration) = duration {
                 tracing::warn!(message = "Failed to update Gacha metadata, retring...", ?duration, ?error);
                 tokio::time::sleep(duration).await;
@@ -218,6 +287,12 @@ pub async fn start(singleton: Singleton, tracing: Tracing, database: Database) {
         });
       }
 
+      // Run the scheduled incremental syncs in the background
+      {
+        let database = Arc::clone(app.state::<Arc<Database>>().inner());
+        tokio::spawn(business::run_auto_sync_scheduler(database, app.handle().clone()));
+      }
+
       info!("Application setup completed");
       Ok(())
     })
@@ -278,6 +353,9 @@ pub async fn start(singleton: Singleton, tracing: Tracing, database: Database) {
       core_is_supported_window_vibrancy,
       core_change_theme,
       core_create_app_lnk,
+      core_http_client_settings,
+      core_update_http_client_settings,
+      core_test_connectivity,
       core_system_fonts,
       core_pick_file,
       core_pick_folder,
@@ -289,6 +367,7 @@ pub async fn start(singleton: Singleton, tracing: Tracing, database: Database) {
       database::kv_questioner::database_update_kv,
       database::kv_questioner::database_upsert_kv,
       database::kv_questioner::database_delete_kv,
+      database::account_questioner::database_find_accounts,
       database::account_questioner::database_find_accounts_by_business,
       database::account_questioner::database_find_account_by_business_and_uid,
       database::account_questioner::database_create_account,
@@ -303,17 +382,40 @@ pub async fn start(singleton: Singleton, tracing: Tracing, database: Database) {
       database::gacha_record_questioner_additions::database_delete_gacha_records_by_business_and_uid,
       database::gacha_record_questioner_additions::database_find_gacha_records_by_businesses_and_uid,
       database::gacha_record_questioner_additions::database_find_gacha_records_by_businesses_or_uid,
+      database::gacha_records_audit_questioner::database_find_gacha_records_audits_by_business_and_uid_with_limit,
+      database::gacha_records_audit_questioner::database_find_gacha_records_audits_by_business_and_uid_and_id,
+      database::gacha_records_checkpoint_questioner::database_find_gacha_records_checkpoints_by_business_and_uid,
+      database::gacha_records_checkpoint_questioner::database_find_gacha_records_checkpoint,
+      database::gacha_records_checkpoint_questioner::database_delete_gacha_records_checkpoint,
+      database::gacha_records_checkpoint_questioner::database_delete_gacha_records_checkpoints_by_business_and_uid,
+      database::pending_gacha_record_questioner::database_find_pending_gacha_records_by_business_and_uid,
+      database::stored_gacha_url_questioner::database_find_stored_gacha_url_by_business_and_uid,
+      database::stored_gacha_url_questioner::database_delete_stored_gacha_url_by_business_and_uid,
       database::database_legacy_migration,
       business::business_locate_data_folder,
+      business::business_locate_data_folders,
       business::business_from_webcaches_gacha_url,
       business::business_from_dirty_gacha_url,
+      business::business_discover_accounts,
       business::business_create_gacha_records_fetcher,
+      business::business_cancel_gacha_records_fetcher,
+      business::business_apply_gacha_records_diff,
+      business::business_sync_all_accounts,
+      business::business_auto_sync_schedule,
+      business::business_update_auto_sync_schedule,
+      business::business_auto_sync_state,
+      business::business_verify_gacha_records,
+      business::business_apply_gacha_records_reconciliation,
       business::business_import_gacha_records,
       business::business_export_gacha_records,
       business::business_find_and_pretty_gacha_records,
       business::business_gacha_metadata_is_updating,
       business::business_gacha_metadata_update,
       business::business_gacha_metadata_item_name_from_id,
+      business::business_gacha_url_rate_limiter_settings,
+      business::business_update_gacha_url_rate_limiter_settings,
+      business::business_endpoint_overrides,
+      business::business_update_endpoint_overrides,
     ])
     .build(generate_context!())
     .expect("Error while building Tauri application");
@@ -444,6 +546,33 @@ async fn core_pick_folder(title: Option<String>, directory: Option<PathBuf>) ->
     .map(Into::into)
 }
 
+#[tauri::command]
+fn core_http_client_settings() -> HttpClientSettings {
+  http_client::http_client_settings()
+}
+
+#[tauri::command]
+async fn core_update_http_client_settings(
+  database: DatabaseState<'_>,
+  settings: HttpClientSettings,
+) -> Result<(), Box<dyn ErrorDetails + Send + 'static>> {
+  // Validate by building the client, before persisting it.
+  http_client::build_http_client(&settings).map_err(Error::boxed)?;
+
+  KvMut::from(database.as_ref(), consts::KV_HTTP_CLIENT_SETTINGS)
+    .try_write_json(&settings)
+    .await
+    .expect("Error serializing http client settings")
+    .map_err(Error::boxed)?;
+
+  http_client::set_http_client_settings(settings).map_err(Error::boxed)
+}
+
+#[tauri::command]
+async fn core_test_connectivity() -> Vec<business::EndpointConnectivity> {
+  business::test_endpoints_connectivity().await
+}
+
 #[tauri::command]
 async fn core_updater_is_updating() -> bool {
   Updater::is_updating()
@@ -13,7 +13,8 @@ use tokio::fs::{self, File};
 use tokio::io::AsyncWriteExt;
 use tracing::info;
 
-use crate::consts;
+use crate::utilities::http_client::http_client;
+use crate::{business, consts};
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TagName {
@@ -136,13 +137,13 @@ impl Updater {
       fs::remove_file(&before_exe).await?;
     }
 
-    const API_RELEASE: &str = "https://hoyo-gacha-v1.lgou2w.com/release";
+    let api_release = business::release_api_base_url();
     const API_TIMEOUT: Duration = Duration::from_secs(30);
 
     #[inline]
-    async fn fetch_latest_release() -> Result<LatestRelease, reqwest::Error> {
-      consts::REQWEST
-        .get(format!("{API_RELEASE}/latest"))
+    async fn fetch_latest_release(api_release: &str) -> Result<LatestRelease, reqwest::Error> {
+      http_client()
+        .get(format!("{api_release}/latest"))
         .timeout(API_TIMEOUT)
         .send()
         .await?
@@ -152,15 +153,16 @@ impl Updater {
     }
 
     #[inline]
-    async fn fetch_latest_release_with_retry() -> Result<LatestRelease, Box<dyn StdError + 'static>>
-    {
+    async fn fetch_latest_release_with_retry(
+      api_release: &str,
+    ) -> Result<LatestRelease, Box<dyn StdError + 'static>> {
       const RETRIES: u32 = 3;
       const MIN: Duration = Duration::from_secs(3);
       const MAX: Duration = Duration::from_secs(10);
 
       let backoff = exponential_backoff::Backoff::new(RETRIES, MIN, MAX);
       for duration in backoff {
-        match fetch_latest_release().await {
+        match fetch_latest_release(api_release).await {
           Ok(res) => return Ok(res),
           Err(error) => {
             let need_retry = error.is_connect()
@@ -193,7 +195,7 @@ impl Updater {
     }
 
     info!("Checking for updates...");
-    let latest_release = fetch_latest_release_with_retry().await?;
+    let latest_release = fetch_latest_release_with_retry(&api_release).await?;
 
     info!(
       message = "Latest release found",
@@ -214,8 +216,8 @@ impl Updater {
     // Download update
     on_progress(-1.)?; // Set progress to -1 to indicate download start
     let out_path = current_dir.join(format!("{}.temp", latest_release.name));
-    let mut res = consts::REQWEST
-      .get(format!("{API_RELEASE}/download"))
+    let mut res = http_client()
+      .get(format!("{api_release}/download"))
       .timeout(API_TIMEOUT)
       .send()
       .await?
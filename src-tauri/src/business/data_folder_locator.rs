@@ -1,13 +1,18 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fmt::Debug;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
 use cfg_if::cfg_if;
 use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::serde::rfc3339;
 use tokio::fs::File as TokioFile;
 use tokio::io::{AsyncBufReadExt, BufReader as TokioBufReader};
 use tracing::{Span, info, warn};
 
+use crate::business::gacha_url::{WebCachesVersion, latest_webcaches_version};
 use crate::consts;
 use crate::error::declare_error_kinds;
 use crate::models::{BizInternals, Business, BusinessRegion};
@@ -33,6 +38,9 @@ declare_error_kinds! {
 
     #[error("Data folder is vacant")]
     Vacant,
+
+    #[error("Data folder locator is unsupported")]
+    Unsupported,
   }
 }
 
@@ -43,6 +51,105 @@ pub struct DataFolder {
   pub value: PathBuf,
 }
 
+/// Which locator found the data folder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum DataFolderLocatorKind {
+  UnityLog,
+  Manual,
+  #[cfg(target_os = "linux")]
+  WinePrefix,
+}
+
+/// The data folder found by the locator, with the details to pick from.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataFolderCandidate {
+  #[serde(flatten)]
+  pub data_folder: DataFolder,
+  /// Whether the game executable file exists next to the data folder.
+  pub executable: bool,
+  /// Last modified time of the game executable file, otherwise of the data folder.
+  #[serde(with = "rfc3339::option")]
+  pub last_modified: Option<OffsetDateTime>,
+  pub webcaches_version: Option<WebCachesVersion>,
+  pub locator: DataFolderLocatorKind,
+}
+
+impl DataFolderCandidate {
+  pub fn new(
+    business: Business,
+    region: BusinessRegion,
+    value: PathBuf,
+    locator: DataFolderLocatorKind,
+  ) -> Self {
+    let biz = BizInternals::mapped(business, region);
+    let executable_file = value
+      .parent()
+      .map(|parent| biz.join_executable_file(parent))
+      .filter(|executable_file| executable_file.is_file());
+
+    let executable = executable_file.is_some();
+    let last_modified = executable_file
+      .as_deref()
+      .unwrap_or(&value)
+      .metadata()
+      .and_then(|metadata| metadata.modified())
+      .ok()
+      .map(OffsetDateTime::from);
+
+    let webcaches_version = latest_webcaches_version(&value);
+
+    Self {
+      data_folder: DataFolder {
+        business,
+        region,
+        value,
+      },
+      executable,
+      last_modified,
+      webcaches_version,
+      locator,
+    }
+  }
+
+  /// Deduplicate by the data folder, keeping the first one found. Then rank by:
+  /// executable file exists > newest webcaches version > last modified time.
+  pub fn rank(mut candidates: Vec<Self>) -> Vec<Self> {
+    let mut seen = HashSet::with_capacity(candidates.len());
+    candidates.retain(|candidate| seen.insert(candidate.data_folder.value.clone()));
+    candidates.sort_by(Self::compare_rank);
+    candidates
+  }
+
+  fn compare_rank(a: &Self, b: &Self) -> Ordering {
+    b.executable
+      .cmp(&a.executable)
+      .then_with(|| b.webcaches_version.cmp(&a.webcaches_version))
+      .then_with(|| b.last_modified.cmp(&a.last_modified))
+  }
+}
+
+/// Pick the best ranked candidate that has the game executable file.
+fn select_data_folder(candidates: Vec<DataFolderCandidate>) -> Result<DataFolder, DataFolderError> {
+  match candidates.into_iter().next() {
+    None => Err(DataFolderErrorKind::Vacant)?,
+    Some(candidate) if !candidate.executable => {
+      warn!(
+        "No valid game executable file found: {:?}",
+        candidate.data_folder.value
+      );
+      Err(DataFolderErrorKind::Invalid)?
+    }
+    Some(candidate) => {
+      info!(
+        "Located in the data folder: {:?}",
+        candidate.data_folder.value
+      );
+      Ok(candidate.data_folder)
+    }
+  }
+}
+
 #[async_trait]
 pub trait DataFolderLocator {
   async fn locate_data_folder(
@@ -50,44 +157,87 @@ pub trait DataFolderLocator {
     business: Business,
     region: BusinessRegion,
   ) -> Result<DataFolder, DataFolderError>;
+
+  /// Locate all the candidate data folders, deduplicated and ranked.
+  async fn locate_data_folders(
+    &self,
+    business: Business,
+    region: BusinessRegion,
+  ) -> Result<Vec<DataFolderCandidate>, DataFolderError>;
 }
 
 // region: Unity log
 
+/// Unity log file of the game in the `LocalLow` folder.
+fn unity_log_path(
+  biz: &BizInternals,
+  appdata_locallow_mihoyo: &Path,
+  appdata_locallow_cognosphere: &Path,
+) -> PathBuf {
+  let log_filename = if biz.business == Business::GenshinImpact {
+    "output_log.txt"
+  } else {
+    "Player.log"
+  };
+
+  let appdata_folder = if biz.business == Business::GenshinImpact
+    || biz.business == Business::ZenlessZoneZero
+    || biz.is_official()
+  {
+    appdata_locallow_mihoyo
+  } else {
+    appdata_locallow_cognosphere
+  };
+
+  appdata_folder
+    .join(biz.appdata_folder_subname())
+    .join(log_filename)
+}
+
+/// Extract the data folder path from a line of the Unity log.
+///
+/// For example: `[Subsystems] Discovering subsystems at path C:/Games/Genshin Impact/GenshinImpact_Data/UnitySubsystems`
+fn extract_unity_log_data_folder<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+  if line.len() <= keyword.len() || !line.contains(keyword) {
+    return None;
+  }
+
+  let colon = line.rfind(':')?;
+  let end = line.find(keyword)?;
+
+  // &line[colon - 1..end + keyword.len()]; // UNSAFE!
+  line.get(colon.checked_sub(1)?..end + keyword.len())
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UnityLogDataFolderLocator;
 
 #[async_trait]
 impl DataFolderLocator for UnityLogDataFolderLocator {
-  #[tracing::instrument(fields(log_path, keyword))]
   async fn locate_data_folder(
     &self,
     business: Business,
     region: BusinessRegion,
   ) -> Result<DataFolder, DataFolderError> {
-    info!("Locating the data folder...");
-
-    let biz = BizInternals::mapped(business, region);
+    select_data_folder(self.locate_data_folders(business, region).await?)
+  }
 
-    let log_filename = if biz.business == Business::GenshinImpact {
-      "output_log.txt"
-    } else {
-      "Player.log"
-    };
+  #[tracing::instrument(fields(log_path, keyword))]
+  async fn locate_data_folders(
+    &self,
+    business: Business,
+    region: BusinessRegion,
+  ) -> Result<Vec<DataFolderCandidate>, DataFolderError> {
+    info!("Locating the data folders...");
 
-    let appdata_folder = if biz.business == Business::GenshinImpact
-      || biz.business == Business::ZenlessZoneZero
-      || biz.is_official()
-    {
-      &*consts::PLATFORM.appdata_locallow_mihoyo
-    } else {
-      &*consts::PLATFORM.appdata_locallow_cognosphere
-    };
+    let biz = BizInternals::mapped(business, region);
 
     let span = Span::current();
-    let log_path = appdata_folder
-      .join(biz.appdata_folder_subname())
-      .join(log_filename);
+    let log_path = unity_log_path(
+      biz,
+      &consts::PLATFORM.appdata_locallow_mihoyo,
+      &consts::PLATFORM.appdata_locallow_cognosphere,
+    );
 
     span.record("log_path", log_path.to_str());
 
@@ -97,10 +247,9 @@ impl DataFolderLocator for UnityLogDataFolderLocator {
     }
 
     let keyword = biz.data_folder_name;
-    let keyword_len = keyword.len();
     span.record("keyword", keyword);
 
-    info!("Try to find the keyword from the log file");
+    info!("Try to find the keywords from the log file");
     let log_file = TokioFile::options()
       .read(true)
       .open(&log_path)
@@ -110,44 +259,24 @@ impl DataFolderLocator for UnityLogDataFolderLocator {
         cause,
       })?;
 
+    let mut candidates = Vec::new();
     let log_file_reader = TokioBufReader::new(log_file);
     let mut lines = log_file_reader.lines();
     while let Ok(Some(line)) = lines.next_line().await {
-      if line.len() <= keyword_len || !line.contains(keyword) {
-        continue;
-      }
-
-      if let Some(colon) = line.rfind(':') {
-        if let Some(end) = line.find(keyword) {
-          // &line[colon - 1..end + keyword_len]; // UNSAFE!
-          if let Some(slice) = line.get(colon - 1..end + keyword_len) {
-            let data_folder = PathBuf::from(slice);
-            info!("Located in the data folder: {data_folder:?}");
-
-            // Test this data folder for validity
-            match data_folder.parent() {
-              None => return Err(DataFolderErrorKind::Invalid)?,
-              Some(parent) => {
-                let executable_file = biz.join_executable_file(parent);
-                if !executable_file.is_file() {
-                  warn!("No valid game executable file found: {executable_file:?}");
-                  return Err(DataFolderErrorKind::Invalid)?;
-                }
-              }
-            };
-
-            return Ok(DataFolder {
-              business,
-              region,
-              value: data_folder,
-            });
-          }
-        }
+      if let Some(slice) = extract_unity_log_data_folder(&line, keyword) {
+        candidates.push(DataFolderCandidate::new(
+          business,
+          region,
+          PathBuf::from(slice),
+          DataFolderLocatorKind::UnityLog,
+        ));
       }
     }
 
-    warn!("Not locating the data folder from the keyword");
-    Err(DataFolderErrorKind::Vacant)?
+    let candidates = DataFolderCandidate::rank(candidates);
+    info!("Candidate data folders found: {}", candidates.len());
+
+    Ok(candidates)
   }
 }
 
@@ -216,10 +345,325 @@ impl DataFolderLocator for ManualDataFolderLocator {
       value: maybe_data_folder,
     })
   }
+
+  async fn locate_data_folders(
+    &self,
+    business: Business,
+    region: BusinessRegion,
+  ) -> Result<Vec<DataFolderCandidate>, DataFolderError> {
+    let data_folder = self.locate_data_folder(business, region).await?;
+    Ok(vec![DataFolderCandidate::new(
+      business,
+      region,
+      data_folder.value,
+      DataFolderLocatorKind::Manual,
+    )])
+  }
 }
 
 // endregion
 
+// region: Wine prefix
+
+cfg_if! {if #[cfg(target_os = "linux")] {
+  use std::fs;
+  use std::sync::LazyLock;
+
+  use regex::Regex;
+
+  // libraryfolders.vdf: "path"    "/mnt/games/SteamLibrary"
+  static REGEX_STEAM_LIBRARY_PATH: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""path"\s+"(?P<path>[^"]+)""#).unwrap());
+
+  /// Locate the data folders of the games running in the Wine prefixes.
+  /// (Wine, Steam Proton, Lutris and anime-game-launcher)
+  #[derive(Debug, Deserialize)]
+  pub struct WinePrefixDataFolderLocator;
+
+  impl WinePrefixDataFolderLocator {
+    /// Maximum depth of the install directory to search for the game folder.
+    const MAX_SEARCH_DEPTH: usize = 4;
+
+    /// Data folders of the anime-game-launcher family in `$XDG_DATA_HOME`.
+    const LAUNCHERS: [&'static str; 4] = [
+      "anime-game-launcher",      // Genshin Impact
+      "honkers-railway-launcher", // Honkai: Star Rail
+      "sleepy-launcher",          // Zenless Zone Zero
+      "anime-games-launcher",
+    ];
+
+    /// Wine prefixes that may contain the game.
+    fn wine_prefixes() -> Vec<PathBuf> {
+      let platform = &*consts::PLATFORM;
+      let mut prefixes = vec![
+        platform.wine_prefix.clone(),
+        platform.user_home.join(".wine"),
+      ];
+
+      // Winetricks: $XDG_DATA_HOME/wineprefixes/<name>
+      prefixes.extend(read_subfolders(platform.appdata_local.join("wineprefixes")));
+
+      // Steam Proton: steamapps/compatdata/<appid>/pfx
+      for library in Self::steam_libraries() {
+        let compatdata = library.join("steamapps").join("compatdata");
+        prefixes.extend(
+          read_subfolders(compatdata)
+            .into_iter()
+            .map(|appid| appid.join("pfx")),
+        );
+      }
+
+      // Lutris: ~/Games/<game>
+      prefixes.extend(read_subfolders(platform.user_home.join("Games")));
+
+      // anime-game-launcher: $XDG_DATA_HOME/<launcher>/prefix
+      prefixes.extend(
+        Self::launchers()
+          .into_iter()
+          .map(|launcher| launcher.join("prefix")),
+      );
+
+      prefixes.retain(|prefix| prefix.join("drive_c").is_dir());
+      dedup(&mut prefixes);
+      prefixes
+    }
+
+    /// Steam installations and their additional libraries.
+    fn steam_libraries() -> Vec<PathBuf> {
+      let platform = &*consts::PLATFORM;
+      let mut libraries = Vec::new();
+
+      for steam in [
+        platform.user_home.join(".steam").join("steam"),
+        platform.appdata_local.join("Steam"),
+        platform
+          .user_home
+          .join(".var/app/com.valvesoftware.Steam/.local/share/Steam"), // Flatpak
+      ] {
+        if !steam.is_dir() {
+          continue;
+        }
+
+        let library_folders = steam.join("steamapps").join("libraryfolders.vdf");
+        if let Ok(content) = fs::read_to_string(library_folders) {
+          libraries.extend(
+            REGEX_STEAM_LIBRARY_PATH
+              .captures_iter(&content)
+              .map(|captures| PathBuf::from(&captures["path"])),
+          );
+        }
+
+        libraries.push(steam);
+      }
+
+      libraries.retain(|library| library.is_dir());
+      dedup(&mut libraries);
+      libraries
+    }
+
+    fn launchers() -> Vec<PathBuf> {
+      Self::LAUNCHERS
+        .iter()
+        .map(|launcher| consts::PLATFORM.appdata_local.join(launcher))
+        .filter(|launcher| launcher.is_dir())
+        .collect()
+    }
+
+    /// Directories where the game may be installed.
+    fn install_directories(prefixes: &[PathBuf]) -> Vec<PathBuf> {
+      let mut directories = Vec::new();
+
+      for prefix in prefixes {
+        let drive_c = prefix.join("drive_c");
+        directories.push(drive_c.join("Program Files"));
+        directories.push(drive_c.join("Program Files (x86)"));
+      }
+
+      for library in Self::steam_libraries() {
+        directories.push(library.join("steamapps").join("common"));
+      }
+
+      // Lutris: ~/Games
+      directories.push(consts::PLATFORM.user_home.join("Games"));
+
+      // anime-game-launcher installs the game in its own data folder
+      directories.extend(Self::launchers());
+
+      directories.retain(|directory| directory.is_dir());
+      dedup(&mut directories);
+      directories
+    }
+
+    /// Search the `<data_folder_name>` folders next to the game executable.
+    fn search_data_folders(
+      biz: &BizInternals,
+      directory: &Path,
+      depth: usize,
+      data_folders: &mut Vec<PathBuf>,
+    ) {
+      let data_folder = directory.join(biz.data_folder_name);
+      if data_folder.is_dir() && biz.join_executable_file(directory).is_file() {
+        data_folders.push(data_folder);
+        return;
+      }
+
+      if depth == 0 {
+        return;
+      }
+
+      let Ok(entries) = fs::read_dir(directory) else {
+        return;
+      };
+
+      for entry in entries.filter_map(Result::ok) {
+        // Don't follow the symbolic links. e.g. `dosdevices/z:` -> `/`
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+          Self::search_data_folders(biz, &entry.path(), depth - 1, data_folders);
+        }
+      }
+    }
+
+    /// Data folders in the Unity log of the Wine prefix, mapped to the host paths.
+    async fn read_unity_log_data_folders(biz: &BizInternals, wine_prefix: &Path) -> Vec<PathBuf> {
+      let appdata_locallow = consts::Platform::wine_appdata_locallow(wine_prefix);
+      let log_path = unity_log_path(
+        biz,
+        &appdata_locallow.join(consts::Platform::FOLDER_MIHOYO),
+        &appdata_locallow.join(consts::Platform::FOLDER_COGNOSPHERE),
+      );
+
+      let Ok(log_file) = TokioFile::open(&log_path).await else {
+        return Vec::new();
+      };
+
+      let mut data_folders = Vec::new();
+      let mut lines = TokioBufReader::new(log_file).lines();
+      while let Ok(Some(line)) = lines.next_line().await {
+        if let Some(data_folder) = extract_unity_log_data_folder(&line, biz.data_folder_name)
+          .and_then(|windows_path| wine_path_to_host(wine_prefix, windows_path))
+        {
+          data_folders.push(data_folder);
+        }
+      }
+
+      dedup(&mut data_folders);
+      data_folders
+    }
+  }
+
+  #[async_trait]
+  impl DataFolderLocator for WinePrefixDataFolderLocator {
+    async fn locate_data_folder(
+      &self,
+      business: Business,
+      region: BusinessRegion,
+    ) -> Result<DataFolder, DataFolderError> {
+      select_data_folder(self.locate_data_folders(business, region).await?)
+    }
+
+    #[tracing::instrument]
+    async fn locate_data_folders(
+      &self,
+      business: Business,
+      region: BusinessRegion,
+    ) -> Result<Vec<DataFolderCandidate>, DataFolderError> {
+      info!("Locating the data folders in the Wine prefixes...");
+
+      let biz = BizInternals::mapped(business, region);
+      let prefixes = tokio::task::spawn_blocking(Self::wine_prefixes)
+        .await
+        .unwrap_or_default();
+
+      info!("Wine prefixes found: {prefixes:?}");
+
+      // Data folders in the Unity logs of each prefix first,
+      // then the data folders searched in the install directories.
+      let mut data_folders = Vec::new();
+      for prefix in &prefixes {
+        data_folders.extend(Self::read_unity_log_data_folders(biz, prefix).await);
+      }
+
+      data_folders.extend(
+        tokio::task::spawn_blocking(move || {
+          let mut data_folders = Vec::new();
+          for directory in Self::install_directories(&prefixes) {
+            Self::search_data_folders(
+              biz,
+              &directory,
+              Self::MAX_SEARCH_DEPTH,
+              &mut data_folders,
+            );
+          }
+          data_folders
+        })
+        .await
+        .unwrap_or_default(),
+      );
+
+      let candidates = DataFolderCandidate::rank(
+        data_folders
+          .into_iter()
+          .map(|data_folder| {
+            DataFolderCandidate::new(
+              business,
+              region,
+              data_folder,
+              DataFolderLocatorKind::WinePrefix,
+            )
+          })
+          .collect(),
+      );
+
+      info!("Candidate data folders found: {}", candidates.len());
+      Ok(candidates)
+    }
+  }
+
+  /// Map the Windows path inside the Wine prefix back to the host path.
+  ///
+  /// - `Z:\home\user\Games\...` -> `/home/user/Games/...`
+  /// - `C:\Program Files\...`   -> `<prefix>/drive_c/Program Files/...`
+  fn wine_path_to_host(wine_prefix: &Path, windows_path: &str) -> Option<PathBuf> {
+    let mut chars = windows_path.chars();
+    let drive = chars
+      .next()
+      .filter(char::is_ascii_alphabetic)?
+      .to_ascii_lowercase();
+    let rest = chars.as_str().strip_prefix(':')?;
+
+    // Drive letters are the symbolic links in the `dosdevices` folder
+    let mut host = fs::canonicalize(wine_prefix.join("dosdevices").join(format!("{drive}:")))
+      .ok()
+      .or_else(|| match drive {
+        'c' => Some(wine_prefix.join("drive_c")),
+        'z' => Some(PathBuf::from("/")),
+        _ => None,
+      })?;
+
+    host.extend(rest.split(['\\', '/']).filter(|component| !component.is_empty()));
+    Some(host)
+  }
+
+  fn read_subfolders(folder: impl AsRef<Path>) -> Vec<PathBuf> {
+    fs::read_dir(folder)
+      .map(|entries| {
+        entries
+          .filter_map(Result::ok)
+          .map(|entry| entry.path())
+          .filter(|path| path.is_dir())
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+
+  fn dedup(paths: &mut Vec<PathBuf>) {
+    let mut seen = HashSet::with_capacity(paths.len());
+    paths.retain(|path| seen.insert(path.clone()));
+  }
+}}
+
+// endregion
+
 // region: Registry
 
 cfg_if! {if #[cfg(windows)] {
@@ -235,7 +679,16 @@ cfg_if! {if #[cfg(windows)] {
       region: BusinessRegion,
     ) -> Result<DataFolder, DataFolderError> {
       // TODO: Locating data folder in the Registry
-      unimplemented!()
+      Err(DataFolderErrorKind::Unsupported)?
+    }
+
+    async fn locate_data_folders(
+      &self,
+      _business: Business,
+      _region: BusinessRegion,
+    ) -> Result<Vec<DataFolderCandidate>, DataFolderError> {
+      // TODO: Locating data folders in the Registry
+      Err(DataFolderErrorKind::Unsupported)?
     }
   }
 }}
@@ -248,6 +701,19 @@ pub enum DataFolderLocatorFactory {
   Manual(ManualDataFolderLocator),
   #[cfg(windows)]
   Registry(RegistryDataFolderLocator),
+  #[cfg(target_os = "linux")]
+  WinePrefix(WinePrefixDataFolderLocator),
+}
+
+macro_rules! proxies {
+  ($self:ident.$method:ident $args:tt; $($(#[$meta:meta])? $t:ident),*) => {
+    match $self {
+      $(
+        $(#[$meta])?
+        Self::$t(inner) => inner.$method $args.await,
+      )*
+    }
+  };
 }
 
 #[async_trait]
@@ -257,16 +723,116 @@ impl DataFolderLocator for DataFolderLocatorFactory {
     business: Business,
     region: BusinessRegion,
   ) -> Result<DataFolder, DataFolderError> {
-    macro_rules! proxies {
-      ($($t:ident),*) => {
-        match self {
-          $(
-            Self::$t(inner) => inner.locate_data_folder(business, region).await,
-          )*
-        }
-      }
+    proxies! {
+      self.locate_data_folder(business, region);
+      UnityLog,
+      Manual,
+      #[cfg(windows)] Registry,
+      #[cfg(target_os = "linux")] WinePrefix
+    }
+  }
+
+  async fn locate_data_folders(
+    &self,
+    business: Business,
+    region: BusinessRegion,
+  ) -> Result<Vec<DataFolderCandidate>, DataFolderError> {
+    proxies! {
+      self.locate_data_folders(business, region);
+      UnityLog,
+      Manual,
+      #[cfg(windows)] Registry,
+      #[cfg(target_os = "linux")] WinePrefix
     }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::str::FromStr;
+
+  use super::*;
+
+  #[test]
+  fn test_extract_unity_log_data_folder() {
+    assert_eq!(
+      extract_unity_log_data_folder(
+        "[Subsystems] Discovering subsystems at path D:/Games/Genshin Impact game/YuanShen_Data/UnitySubsystems",
+        "YuanShen_Data"
+      ),
+      Some("D:/Games/Genshin Impact game/YuanShen_Data")
+    );
+
+    assert_eq!(
+      extract_unity_log_data_folder(
+        r"Z:\home\user\Games\Star Rail\Game\StarRail_Data\Plugins",
+        "StarRail_Data"
+      ),
+      Some(r"Z:\home\user\Games\Star Rail\Game\StarRail_Data")
+    );
+
+    assert_eq!(
+      extract_unity_log_data_folder("Loading YuanShen_Data/Managed", "YuanShen_Data"),
+      None
+    );
+    assert_eq!(
+      extract_unity_log_data_folder("Nothing", "YuanShen_Data"),
+      None
+    );
+  }
+
+  #[test]
+  fn test_rank_data_folder_candidates() {
+    let candidate =
+      |value: &str, executable, webcaches_version, last_modified| DataFolderCandidate {
+        data_folder: DataFolder {
+          business: Business::GenshinImpact,
+          region: BusinessRegion::Official,
+          value: PathBuf::from(value),
+        },
+        executable,
+        last_modified: Some(OffsetDateTime::from_unix_timestamp(last_modified).unwrap()),
+        webcaches_version,
+        locator: DataFolderLocatorKind::UnityLog,
+      };
+
+    let version = |v: &str| Some(WebCachesVersion::from_str(v).unwrap());
+
+    let ranked = DataFolderCandidate::rank(vec![
+      candidate("stale", false, version("2.30.0.0"), 3),
+      candidate("old", true, version("2.9.0.0"), 2),
+      candidate("new", true, version("2.30.0.0"), 1),
+      candidate("touched", true, version("2.30.0.0"), 4),
+      candidate("new", true, None, 5),
+    ]);
+
+    assert_eq!(
+      ranked
+        .iter()
+        .map(|candidate| candidate.data_folder.value.to_str().unwrap())
+        .collect::<Vec<_>>(),
+      vec!["touched", "new", "old", "stale"]
+    );
+  }
 
-    proxies! { UnityLog, Manual, Registry }
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn test_wine_path_to_host() {
+    let wine_prefix = Path::new("/nonexistent/pfx");
+
+    assert_eq!(
+      wine_path_to_host(wine_prefix, r"Z:\home\user\Games\YuanShen_Data"),
+      Some(PathBuf::from("/home/user/Games/YuanShen_Data"))
+    );
+
+    assert_eq!(
+      wine_path_to_host(wine_prefix, "C:/Program Files/Genshin Impact/YuanShen_Data"),
+      Some(PathBuf::from(
+        "/nonexistent/pfx/drive_c/Program Files/Genshin Impact/YuanShen_Data"
+      ))
+    );
+
+    assert_eq!(wine_path_to_host(wine_prefix, r"D:\Games"), None);
+    assert_eq!(wine_path_to_host(wine_prefix, "/home/user"), None);
   }
 }
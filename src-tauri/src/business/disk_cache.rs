@@ -16,12 +16,20 @@
 // This implementation is Disk Cache Version 2.1, not 3.0.
 // Because 'Genshin Impact', 'Honkai: Star Rail' and 'Zenless Zone Zero' are both version 2.1.
 //
+// Newer embedded browsers may store the cache in the 'Simple Cache' backend instead.
+//   References:
+//     https://www.chromium.org/developers/design-documents/network-stack/disk-cache/very-simple-backend/
+//     https://github.com/chromium/chromium/blob/main/net/disk_cache/simple/simple_entry_format.h
+//     https://github.com/chromium/chromium/blob/main/net/disk_cache/simple/simple_index_file.cc
+//
 
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::fmt::{Debug, Formatter};
 use std::fs::File;
 use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use paste::paste;
 
@@ -177,7 +185,6 @@ trait DiskCacheRead: Read {
     Ok(buf)
   }
 
-  #[allow(unused)]
   fn read_u8_vec(&mut self, length: usize) -> Result<Vec<u8>> {
     let mut vec = vec![0; length];
     self.read_exact(&mut vec)?;
@@ -452,6 +459,18 @@ impl BlockFile {
   }
 }
 
+impl BlockFile {
+  /// Scan the raw block data for the strings separated by the zero bytes.
+  /// This is used to salvage the keys when the index file is unusable.
+  pub fn scan_strings(&self) -> impl Iterator<Item = Cow<'_, str>> {
+    self
+      .data
+      .split(|byte| *byte == 0)
+      .filter(|data| !data.is_empty())
+      .map(String::from_utf8_lossy)
+  }
+}
+
 // ===========
 // Entry Store
 // ===========
@@ -539,7 +558,7 @@ impl EntryStore {
     }
   }
 
-  pub fn read_long_key<'a>(&self, block_file: &'a BlockFile) -> Result<Cow<'a, str>> {
+  pub fn read_long_key(&self, block_files: &mut BlockFiles) -> Result<String> {
     if !self.has_long_key() {
       return Err(Error::new(
         ErrorKind::Unsupported,
@@ -547,16 +566,525 @@ impl EntryStore {
       ));
     }
 
-    let long_key_data = block_file.read_data(&self.long_key)?;
-    let data = &long_key_data[..self.key_len as usize];
-    Ok(String::from_utf8_lossy(data))
+    let long_key_data = block_files.read_data(&self.long_key)?;
+    let key_len = self.key_len as usize;
+
+    // HACK: Avoid index out-of-bounds caused by illegal key length
+    if let Some(data) = long_key_data.get(..key_len) {
+      Ok(String::from_utf8_lossy(data).into_owned())
+    } else {
+      Err(Error::new(
+        ErrorKind::InvalidData,
+        format!(
+          "Illegal key length: {key_len}, Long key data length: {}",
+          long_key_data.len()
+        ),
+      ))
+    }
   }
 }
 
-impl BlockFile {
-  pub fn read_entry_store(&self, addr: &CacheAddr) -> Result<EntryStore> {
+// ===========
+// Block Files
+// ===========
+
+/// Block files `data_N` and separate files `f_XXXXXX` of the cache folder.
+/// Block files are opened lazily on demand by the file number of the address.
+pub struct BlockFiles {
+  folder: PathBuf,
+  files: HashMap<u32, BlockFile>,
+}
+
+impl Debug for BlockFiles {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("BlockFiles")
+      .field("folder", &self.folder)
+      .field("files", &self.files.keys())
+      .finish()
+  }
+}
+
+impl BlockFiles {
+  pub fn new(folder: impl Into<PathBuf>) -> Self {
+    Self {
+      folder: folder.into(),
+      files: HashMap::new(),
+    }
+  }
+
+  /// Path of the file that the address points to.
+  pub fn file_path(&self, addr: &CacheAddr) -> PathBuf {
+    if addr.is_separate_file() {
+      self.folder.join(format!("f_{:06x}", addr.file_number()))
+    } else {
+      self.folder.join(format!("data_{}", addr.file_number()))
+    }
+  }
+
+  pub fn block_file(&mut self, file_number: u32) -> Result<&BlockFile> {
+    match self.files.entry(file_number) {
+      Entry::Occupied(entry) => Ok(entry.into_mut()),
+      Entry::Vacant(entry) => {
+        let block_file = BlockFile::from_file(self.folder.join(format!("data_{file_number}")))?;
+        Ok(entry.insert(block_file))
+      }
+    }
+  }
+
+  pub fn read_data(&mut self, addr: &CacheAddr) -> Result<Cow<'_, [u8]>> {
+    if !addr.is_initialized() {
+      return Err(Error::new(ErrorKind::InvalidInput, "Invalid address"));
+    }
+
+    if addr.is_separate_file() {
+      // The whole separate file is the data
+      let mut data = Vec::new();
+      File::open(self.file_path(addr))?.read_to_end(&mut data)?;
+      Ok(Cow::Owned(data))
+    } else {
+      self
+        .block_file(addr.file_number())?
+        .read_data(addr)
+        .map(Cow::Borrowed)
+    }
+  }
+
+  pub fn read_entry_store(&mut self, addr: &CacheAddr) -> Result<EntryStore> {
     let data = self.read_data(addr)?;
-    let entry = EntryStore::from_reader(data)?;
+    let entry = EntryStore::from_reader(&*data)?;
     Ok(entry)
   }
 }
+
+// =================
+// Disk Cache Format
+// =================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiskCacheFormat {
+  /// `index`, `data_1`, `data_2`, `data_3`, `data_4` and `f_XXXXXX`
+  BlockFile,
+  /// `index`, `index-dir/the-real-index` and `<hash>_0`
+  Simple,
+}
+
+impl DiskCacheFormat {
+  /// Detect the format by the magic number of the `index` file in the cache folder.
+  pub fn detect(cache_folder: impl AsRef<Path>) -> Result<Self> {
+    let mut index_file = File::open(cache_folder.as_ref().join("index"))?;
+    let magic = index_file.read_u64()?;
+
+    if magic as u32 == INDEX_MAGIC {
+      Ok(Self::BlockFile)
+    } else if magic == SIMPLE_INITIAL_MAGIC {
+      Ok(Self::Simple)
+    } else {
+      Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("Unknown disk cache format of the index file magic number: 0x{magic:X}"),
+      ))
+    }
+  }
+}
+
+// =================
+// Simple Index File
+// =================
+
+const SIMPLE_INITIAL_MAGIC: u64 = 0xFCFB6D1BA7725C30;
+const SIMPLE_INDEX_MAGIC: u64 = 0x656E74657220796F;
+const SIMPLE_INDEX_MIN_VERSION: u32 = 6;
+const SIMPLE_INDEX_MAX_VERSION: u32 = 9;
+const SIMPLE_INDEX_VERSION_WITH_REASON: u32 = 7;
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct SimpleIndexHeader {
+  pub magic: u64,
+  pub version: u32,
+  pub entry_count: u64,
+  pub cache_size: u64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct SimpleIndexEntry {
+  pub hash: u64,
+  pub last_used_time: i64,
+  pub entry_size: u64,
+}
+
+impl SimpleIndexEntry {
+  /// File name of the entry, which contains the key and the stream 0 and 1.
+  pub fn file_name(&self) -> String {
+    format!("{:016x}_0", self.hash)
+  }
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct SimpleIndexFile {
+  pub header: SimpleIndexHeader,
+  pub entries: Vec<SimpleIndexEntry>,
+}
+
+impl SimpleIndexFile {
+  pub fn from_reader(mut reader: impl Read) -> Result<Self> {
+    // Pickle header: payload size and crc32 of the payload
+    // HACK: Useless
+    let _payload_size = reader.read_u32()?;
+    let _crc = reader.read_u32()?;
+
+    let magic = reader.read_u64()?;
+    if magic != SIMPLE_INDEX_MAGIC {
+      return Err(Error::new(
+        ErrorKind::InvalidData,
+        format!(
+          "Invalid simple index file magic number: 0x{magic:X} (Expected: 0x{SIMPLE_INDEX_MAGIC:X})"
+        ),
+      ));
+    }
+
+    let version = reader.read_u32()?;
+    if !(SIMPLE_INDEX_MIN_VERSION..=SIMPLE_INDEX_MAX_VERSION).contains(&version) {
+      return Err(Error::new(
+        ErrorKind::InvalidData,
+        format!(
+          "Unsupported simple index file version: {version} (Valid: {SIMPLE_INDEX_MIN_VERSION}..={SIMPLE_INDEX_MAX_VERSION})"
+        ),
+      ));
+    }
+
+    let entry_count = reader.read_u64()?;
+    let cache_size = reader.read_u64()?;
+
+    if version >= SIMPLE_INDEX_VERSION_WITH_REASON {
+      // HACK: Useless. Reason of the index write
+      let _reason = reader.read_u32()?;
+    }
+
+    // HACK: Avoid allocating by the corrupted entry count
+    let mut entries = Vec::with_capacity(entry_count.min(u16::MAX as u64) as usize);
+    for _ in 0..entry_count {
+      let hash = reader.read_u64()?;
+      let last_used_time = reader.read_i64()?;
+      let entry_size = reader.read_u64()?;
+      entries.push(SimpleIndexEntry {
+        hash,
+        last_used_time,
+        entry_size,
+      });
+    }
+
+    Ok(Self {
+      header: SimpleIndexHeader {
+        magic,
+        version,
+        entry_count,
+        cache_size,
+      },
+      entries,
+    })
+  }
+
+  pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+    let file = File::open(path)?;
+    Self::from_reader(file)
+  }
+}
+
+// =================
+// Simple Entry File
+// =================
+
+const SIMPLE_ENTRY_VERSION: u32 = 5;
+const SIMPLE_ENTRY_MAX_KEY_LENGTH: u32 = 1024 * 1024;
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct SimpleEntryHeader {
+  pub magic: u64,
+  pub version: u32,
+  pub key_length: u32,
+  pub key_hash: u32,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct SimpleEntryFile {
+  pub header: SimpleEntryHeader,
+  pub key: Vec<u8>,
+  // HACK: Useless. See below
+  // pub stream_1: [u8],
+  // pub eof_1: SimpleFileEOF,
+  // pub stream_0: [u8],
+  // pub eof_0: SimpleFileEOF,
+}
+
+impl SimpleEntryFile {
+  pub fn from_reader(mut reader: impl Read) -> Result<Self> {
+    let magic = reader.read_u64()?;
+    if magic != SIMPLE_INITIAL_MAGIC {
+      return Err(Error::new(
+        ErrorKind::InvalidData,
+        format!(
+          "Invalid simple entry file magic number: 0x{magic:X} (Expected: 0x{SIMPLE_INITIAL_MAGIC:X})"
+        ),
+      ));
+    }
+
+    let version = reader.read_u32()?;
+    if version != SIMPLE_ENTRY_VERSION {
+      return Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("Unsupported simple entry file version: {version} (Valid: {SIMPLE_ENTRY_VERSION})"),
+      ));
+    }
+
+    let key_length = reader.read_u32()?;
+    let key_hash = reader.read_u32()?;
+
+    // HACK: Useless. Unused padding
+    let _padding = reader.read_u32()?;
+
+    // HACK: Avoid allocating by the corrupted key length
+    if key_length > SIMPLE_ENTRY_MAX_KEY_LENGTH {
+      return Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("Simple entry file key is too long: {key_length}"),
+      ));
+    }
+
+    let key = reader.read_u8_vec(key_length as usize)?;
+
+    Ok(Self {
+      header: SimpleEntryHeader {
+        magic,
+        version,
+        key_length,
+        key_hash,
+      },
+      key,
+    })
+  }
+
+  pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+    let file = File::open(path)?;
+    Self::from_reader(file)
+  }
+
+  pub fn read_key(&self) -> Cow<'_, str> {
+    String::from_utf8_lossy(&self.key)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use super::*;
+
+  fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+      .join("fixtures")
+      .join("disk_cache")
+      .join(name)
+  }
+
+  #[test]
+  fn test_detect_disk_cache_format() {
+    assert_eq!(
+      DiskCacheFormat::detect(fixture("blockfile")).unwrap(),
+      DiskCacheFormat::BlockFile
+    );
+
+    assert_eq!(
+      DiskCacheFormat::detect(fixture("simple")).unwrap(),
+      DiskCacheFormat::Simple
+    );
+
+    assert_eq!(
+      DiskCacheFormat::detect(fixture("nonexistent"))
+        .unwrap_err()
+        .kind(),
+      ErrorKind::NotFound
+    );
+  }
+
+  #[test]
+  fn test_block_file_long_key() {
+    let index_file = IndexFile::from_file(fixture("blockfile").join("index")).unwrap();
+    let mut block_files = BlockFiles::new(fixture("blockfile"));
+
+    assert_eq!(index_file.table.len(), 6);
+
+    let short_key_entry = block_files.read_entry_store(&index_file.table[1]).unwrap();
+    assert!(!short_key_entry.has_long_key());
+    assert_eq!(
+      short_key_entry.read_key().unwrap(),
+      "1/0/https://webstatic.mihoyo.com/hk4e/event/e20190909gacha-v3/index.html"
+    );
+
+    // Long keys in: data_2, data_3, f_000001
+    for (addr, gacha_type, file_name) in [
+      (&index_file.table[0], 301, "data_2"),
+      (&index_file.table[2], 302, "data_3"),
+      (&index_file.table[3], 200, "f_000001"),
+    ] {
+      let entry_store = block_files.read_entry_store(addr).unwrap();
+      assert!(entry_store.has_long_key());
+      assert_eq!(
+        block_files.file_path(&entry_store.long_key),
+        fixture("blockfile").join(file_name)
+      );
+
+      let long_key = entry_store.read_long_key(&mut block_files).unwrap();
+      assert!(long_key.starts_with("1/0/https://public-operation-hk4e.mihoyo.com/"));
+      assert!(long_key.contains(&format!("&gacha_type={gacha_type}&")));
+    }
+
+    // Illegal start block, and the block file data_5 does not exist
+    assert_eq!(
+      block_files
+        .read_entry_store(&index_file.table[4])
+        .unwrap_err()
+        .kind(),
+      ErrorKind::InvalidInput
+    );
+    assert_eq!(
+      block_files
+        .read_entry_store(&index_file.table[5])
+        .unwrap_err()
+        .kind(),
+      ErrorKind::NotFound
+    );
+  }
+
+  #[test]
+  fn test_block_files_lazy_open() {
+    let index_file = IndexFile::from_file(fixture("blockfile").join("index")).unwrap();
+    let mut block_files = BlockFiles::new(fixture("blockfile"));
+
+    let opened = |block_files: &BlockFiles| {
+      let mut file_numbers = block_files.files.keys().copied().collect::<Vec<_>>();
+      file_numbers.sort();
+      file_numbers
+    };
+
+    assert!(opened(&block_files).is_empty());
+
+    // Entry stores are in data_1, and the long key is in the separate file f_000001,
+    // which is read directly and never cached as a block file.
+    let entry_store = block_files.read_entry_store(&index_file.table[3]).unwrap();
+    assert_eq!(opened(&block_files), vec![1]);
+    assert!(entry_store.long_key.is_separate_file());
+    entry_store.read_long_key(&mut block_files).unwrap();
+    assert_eq!(opened(&block_files), vec![1]);
+
+    // Opened once, and reused by the next reads
+    for addr in [&index_file.table[0], &index_file.table[2]] {
+      let entry_store = block_files.read_entry_store(addr).unwrap();
+      entry_store.read_long_key(&mut block_files).unwrap();
+    }
+    assert_eq!(opened(&block_files), vec![1, 2, 3]);
+
+    // Missing block file is not cached
+    assert!(block_files.read_entry_store(&index_file.table[5]).is_err());
+    assert!(block_files.block_file(5).is_err());
+    assert_eq!(opened(&block_files), vec![1, 2, 3]);
+  }
+
+  #[test]
+  fn test_block_files_out_of_range() {
+    let index_file = IndexFile::from_file(fixture("blockfile").join("index")).unwrap();
+    let mut block_files = BlockFiles::new(fixture("blockfile"));
+
+    // data_2 has only 4 blocks of 256 bytes: Initialized | Block 256 | data_2 | Start block 4
+    assert_eq!(
+      block_files
+        .read_data(&CacheAddr(0xA002_0004))
+        .unwrap_err()
+        .kind(),
+      ErrorKind::InvalidInput
+    );
+
+    // Uninitialized address
+    assert_eq!(
+      block_files
+        .read_data(&CacheAddr(0x2002_0000))
+        .unwrap_err()
+        .kind(),
+      ErrorKind::InvalidInput
+    );
+
+    // Key length is longer than the long key data, both in the block file and the separate file
+    for addr in [&index_file.table[0], &index_file.table[3]] {
+      let mut entry_store = block_files.read_entry_store(addr).unwrap();
+      let long_key_len = block_files.read_data(&entry_store.long_key).unwrap().len();
+
+      entry_store.key_len = long_key_len as i32 + 1;
+      assert_eq!(
+        entry_store
+          .read_long_key(&mut block_files)
+          .unwrap_err()
+          .kind(),
+        ErrorKind::InvalidData
+      );
+    }
+
+    // Short key entry does not have a long key
+    let short_key_entry = block_files.read_entry_store(&index_file.table[1]).unwrap();
+    assert_eq!(
+      short_key_entry
+        .read_long_key(&mut block_files)
+        .unwrap_err()
+        .kind(),
+      ErrorKind::Unsupported
+    );
+  }
+
+  #[test]
+  fn test_block_file_scan_strings() {
+    assert_eq!(
+      IndexFile::from_file(fixture("blockfile_salvage").join("index"))
+        .unwrap_err()
+        .kind(),
+      ErrorKind::InvalidData
+    );
+
+    let block_file2 = BlockFile::from_file(fixture("blockfile_salvage").join("data_2")).unwrap();
+    let strings = block_file2.scan_strings().collect::<Vec<_>>();
+
+    assert_eq!(strings.len(), 1);
+    assert!(strings[0].starts_with("1/0/https://public-operation-hk4e.mihoyo.com/"));
+  }
+
+  #[test]
+  fn test_simple_cache_entries() {
+    let index_file =
+      SimpleIndexFile::from_file(fixture("simple").join("index-dir").join("the-real-index"))
+        .unwrap();
+
+    assert_eq!(index_file.header.version, 9);
+    assert_eq!(index_file.entries.len(), 3);
+
+    let entry_file =
+      SimpleEntryFile::from_file(fixture("simple").join(index_file.entries[0].file_name()))
+        .unwrap();
+
+    assert_eq!(entry_file.header.key_length as usize, entry_file.key.len());
+    assert!(
+      entry_file
+        .read_key()
+        .starts_with("1/0/https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?")
+    );
+
+    // Evicted entry, the file does not exist
+    assert_eq!(
+      SimpleEntryFile::from_file(fixture("simple").join(index_file.entries[2].file_name()))
+        .unwrap_err()
+        .kind(),
+      ErrorKind::NotFound
+    );
+  }
+}
@@ -0,0 +1,342 @@
+use std::sync::{LazyLock, RwLock};
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+use serde::Serialize;
+use tracing::info;
+use url::Url;
+
+use crate::error::declare_error_kinds;
+use crate::models::{
+  BizInternals, Business, BusinessRegion, EndpointOverrides, GachaUrlEndpointOverride,
+};
+use crate::utilities::http_client::http_client;
+
+declare_error_kinds! {
+  #[derive(Debug, thiserror::Error)]
+  EndpointOverridesError {
+    #[error("Invalid base url of the endpoint '{endpoint}': {url} ({reason})")]
+    InvalidBaseUrl {
+      endpoint: String,
+      url: String,
+      reason: String
+    },
+  }
+}
+
+pub const GACHA_METADATA_API_BASE_URL: &str = "https://hoyo-gacha-v1.lgou2w.com/GachaMetadata/v2";
+pub const RELEASE_API_BASE_URL: &str = "https://hoyo-gacha-v1.lgou2w.com/release";
+
+fn gacha_url_endpoint(business: Business, region: Option<BusinessRegion>) -> String {
+  match region {
+    Some(region) => format!("gacha_url.{business}.{region}"),
+    None => format!("gacha_url.{business}"),
+  }
+}
+
+static ENDPOINT_OVERRIDES: LazyLock<RwLock<EndpointOverrides>> = LazyLock::new(Default::default);
+
+// Returns the base url without the trailing slash.
+fn validate_base_url(endpoint: String, url: &str) -> Result<String, EndpointOverridesError> {
+  let invalid = |reason: &str| EndpointOverridesErrorKind::InvalidBaseUrl {
+    endpoint: endpoint.clone(),
+    url: url.to_owned(),
+    reason: reason.to_owned(),
+  };
+
+  let parsed = Url::parse(url).map_err(|error| invalid(&error.to_string()))?;
+
+  if !matches!(parsed.scheme(), "http" | "https") {
+    Err(invalid("Only http or https is supported"))?
+  } else if parsed.host_str().is_none_or(str::is_empty) {
+    Err(invalid("Missing host"))?
+  } else if parsed.query().is_some() || parsed.fragment().is_some() {
+    Err(invalid("Query or fragment is not allowed"))?
+  }
+
+  Ok(url.trim_end_matches('/').to_owned())
+}
+
+impl EndpointOverrides {
+  pub fn validated(self) -> Result<Self, EndpointOverridesError> {
+    let mirror = self
+      .mirror
+      .map(|url| validate_base_url("mirror".into(), &url))
+      .transpose()?;
+
+    let gacha_urls = self
+      .gacha_urls
+      .into_iter()
+      .map(|value| {
+        let endpoint = gacha_url_endpoint(value.business, value.region);
+        Ok(GachaUrlEndpointOverride {
+          base_url: validate_base_url(endpoint, &value.base_url)?,
+          ..value
+        })
+      })
+      .collect::<Result<Vec<_>, EndpointOverridesError>>()?;
+
+    let metadata = self
+      .metadata
+      .map(|url| validate_base_url("metadata".into(), &url))
+      .transpose()?;
+
+    let release = self
+      .release
+      .map(|url| validate_base_url("release".into(), &url))
+      .transpose()?;
+
+    Ok(Self {
+      mirror,
+      gacha_urls,
+      metadata,
+      release,
+    })
+  }
+
+  /// The override of the region takes precedence over the business.
+  /// Then the mirror: `{mirror}/{codename}/getGachaLog`.
+  pub fn base_gacha_url(&self, biz: &BizInternals) -> String {
+    self
+      .gacha_urls
+      .iter()
+      .filter(|value| {
+        value.business == biz.business && value.region.is_none_or(|region| region == biz.region)
+      })
+      .max_by_key(|value| value.region.is_some())
+      .map(|value| value.base_url.clone())
+      .or_else(|| {
+        self
+          .mirror
+          .as_ref()
+          .map(|mirror| format!("{mirror}/{}/getGachaLog", biz.codename))
+      })
+      .unwrap_or_else(|| biz.base_gacha_url.to_owned())
+  }
+
+  /// Then the mirror: `{mirror}/GachaMetadata/v2`.
+  pub fn gacha_metadata_api_base_url(&self) -> String {
+    self
+      .metadata
+      .clone()
+      .or_else(|| {
+        self
+          .mirror
+          .as_ref()
+          .map(|mirror| format!("{mirror}/GachaMetadata/v2"))
+      })
+      .unwrap_or_else(|| GACHA_METADATA_API_BASE_URL.to_owned())
+  }
+
+  /// Then the mirror: `{mirror}/release`.
+  pub fn release_api_base_url(&self) -> String {
+    self
+      .release
+      .clone()
+      .or_else(|| {
+        self
+          .mirror
+          .as_ref()
+          .map(|mirror| format!("{mirror}/release"))
+      })
+      .unwrap_or_else(|| RELEASE_API_BASE_URL.to_owned())
+  }
+}
+
+pub fn endpoint_overrides() -> EndpointOverrides {
+  ENDPOINT_OVERRIDES.read().unwrap().clone()
+}
+
+#[tracing::instrument]
+pub fn set_endpoint_overrides(
+  overrides: EndpointOverrides,
+) -> Result<EndpointOverrides, EndpointOverridesError> {
+  let overrides = overrides.validated()?;
+  info!("Setting the endpoint overrides");
+
+  *ENDPOINT_OVERRIDES.write().unwrap() = overrides.clone();
+  Ok(overrides)
+}
+
+pub fn base_gacha_url(biz: &BizInternals) -> String {
+  ENDPOINT_OVERRIDES.read().unwrap().base_gacha_url(biz)
+}
+
+pub fn gacha_metadata_api_base_url() -> String {
+  ENDPOINT_OVERRIDES
+    .read()
+    .unwrap()
+    .gacha_metadata_api_base_url()
+}
+
+pub fn release_api_base_url() -> String {
+  ENDPOINT_OVERRIDES.read().unwrap().release_api_base_url()
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointConnectivity {
+  pub endpoint: String,
+  pub url: String,
+  // Any http response, regardless of the status
+  pub reachable: bool,
+  pub status: Option<u16>,
+  // Milliseconds
+  pub elapsed: u64,
+  pub error: Option<String>,
+}
+
+/// Check whether the game apis, the metadata and the release hosts can be reached.
+#[tracing::instrument]
+pub async fn test_endpoints_connectivity() -> Vec<EndpointConnectivity> {
+  const TIMEOUT: Duration = Duration::from_secs(10);
+
+  let overrides = endpoint_overrides();
+
+  let mut bizs = BizInternals::all().collect::<Vec<_>>();
+  bizs.sort_by_key(|biz| (biz.business, biz.region));
+
+  let mut endpoints = bizs
+    .into_iter()
+    .map(|biz| {
+      (
+        gacha_url_endpoint(biz.business, Some(biz.region)),
+        overrides.base_gacha_url(biz),
+      )
+    })
+    .collect::<Vec<_>>();
+
+  endpoints.push((
+    "metadata".into(),
+    format!("{}/index.json", overrides.gacha_metadata_api_base_url()),
+  ));
+  endpoints.push((
+    "release".into(),
+    format!("{}/latest", overrides.release_api_base_url()),
+  ));
+
+  let client = http_client();
+  let connectivities = join_all(endpoints.into_iter().map(|(endpoint, url)| {
+    let client = client.clone();
+    async move {
+      let start = Instant::now();
+      let result = client.get(&url).timeout(TIMEOUT).send().await;
+      let elapsed = start.elapsed().as_millis() as u64;
+
+      match result {
+        Ok(response) => EndpointConnectivity {
+          endpoint,
+          url,
+          reachable: true,
+          status: Some(response.status().as_u16()),
+          elapsed,
+          error: None,
+        },
+        Err(error) => EndpointConnectivity {
+          endpoint,
+          url,
+          reachable: false,
+          status: None,
+          elapsed,
+          error: Some(error.without_url().to_string()),
+        },
+      }
+    }
+  }))
+  .await;
+
+  info!(
+    "Reachable endpoints: {}/{}",
+    connectivities
+      .iter()
+      .filter(|value| value.reachable)
+      .count(),
+    connectivities.len()
+  );
+
+  connectivities
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validated() {
+    let overrides = EndpointOverrides {
+      mirror: Some("http://127.0.0.1:8080/".into()),
+      ..Default::default()
+    }
+    .validated()
+    .unwrap();
+
+    assert_eq!(overrides.mirror.as_deref(), Some("http://127.0.0.1:8080"));
+
+    for url in [
+      "127.0.0.1:8080",
+      "ftp://127.0.0.1",
+      "https://example.com/?query",
+      "https://example.com/#fragment",
+    ] {
+      let error = EndpointOverrides {
+        metadata: Some(url.into()),
+        ..Default::default()
+      }
+      .validated()
+      .map_err(|e| e.into_inner());
+
+      assert!(
+        matches!(&error, Err(EndpointOverridesErrorKind::InvalidBaseUrl { endpoint, .. }) if endpoint == "metadata"),
+        "{url}: {error:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn test_resolve_endpoints() {
+    let genshin_official = BizInternals::mapped(Business::GenshinImpact, BusinessRegion::Official);
+    let genshin_global = BizInternals::mapped(Business::GenshinImpact, BusinessRegion::Global);
+    let starrail_official =
+      BizInternals::mapped(Business::HonkaiStarRail, BusinessRegion::Official);
+
+    let defaults = EndpointOverrides::default();
+    assert_eq!(
+      defaults.base_gacha_url(genshin_official),
+      genshin_official.base_gacha_url
+    );
+    assert_eq!(
+      defaults.gacha_metadata_api_base_url(),
+      GACHA_METADATA_API_BASE_URL
+    );
+    assert_eq!(defaults.release_api_base_url(), RELEASE_API_BASE_URL);
+
+    let overrides = EndpointOverrides {
+      mirror: Some("http://mirror".into()),
+      gacha_urls: vec![
+        GachaUrlEndpointOverride {
+          business: Business::GenshinImpact,
+          region: Some(BusinessRegion::Global),
+          base_url: "http://global".into(),
+        },
+        GachaUrlEndpointOverride {
+          business: Business::GenshinImpact,
+          region: None,
+          base_url: "http://genshin".into(),
+        },
+      ],
+      metadata: Some("http://metadata".into()),
+      release: None,
+    };
+
+    assert_eq!(overrides.base_gacha_url(genshin_global), "http://global");
+    assert_eq!(overrides.base_gacha_url(genshin_official), "http://genshin");
+    assert_eq!(
+      overrides.base_gacha_url(starrail_official),
+      "http://mirror/hkrpg_cn/getGachaLog"
+    );
+    assert_eq!(overrides.gacha_metadata_api_base_url(), "http://metadata");
+    assert_eq!(overrides.release_api_base_url(), "http://mirror/release");
+  }
+}
@@ -0,0 +1,439 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use crate::consts;
+use crate::database::{AccountQuestioner, Database, KvMut, SqlxError};
+use crate::models::{Account, AutoSyncSchedule, Business};
+use crate::utilities::progress_sink::NoopProgressSink;
+
+use super::{DirtyGachaUrl, GachaRecordsSyncResult, sync_accounts};
+
+// region: Schedule
+
+static AUTO_SYNC_SCHEDULE: LazyLock<RwLock<AutoSyncSchedule>> = LazyLock::new(Default::default);
+
+// Wake up the scheduler, instead of waiting for the next tick.
+static AUTO_SYNC_SCHEDULE_CHANGED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+pub fn auto_sync_schedule() -> AutoSyncSchedule {
+  AUTO_SYNC_SCHEDULE.read().unwrap().clone()
+}
+
+#[tracing::instrument]
+pub fn set_auto_sync_schedule(schedule: AutoSyncSchedule) -> AutoSyncSchedule {
+  let schedule = schedule.sanitized();
+  info!("Setting the auto sync schedule");
+
+  *AUTO_SYNC_SCHEDULE.write().unwrap() = schedule.clone();
+  AUTO_SYNC_SCHEDULE_CHANGED.notify_one();
+  schedule
+}
+
+// endregion
+
+// region: State
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum AutoSyncTrigger {
+  Interval,
+  WebcachesModified,
+}
+
+/// Last run of the auto sync, persisted in the database and emitted as the event.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AutoSyncState {
+  #[serde(with = "rfc3339::option")]
+  pub last_run: Option<OffsetDateTime>,
+  // The webcaches modified runs do not postpone the interval
+  #[serde(with = "rfc3339::option")]
+  pub last_interval_run: Option<OffsetDateTime>,
+  pub last_trigger: Option<AutoSyncTrigger>,
+  pub last_results: Vec<GachaRecordsSyncResult>,
+  // Milliseconds timestamp of the webcaches modification time of each data folder
+  pub webcaches_modified: HashMap<String, i64>,
+  // Milliseconds timestamp of the last sync of each account, see `account_key`
+  pub last_synced: HashMap<String, i64>,
+}
+
+impl AutoSyncState {
+  fn account_key(business: Business, uid: u32) -> String {
+    format!("{business}_{uid}")
+  }
+
+  fn last_synced(&self, account: &Account) -> Option<OffsetDateTime> {
+    let last = self
+      .last_synced
+      .get(&Self::account_key(account.business, account.uid))?;
+    OffsetDateTime::from_unix_timestamp_nanos(*last as i128 * 1_000_000).ok()
+  }
+
+  // Only the succeeded accounts are not synced again within the minimum interval.
+  fn record_run(
+    &mut self,
+    trigger: AutoSyncTrigger,
+    now: OffsetDateTime,
+    results: Vec<GachaRecordsSyncResult>,
+  ) {
+    for result in results
+      .iter()
+      .filter(|result| result.outcome.is_succeeded())
+    {
+      self.last_synced.insert(
+        Self::account_key(result.business, result.uid),
+        (now.unix_timestamp_nanos() / 1_000_000) as i64,
+      );
+    }
+
+    self.last_run = Some(now);
+    if trigger == AutoSyncTrigger::Interval {
+      self.last_interval_run = Some(now);
+    }
+
+    self.last_trigger = Some(trigger);
+    self.last_results = results;
+  }
+
+  async fn refresh_webcaches_modified(&mut self, accounts: &[Account]) {
+    for account in accounts {
+      if let Ok(Some(modified)) = DirtyGachaUrl::webcaches_modified(&account.data_folder).await {
+        self.webcaches_modified.insert(
+          account.data_folder.clone(),
+          (modified.unix_timestamp_nanos() / 1_000_000) as i64,
+        );
+      }
+    }
+  }
+
+  async fn is_webcaches_modified(&self, account: &Account) -> bool {
+    match DirtyGachaUrl::webcaches_modified(&account.data_folder).await {
+      Ok(Some(modified)) => self
+        .webcaches_modified
+        .get(&account.data_folder)
+        .is_none_or(|last| *last < (modified.unix_timestamp_nanos() / 1_000_000) as i64),
+      _ => false,
+    }
+  }
+}
+
+pub async fn auto_sync_state(database: &Database) -> Result<Option<AutoSyncState>, SqlxError> {
+  match KvMut::from(database, consts::KV_AUTO_SYNC_STATE)
+    .try_read_val_json::<AutoSyncState>()
+    .await?
+  {
+    Some(Ok(state)) => Ok(Some(state)),
+    Some(Err(error)) => {
+      warn!("Invalid auto sync state, discard it: {error}");
+      Ok(None)
+    }
+    None => Ok(None),
+  }
+}
+
+// endregion
+
+// region: Scheduler
+
+const AUTO_SYNC_TICK: Duration = Duration::from_secs(60);
+
+/// Run the incremental syncs of every account on the schedule, until the application exits.
+/// An account is also synced once the webcaches of its data folder are modified,
+/// e.g. the gacha records are opened in the game.
+pub async fn run_auto_sync_scheduler(database: Arc<Database>, app: AppHandle) {
+  info!("Starting the auto sync scheduler...");
+
+  loop {
+    let schedule = auto_sync_schedule();
+    if schedule.enabled {
+      if let Err(error) = auto_sync(&database, &app, &schedule).await {
+        warn!("Error running the auto sync: {error}");
+      }
+    }
+
+    tokio::select! {
+      _ = tokio::time::sleep(AUTO_SYNC_TICK) => {}
+      _ = AUTO_SYNC_SCHEDULE_CHANGED.notified() => {
+        info!("Auto sync schedule has changed");
+      }
+    }
+  }
+}
+
+// Every account is synced on the interval. Otherwise, only the accounts
+// whose webcaches are modified, and not synced within the minimum interval.
+// The accounts are paired with whether their webcaches are modified.
+fn auto_sync_trigger(
+  schedule: &AutoSyncSchedule,
+  state: &AutoSyncState,
+  now: OffsetDateTime,
+  accounts: Vec<(Account, bool)>,
+) -> Option<(AutoSyncTrigger, Vec<Account>)> {
+  let interval = time::Duration::minutes(schedule.interval as i64);
+  if state
+    .last_interval_run
+    .is_none_or(|last_interval_run| last_interval_run + interval <= now)
+  {
+    let accounts = accounts.into_iter().map(|(account, _)| account).collect();
+    return Some((AutoSyncTrigger::Interval, accounts));
+  }
+
+  if !schedule.on_webcaches_modified {
+    return None;
+  }
+
+  let min_interval = time::Duration::minutes(AutoSyncSchedule::MIN_INTERVAL as i64);
+  let modified = accounts
+    .into_iter()
+    .filter(|(account, modified)| {
+      *modified
+        && state
+          .last_synced(account)
+          .is_none_or(|last_synced| last_synced + min_interval <= now)
+    })
+    .map(|(account, _)| account)
+    .collect::<Vec<_>>();
+
+  (!modified.is_empty()).then_some((AutoSyncTrigger::WebcachesModified, modified))
+}
+
+#[tracing::instrument(skip(database, app))]
+async fn auto_sync(
+  database: &Arc<Database>,
+  app: &AppHandle,
+  schedule: &AutoSyncSchedule,
+) -> Result<(), SqlxError> {
+  let mut state = auto_sync_state(database).await?.unwrap_or_default();
+  let now = OffsetDateTime::now_utc().to_offset(*consts::LOCAL_OFFSET);
+  let accounts = AccountQuestioner::find_accounts(database).await?;
+
+  let mut candidates = Vec::with_capacity(accounts.len());
+  for account in accounts {
+    let modified = schedule.on_webcaches_modified && state.is_webcaches_modified(&account).await;
+    candidates.push((account, modified));
+  }
+
+  let Some((trigger, accounts)) = auto_sync_trigger(schedule, &state, now, candidates) else {
+    return Ok(());
+  };
+
+  info!(
+    message = "Auto sync is triggered",
+    ?trigger,
+    accounts = accounts.len()
+  );
+
+  let session = super::GachaUrlSession::from_env();
+  let results = sync_accounts(
+    database,
+    session.transport.clone(),
+    accounts.clone(),
+    &NoopProgressSink,
+  )
+  .await;
+  session.finish("AutoSync");
+
+  state.refresh_webcaches_modified(&accounts).await;
+  state.record_run(trigger, now, results);
+
+  KvMut::from(database, consts::KV_AUTO_SYNC_STATE)
+    .try_write_json(&state)
+    .await
+    .expect("Error serializing auto sync state")?;
+
+  if let Err(error) = app.emit(consts::EVENT_AUTO_SYNC, &state) {
+    warn!("Error emitting the auto sync event: {error}");
+  }
+
+  Ok(())
+}
+
+// endregion
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use time::macros::datetime;
+
+  use super::*;
+  use crate::business::GachaRecordsSyncOutcome;
+
+  fn account(uid: u32) -> Account {
+    Account {
+      business: Business::GenshinImpact,
+      uid,
+      data_folder: format!("data_folder_{uid}"),
+      properties: None,
+    }
+  }
+
+  fn result(uid: u32, outcome: GachaRecordsSyncOutcome) -> GachaRecordsSyncResult {
+    GachaRecordsSyncResult {
+      business: Business::GenshinImpact,
+      uid,
+      outcome,
+    }
+  }
+
+  fn synced(uid: u32) -> GachaRecordsSyncResult {
+    result(uid, GachaRecordsSyncOutcome::Synced { changes: 0 })
+  }
+
+  fn uids(chosen: Option<(AutoSyncTrigger, Vec<Account>)>) -> Option<(AutoSyncTrigger, Vec<u32>)> {
+    chosen.map(|(trigger, accounts)| {
+      let uids = accounts.into_iter().map(|account| account.uid).collect();
+      (trigger, uids)
+    })
+  }
+
+  const SCHEDULE: AutoSyncSchedule = AutoSyncSchedule {
+    enabled: true,
+    interval: 60,
+    on_webcaches_modified: true,
+  };
+
+  #[test]
+  fn test_auto_sync_trigger() {
+    let now = datetime!(2024-01-01 12:00:00 +8);
+
+    // Account 1 is modified, 2 is not, 3 is modified but has just synced
+    let accounts = || {
+      vec![
+        (account(100_000_001), true),
+        (account(100_000_002), false),
+        (account(100_000_003), true),
+      ]
+    };
+
+    let mut state = AutoSyncState::default();
+    state.record_run(
+      AutoSyncTrigger::WebcachesModified,
+      now - time::Duration::minutes(2),
+      vec![synced(100_000_003)],
+    );
+
+    // Never run on the interval
+    assert_eq!(
+      uids(auto_sync_trigger(&SCHEDULE, &state, now, accounts())),
+      Some((
+        AutoSyncTrigger::Interval,
+        vec![100_000_001, 100_000_002, 100_000_003]
+      ))
+    );
+
+    // Interval has elapsed
+    state.last_interval_run = Some(now - time::Duration::minutes(60));
+    assert_eq!(
+      uids(auto_sync_trigger(&SCHEDULE, &state, now, accounts())),
+      Some((
+        AutoSyncTrigger::Interval,
+        vec![100_000_001, 100_000_002, 100_000_003]
+      ))
+    );
+
+    // Within the interval
+    state.last_interval_run = Some(now - time::Duration::minutes(10));
+    assert_eq!(
+      uids(auto_sync_trigger(&SCHEDULE, &state, now, accounts())),
+      Some((AutoSyncTrigger::WebcachesModified, vec![100_000_001]))
+    );
+
+    // Minimum interval of the account has elapsed
+    state.record_run(
+      AutoSyncTrigger::WebcachesModified,
+      now - time::Duration::minutes(AutoSyncSchedule::MIN_INTERVAL as i64),
+      vec![synced(100_000_003)],
+    );
+    assert_eq!(
+      uids(auto_sync_trigger(&SCHEDULE, &state, now, accounts())),
+      Some((
+        AutoSyncTrigger::WebcachesModified,
+        vec![100_000_001, 100_000_003]
+      ))
+    );
+
+    // Nothing is modified
+    let unmodified = accounts()
+      .into_iter()
+      .map(|(account, _)| (account, false))
+      .collect();
+    assert_eq!(
+      uids(auto_sync_trigger(&SCHEDULE, &state, now, unmodified)),
+      None
+    );
+
+    // Webcaches modification is disabled
+    let schedule = AutoSyncSchedule {
+      on_webcaches_modified: false,
+      ..SCHEDULE
+    };
+    assert_eq!(
+      uids(auto_sync_trigger(&schedule, &state, now, accounts())),
+      None
+    );
+  }
+
+  #[test]
+  fn test_auto_sync_trigger_after_runs() {
+    let now = datetime!(2024-01-01 12:00:00 +8);
+    let accounts = || {
+      vec![
+        (account(100_000_001), true),
+        (account(100_000_002), true),
+        (account(100_000_003), true),
+      ]
+    };
+
+    // The webcaches modified runs do not postpone the interval
+    let mut state = AutoSyncState::default();
+    state.record_run(
+      AutoSyncTrigger::Interval,
+      now - time::Duration::minutes(60),
+      vec![synced(100_000_001)],
+    );
+    state.record_run(
+      AutoSyncTrigger::WebcachesModified,
+      now - time::Duration::minutes(10),
+      vec![synced(100_000_001)],
+    );
+    assert_eq!(state.last_run, Some(now - time::Duration::minutes(10)));
+    assert_eq!(
+      uids(auto_sync_trigger(&SCHEDULE, &state, now, accounts())),
+      Some((
+        AutoSyncTrigger::Interval,
+        vec![100_000_001, 100_000_002, 100_000_003]
+      ))
+    );
+
+    // The failed accounts are synced again within the minimum interval
+    state.record_run(
+      AutoSyncTrigger::Interval,
+      now - time::Duration::minutes(1),
+      vec![
+        synced(100_000_001),
+        result(100_000_002, GachaRecordsSyncOutcome::Expired),
+        result(
+          100_000_003,
+          GachaRecordsSyncOutcome::Failed {
+            error: serde_json::Value::Null,
+          },
+        ),
+      ],
+    );
+    assert_eq!(
+      uids(auto_sync_trigger(&SCHEDULE, &state, now, accounts())),
+      Some((
+        AutoSyncTrigger::WebcachesModified,
+        vec![100_000_002, 100_000_003]
+      ))
+    );
+  }
+}
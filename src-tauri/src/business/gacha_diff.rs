@@ -0,0 +1,346 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::database::{
+  Database, GachaRecordQuestioner, GachaRecordQuestionerAdditions, PendingGachaRecordQuestioner,
+  PendingGachaRecordQuestionerAdditions, SqlxError,
+};
+use crate::models::{Business, GachaRecord, GachaRecordsAuditSource, PendingGachaRecord};
+
+use super::gacha_type_of_request;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaRecordFieldChange {
+  pub field: String,
+  pub local: serde_json::Value,
+  pub remote: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaRecordChanged {
+  pub local: GachaRecord,
+  pub remote: GachaRecord,
+  pub changes: Vec<GachaRecordFieldChange>,
+}
+
+/// Changes of the full update to the local records of the gacha type.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaTypeDiff {
+  pub gacha_type: u32,
+  // Oldest id of the remote, the older local records are not replaced.
+  // None if the remote has no records of the gacha type.
+  pub oldest_id: Option<String>,
+  pub deleted: Vec<GachaRecord>,
+  pub created: Vec<GachaRecord>,
+  pub changed: Vec<GachaRecordChanged>,
+  // Remote records without the metadata, they are quarantined on apply.
+  pub pending: Vec<PendingGachaRecord>,
+}
+
+/// Preview of the full update, can be applied as it is.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaRecordsDiff {
+  pub business: Business,
+  pub uid: u32,
+  pub gacha_types: Vec<GachaTypeDiff>,
+}
+
+// Field by field, the keys are always the same.
+fn diff_gacha_record_fields(
+  local: &GachaRecord,
+  remote: &GachaRecord,
+) -> Vec<GachaRecordFieldChange> {
+  let (Ok(serde_json::Value::Object(local)), Ok(serde_json::Value::Object(mut remote))) =
+    (serde_json::to_value(local), serde_json::to_value(remote))
+  else {
+    unreachable!("Gacha record is always serialized as an object")
+  };
+
+  let mut changes = local
+    .into_iter()
+    .filter_map(|(field, local)| {
+      let remote = remote.remove(&field).unwrap_or_default();
+      (local != remote).then_some(GachaRecordFieldChange {
+        field,
+        local,
+        remote,
+      })
+    })
+    .collect::<Vec<_>>();
+
+  changes.sort_by(|a, b| a.field.cmp(&b.field));
+  changes
+}
+
+/// Compute the changes of the full update per requested gacha type, without writing anything.
+/// The local records newer than the oldest remote one are replaced by the remote records,
+/// except the pending ones that are quarantined instead. See: `PendingGachaRecord`
+pub fn diff_gacha_records(
+  business: Business,
+  uid: u32,
+  gacha_types: &[u32],
+  remote: &[GachaRecord],
+  remote_pending: &[PendingGachaRecord],
+  local: &[GachaRecord],
+) -> GachaRecordsDiff {
+  let mut diffs = Vec::with_capacity(gacha_types.len());
+
+  for gacha_type in gacha_types {
+    let of_request =
+      |record_gacha_type: u32| gacha_type_of_request(business, record_gacha_type) == *gacha_type;
+
+    let remote = remote
+      .iter()
+      .filter(|record| of_request(record.gacha_type))
+      .map(|record| (&record.id, record))
+      .collect::<HashMap<_, _>>();
+
+    let mut pending = remote_pending
+      .iter()
+      .filter(|record| of_request(record.gacha_type))
+      .cloned()
+      .collect::<Vec<_>>();
+
+    pending.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let remote_pending_ids = pending
+      .iter()
+      .map(|record| &record.id)
+      .collect::<HashSet<_>>();
+
+    let Some(oldest_id) = remote
+      .keys()
+      .chain(remote_pending_ids.iter())
+      .min()
+      .map(|id| (*id).clone())
+    else {
+      // Nothing is replaced
+      diffs.push(GachaTypeDiff {
+        gacha_type: *gacha_type,
+        ..Default::default()
+      });
+      continue;
+    };
+
+    let local = local
+      .iter()
+      .filter(|record| of_request(record.gacha_type) && record.id >= oldest_id)
+      .map(|record| (&record.id, record))
+      .collect::<HashMap<_, _>>();
+
+    let mut deleted = Vec::new();
+    let mut changed = Vec::new();
+    for (id, record) in &local {
+      match remote.get(id) {
+        None if !remote_pending_ids.contains(id) => deleted.push((*record).clone()),
+        None => {}
+        Some(remote) => {
+          let changes = diff_gacha_record_fields(record, remote);
+          if !changes.is_empty() {
+            changed.push(GachaRecordChanged {
+              local: (*record).clone(),
+              remote: (*remote).clone(),
+              changes,
+            });
+          }
+        }
+      }
+    }
+
+    let mut created = remote
+      .iter()
+      .filter(|(id, _)| !local.contains_key(*id))
+      .map(|(_, record)| (*record).clone())
+      .collect::<Vec<_>>();
+
+    deleted.sort_by(|a, b| a.id.cmp(&b.id));
+    created.sort_by(|a, b| a.id.cmp(&b.id));
+    changed.sort_by(|a, b| a.local.id.cmp(&b.local.id));
+
+    diffs.push(GachaTypeDiff {
+      gacha_type: *gacha_type,
+      oldest_id: Some(oldest_id),
+      deleted,
+      created,
+      changed,
+      pending,
+    });
+  }
+
+  GachaRecordsDiff {
+    business,
+    uid,
+    gacha_types: diffs,
+  }
+}
+
+/// Apply exactly the previewed changes, the changed records are replaced by the remote ones.
+/// The pending records are quarantined. Returns the number of created minus deleted.
+#[tracing::instrument(skip_all, fields(business = ?diff.business, uid = diff.uid))]
+pub async fn apply_gacha_records_diff(
+  database: &Database,
+  diff: GachaRecordsDiff,
+) -> Result<i64, SqlxError> {
+  let GachaRecordsDiff { business, uid, .. } = diff;
+
+  let mut created = Vec::new();
+  let mut deleted = Vec::new();
+  let mut pending = Vec::new();
+  for gacha_type in diff.gacha_types {
+    created.extend(gacha_type.created);
+    deleted.extend(gacha_type.deleted);
+    pending.extend(gacha_type.pending);
+
+    for changed in gacha_type.changed {
+      deleted.push(changed.local);
+      created.push(changed.remote);
+    }
+  }
+
+  // Only the records of the previewed account
+  created.retain(|record| record.business == business && record.uid == uid);
+  deleted.retain(|record| record.business == business && record.uid == uid);
+  pending.retain(|record| record.business == business && record.uid == uid);
+
+  if !pending.is_empty() {
+    PendingGachaRecordQuestioner::create_pending_gacha_records(database, pending).await?;
+  }
+
+  if created.is_empty() && deleted.is_empty() {
+    return Ok(0);
+  }
+
+  GachaRecordQuestioner::reconcile_gacha_records(
+    database,
+    created,
+    deleted,
+    &GachaRecordsAuditSource::FullUpdate,
+  )
+  .await
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use time::macros::datetime;
+
+  use super::*;
+
+  #[test]
+  fn test_diff_gacha_records() {
+    let remote = vec![
+      GachaRecord::fixture("1004", 400),
+      GachaRecord::fixture("1003", 301),
+      GachaRecord::fixture("1002", 301).with_name("Harbinger of Dawn"),
+    ];
+
+    let local = vec![
+      // Older than the remote, kept
+      GachaRecord::fixture("1000", 301),
+      GachaRecord::fixture("1001", 301),
+      GachaRecord::fixture("1002", 301),
+      GachaRecord::fixture("1005", 301),
+    ];
+
+    let pending = vec![PendingGachaRecord {
+      business: Business::GenshinImpact,
+      uid: 100_000_001,
+      id: "1001".into(),
+      gacha_type: 301,
+      gacha_id: None,
+      rank_type: 3,
+      count: 1,
+      time: datetime!(2024-01-01 00:00:00 +8),
+      lang: "en-us".into(),
+      name: "Unknown".into(),
+      item_type: "Weapon".into(),
+    }];
+
+    let diff = diff_gacha_records(
+      Business::GenshinImpact,
+      100_000_001,
+      &[301, 302],
+      &remote,
+      &pending,
+      &local,
+    );
+
+    let character = &diff.gacha_types[0];
+    assert_eq!(character.oldest_id.as_deref(), Some("1001"));
+    assert_eq!(character.deleted, vec![local[3].clone()]);
+    assert_eq!(
+      character.created,
+      vec![remote[1].clone(), remote[0].clone()]
+    );
+    assert_eq!(
+      character.changed,
+      vec![GachaRecordChanged {
+        local: local[2].clone(),
+        remote: remote[2].clone(),
+        changes: vec![GachaRecordFieldChange {
+          field: "name".into(),
+          local: "Cool Steel".into(),
+          remote: "Harbinger of Dawn".into(),
+        }],
+      }]
+    );
+
+    assert_eq!(character.pending, pending);
+
+    let weapon = &diff.gacha_types[1];
+    assert_eq!(weapon.oldest_id, None);
+    assert!(weapon.deleted.is_empty() && weapon.created.is_empty() && weapon.changed.is_empty());
+    assert!(weapon.pending.is_empty());
+  }
+
+  #[tokio::test]
+  async fn test_apply_gacha_records_diff_pending() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let database = Database::new_with(temp_dir.path().join("test.db")).await;
+
+    let pending = PendingGachaRecord {
+      business: Business::GenshinImpact,
+      uid: 100_000_001,
+      id: "1001".into(),
+      gacha_type: 301,
+      gacha_id: None,
+      rank_type: 3,
+      count: 1,
+      time: datetime!(2024-01-01 00:00:00 +8),
+      lang: "en-us".into(),
+      name: "Unknown".into(),
+      item_type: "Weapon".into(),
+    };
+
+    // Nothing is created or deleted, but the pending record is still quarantined
+    let diff = diff_gacha_records(
+      Business::GenshinImpact,
+      100_000_001,
+      &[301],
+      &[],
+      std::slice::from_ref(&pending),
+      &[],
+    );
+
+    let changes = apply_gacha_records_diff(&database, diff).await.unwrap();
+    assert_eq!(changes, 0);
+
+    let quarantined = PendingGachaRecordQuestioner::find_pending_gacha_records_by_business_and_uid(
+      &database,
+      Business::GenshinImpact,
+      100_000_001,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(quarantined, vec![pending]);
+
+    database.close().await;
+  }
+}
@@ -1,16 +1,28 @@
-use std::time::Duration;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
 
 use serde::Serialize;
-use tauri::{Emitter, WebviewWindow};
+use time::OffsetDateTime;
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
-use crate::error::{Error, ErrorDetails};
-use crate::models::{Business, BusinessRegion, GachaRecord};
+use crate::database::{
+  Database, GachaRecordQuestioner, GachaRecordQuestionerAdditions, GachaRecordSaveOnConflict,
+  GachaRecordsCheckpointQuestioner, GachaRecordsCheckpointQuestionerAdditions,
+  PendingGachaRecordQuestioner, PendingGachaRecordQuestionerAdditions, SqlxError,
+};
+use crate::error::{ErrorDetails, declare_error_kinds};
+use crate::models::{
+  Business, BusinessRegion, GachaRecord, GachaRecordsAuditSource, PendingGachaRecord,
+};
+use crate::utilities::progress_sink::ProgressSink;
+use crate::utilities::redact::RedactedUrl;
 
-use super::{GachaUrlError, PrettyCategory};
+use super::{GachaUrlError, GachaUrlTransport, PrettyCategory, gacha_types_of_request};
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "PascalCase")] // Enum name
 pub enum GachaRecordsFetcherFragment {
   Sleeping,
@@ -19,76 +31,343 @@ pub enum GachaRecordsFetcherFragment {
   DataRef(usize),
   Data(Vec<GachaRecord>),
   Completed(PrettyCategory),
+  // Warning, the gacha type is not known by any category
+  UnknownGachaType(u32),
+  // Warning, the item ids of these records are missing from the metadata
+  Pending(usize),
   Finished,
+  // Only the records of the completed gacha types are kept, unless discarded.
+  // The gacha type in progress is dropped, otherwise it leaves a gap with the last end id.
+  Cancelled,
 }
 
+declare_error_kinds! {
+  #[derive(Debug, thiserror::Error)]
+  GachaRecordsFetcherCancelError {
+    #[error("Records of the streaming fetcher are committed, and cannot be discarded")]
+    DiscardStreaming,
+  }
+}
+
+#[derive(Debug)]
+pub enum GachaRecordsFetcherError {
+  GachaUrl(GachaUrlError),
+  // Only when streaming into the database
+  Database(SqlxError),
+}
+
+impl GachaRecordsFetcherError {
+  pub fn boxed(self) -> Box<dyn ErrorDetails + Send + 'static> {
+    match self {
+      Self::GachaUrl(error) => error.boxed(),
+      Self::Database(error) => error.boxed(),
+    }
+  }
+}
+
+impl fmt::Display for GachaRecordsFetcherError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::GachaUrl(error) => error.fmt(f),
+      Self::Database(error) => error.fmt(f),
+    }
+  }
+}
+
+impl From<GachaUrlError> for GachaRecordsFetcherError {
+  fn from(value: GachaUrlError) -> Self {
+    Self::GachaUrl(value)
+  }
+}
+
+impl From<SqlxError> for GachaRecordsFetcherError {
+  fn from(value: SqlxError) -> Self {
+    Self::Database(value)
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct GachaRecordsFetched {
+  // Empty when streaming, they have been committed into the database.
+  pub records: Vec<GachaRecord>,
+  // Same as the records, the item id is pending. See: `PendingGachaRecord`
+  pub pending: Vec<PendingGachaRecord>,
+  // Only when streaming
+  pub changes: i64,
+}
+
+// region: Cancellation
+
+#[derive(Default)]
+struct GachaRecordsFetcherCancellation {
+  cancelled: AtomicBool,
+  discard: AtomicBool,
+  // The pages are committed into the database, so they are never discarded
+  streaming: bool,
+}
+
+impl GachaRecordsFetcherCancellation {
+  fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::Acquire)
+  }
+
+  fn is_discard(&self) -> bool {
+    self.discard.load(Ordering::Acquire)
+  }
+}
+
+static GACHA_RECORDS_FETCHERS: LazyLock<
+  Mutex<HashMap<String, Arc<GachaRecordsFetcherCancellation>>>,
+> = LazyLock::new(Default::default);
+
+// Registered while the fetcher is running, and unregistered on drop.
+struct GachaRecordsFetcherRegistration {
+  id: Option<String>,
+  cancellation: Arc<GachaRecordsFetcherCancellation>,
+}
+
+impl GachaRecordsFetcherRegistration {
+  fn register(id: Option<String>, streaming: bool) -> Self {
+    let cancellation = Arc::new(GachaRecordsFetcherCancellation {
+      streaming,
+      ..Default::default()
+    });
+
+    if let Some(id) = &id {
+      let mut fetchers = GACHA_RECORDS_FETCHERS.lock().unwrap();
+      if fetchers.insert(id.clone(), cancellation.clone()).is_some() {
+        warn!("Gacha records fetcher id is already registered, replace it: {id}");
+      }
+    }
+
+    Self { id, cancellation }
+  }
+}
+
+impl Drop for GachaRecordsFetcherRegistration {
+  fn drop(&mut self) {
+    if let Some(id) = &self.id {
+      let mut fetchers = GACHA_RECORDS_FETCHERS.lock().unwrap();
+      if fetchers
+        .get(id)
+        .is_some_and(|cancellation| Arc::ptr_eq(cancellation, &self.cancellation))
+      {
+        fetchers.remove(id);
+      }
+    }
+  }
+}
+
+/// Cancel the running gacha records fetcher with the id.
+/// It stops between pages, and the records already collected are discarded if `discard`.
+/// Otherwise, only the records of the completed gacha types are kept,
+/// the pages of the gacha type in progress are dropped.
+/// When streaming, the committed records are always kept and resumed by the next run,
+/// so `discard` is rejected without cancelling it.
+#[tracing::instrument]
+pub fn cancel_gacha_records_fetcher(
+  id: &str,
+  discard: bool,
+) -> Result<bool, GachaRecordsFetcherCancelError> {
+  let fetchers = GACHA_RECORDS_FETCHERS.lock().unwrap();
+  if let Some(cancellation) = fetchers.get(id) {
+    if discard && cancellation.streaming {
+      warn!("Streaming gacha records fetcher cannot be discarded");
+      return Err(GachaRecordsFetcherCancelErrorKind::DiscardStreaming)?;
+    }
+
+    info!("Cancelling the gacha records fetcher");
+    cancellation.discard.store(discard, Ordering::Release);
+    cancellation.cancelled.store(true, Ordering::Release);
+    Ok(true)
+  } else {
+    warn!("Gacha records fetcher is not found or has finished");
+    Ok(false)
+  }
+}
+
+// endregion
+
+// region: Streaming
+
+/// Commit each batch of records into the database as it arrives,
+/// instead of holding all of them until the fetcher is finished.
+/// The progress of each gacha type is kept as a checkpoint, until it is completed.
+pub struct GachaRecordsStreaming {
+  pub database: Arc<Database>,
+  pub save_on_conflict: GachaRecordSaveOnConflict,
+  // Replace the newer records, instead of inserting
+  pub full_update: bool,
+  pub gacha_url_creation_time: Option<OffsetDateTime>,
+  changes: i64,
+}
+
+impl GachaRecordsStreaming {
+  pub fn new(
+    database: Arc<Database>,
+    save_on_conflict: GachaRecordSaveOnConflict,
+    full_update: bool,
+    gacha_url_creation_time: Option<OffsetDateTime>,
+  ) -> Self {
+    Self {
+      database,
+      save_on_conflict,
+      full_update,
+      gacha_url_creation_time,
+      changes: 0,
+    }
+  }
+
+  // The records of the page are in [end_id, older_than) of the requested gacha type.
+  #[allow(clippy::too_many_arguments)]
+  async fn commit(
+    &mut self,
+    business: Business,
+    uid: u32,
+    gacha_type: u32,
+    records: Vec<GachaRecord>,
+    full_update: bool,
+    end_id: &str,
+    older_than: &str,
+  ) -> Result<(), SqlxError> {
+    self.changes += if full_update {
+      GachaRecordQuestioner::replace_gacha_records(
+        &self.database,
+        business,
+        uid,
+        // Genshin Impact: 400 is returned together with 301
+        &gacha_types_of_request(business, gacha_type),
+        records,
+        self.save_on_conflict,
+        &GachaRecordsAuditSource::FullUpdate,
+        end_id,
+        // The first page is newer than any records
+        Some(older_than).filter(|id| *id != "0"),
+      )
+      .await?
+    } else {
+      GachaRecordQuestioner::create_gacha_records(
+        &self.database,
+        records,
+        self.save_on_conflict,
+        &GachaRecordsAuditSource::Fetch,
+        None,
+      )
+      .await? as i64
+    };
+
+    Ok(())
+  }
+}
+
+// endregion
+
 #[allow(clippy::too_many_arguments)]
-#[tracing::instrument(skip(window))]
+#[tracing::instrument(
+  skip(transport, gacha_url, progress_sink, streaming),
+  fields(gacha_url = %RedactedUrl(&gacha_url))
+)]
 pub async fn create_gacha_records_fetcher(
   business: Business,
   region: BusinessRegion,
   uid: u32,
+  transport: Arc<dyn GachaUrlTransport>,
   gacha_url: String,
   gacha_type_and_last_end_id_mappings: Vec<(u32, Option<String>)>,
-  window: WebviewWindow,
-  event_channel: Option<String>,
-) -> Result<Option<Vec<GachaRecord>>, Box<dyn ErrorDetails + Send + 'static>> {
+  // Otherwise, the unknown gacha types are skipped with the warning
+  fetch_unknown_gacha_types: bool,
+  progress_sink: &dyn ProgressSink<GachaRecordsFetcherFragment>,
+  id: Option<String>,
+  mut streaming: Option<GachaRecordsStreaming>,
+) -> Result<GachaRecordsFetched, GachaRecordsFetcherError> {
   info!("Creating a Gacha Records Fetcher");
 
   if gacha_type_and_last_end_id_mappings.is_empty() {
     warn!("Empty Gacha type and last end id mappings");
-    return Ok(None);
+    return Ok(GachaRecordsFetched::default());
   }
 
+  let is_streaming = streaming.is_some();
+
   // Internal Abbreviations
   type Fragment = GachaRecordsFetcherFragment;
 
+  let registration = GachaRecordsFetcherRegistration::register(id, is_streaming);
+  let cancellation = registration.cancellation.clone();
+
   let (sender, mut receiver) = mpsc::channel(1);
   let task = tokio::spawn(async move {
+    let mut pending = Vec::new();
+
     for (gacha_type, last_end_id) in gacha_type_and_last_end_id_mappings {
-      pull_gacha_records(
+      // Same as the records, only the completed gacha types are kept
+      let mut gacha_type_pending = Vec::new();
+      let completed = pull_gacha_records(
         business,
         region,
+        uid,
         &sender,
+        transport.as_ref(),
         &gacha_url,
         &gacha_type,
         last_end_id.as_deref(),
+        fetch_unknown_gacha_types,
+        &cancellation,
+        streaming.as_mut(),
+        &mut gacha_type_pending,
       )
-      .await
-      .map_err(Error::boxed)?;
+      .await?;
+
+      if !completed {
+        info!("Gacha records fetcher is cancelled");
+        sender.send(Fragment::Cancelled).await.unwrap();
+
+        if cancellation.is_discard() {
+          pending.clear();
+        }
+
+        return Ok((streaming.map_or(0, |streaming| streaming.changes), pending));
+      }
+
+      pending.append(&mut gacha_type_pending);
     }
 
     sender.send(Fragment::Finished).await.unwrap();
-    Ok(())
+    Ok((streaming.map_or(0, |streaming| streaming.changes), pending))
   });
 
-  let event_channel = event_channel.unwrap_or_default();
-  let event_emit = !event_channel.is_empty();
   let mut records = Vec::new();
 
+  // Records of the gacha type in progress, until it is completed.
+  // When cancelled, they are incomplete and may leave a gap with the last end id.
+  let mut pending = Vec::new();
+
   while let Some(fragment) = receiver.recv().await {
-    if event_emit {
-      // FIXME: emit SAFETY?
-      // If the fragment is the actual records data, then just send the length
-      if let Fragment::Data(records) = &fragment {
-        window
-          .emit(&event_channel, &Fragment::DataRef(records.len()))
-          .unwrap();
-      } else {
-        window.emit(&event_channel, &fragment).unwrap();
-      }
+    // If the fragment is the actual records data, then just report the length
+    if let Fragment::Data(records) = &fragment {
+      progress_sink.report(Fragment::DataRef(records.len()));
+    } else {
+      progress_sink.report(fragment.clone());
     }
 
-    if let GachaRecordsFetcherFragment::Data(data) = fragment {
-      records.extend(data);
+    match fragment {
+      Fragment::Data(_) if is_streaming => {}
+      Fragment::Data(data) => pending.extend(data),
+      Fragment::Completed(_) => records.append(&mut pending),
+      Fragment::Cancelled if registration.cancellation.is_discard() => records.clear(),
+      _ => {}
     }
   }
 
   match task.await.expect("Task join error") {
     // FIXME: The task should not panic
-    Ok(_) => {
+    Ok((changes, pending)) => {
       info!("Gacha Records fetcher execution is finished");
-      Ok(Some(records))
+      Ok(GachaRecordsFetched {
+        records,
+        pending,
+        changes,
+      })
     }
     Err(err) => {
       error!("Error while pull gacha records: {err}");
@@ -97,93 +376,495 @@ pub async fn create_gacha_records_fetcher(
   }
 }
 
+// Returns false if it is cancelled before completed.
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(skip_all, fields(?gacha_type, ?last_end_id))]
 async fn pull_gacha_records(
   business: Business,
   _region: BusinessRegion,
+  uid: u32,
   sender: &mpsc::Sender<GachaRecordsFetcherFragment>,
+  transport: &dyn GachaUrlTransport,
   gacha_url: &str,
   gacha_type: &u32,
   last_end_id: Option<&str>,
-) -> Result<(), GachaUrlError> {
+  fetch_unknown_gacha_types: bool,
+  cancellation: &GachaRecordsFetcherCancellation,
+  mut streaming: Option<&mut GachaRecordsStreaming>,
+  // Only when not streaming, otherwise they are quarantined into the database
+  pending_records: &mut Vec<PendingGachaRecord>,
+) -> Result<bool, GachaRecordsFetcherError> {
   // Internal Abbreviations
   type Fragment = GachaRecordsFetcherFragment;
 
-  let category = PrettyCategory::from_gacha_type(&business, *gacha_type);
-  if category.is_none() {
-    // HACK: Normally it must be Some,
-    //   unless an unofficial enumeration value is manually added by the user.
-    //   A better solution is to return an Err.
-    return Ok(());
-  }
+  // Unknown when the game adds a new gacha type, or it is manually added by the user.
+  // The records keep the raw gacha type, so they are classified once it is known.
+  let category = match PrettyCategory::from_gacha_type(&business, *gacha_type) {
+    Some(category) => category,
+    None => {
+      warn!(message = "Unknown gacha type", fetch_unknown_gacha_types);
+      sender
+        .send(Fragment::UnknownGachaType(*gacha_type))
+        .await
+        .unwrap();
 
-  let category = category.unwrap(); // SAFETY
+      if !fetch_unknown_gacha_types {
+        return Ok(true);
+      }
+
+      PrettyCategory::Unknown
+    }
+  };
 
   info!(message = "Start pulling gacha records...", ?category);
   sender.send(Fragment::Ready(category)).await.unwrap();
 
-  const THRESHOLD: usize = 5;
-  const WAIT_MOMENT_MILLIS: u64 = 500;
+  // When streaming, the interrupted range of the checkpoint is resumed first.
+  // Then the newer records until the last end id, which is the newest in the database.
+  let mut ranges = Vec::with_capacity(2);
+  if let Some(streaming) = streaming.as_deref() {
+    let checkpoint = GachaRecordsCheckpointQuestioner::find_gacha_records_checkpoint(
+      &streaming.database,
+      business,
+      uid,
+      *gacha_type,
+    )
+    .await?;
+
+    if let Some(checkpoint) = checkpoint {
+      info!(
+        message = "Resume the interrupted sync checkpoint",
+        ?checkpoint
+      );
+      ranges.push((
+        checkpoint.end_id,
+        checkpoint.last_end_id,
+        checkpoint.full_update,
+      ));
+      ranges.push((String::from("0"), last_end_id.map(ToOwned::to_owned), false));
+    } else if streaming.full_update {
+      // The last end id is discarded on full update
+      ranges.push((String::from("0"), None, true));
+    }
+  }
+
+  if ranges.is_empty() {
+    ranges.push((String::from("0"), last_end_id.map(ToOwned::to_owned), false));
+  }
 
-  let mut end_id = String::from("0");
   let mut pagination: usize = 0;
-  loop {
-    // Avoid visit too frequently
-    if pagination > 1 && pagination % THRESHOLD == 0 {
-      info!("One continuous request reached. Wait a moment...");
-      sender.send(Fragment::Sleeping).await.unwrap();
-      tokio::time::sleep(Duration::from_millis(WAIT_MOMENT_MILLIS)).await;
-    }
-
-    pagination += 1;
-    info!("Start fetching page {pagination} data...");
-    sender.send(Fragment::Pagination(pagination)).await.unwrap();
-
-    if let Some(records) = super::gacha_url::fetch_gacha_records(
-      gacha_url,
-      Some(&format!("{}", *gacha_type)),
-      Some(&end_id),
-      None,
-    )
-    .await?
-    {
-      // The gacha records is always not empty.
-      // See: `super::gacha_url::fetch_gacha_records`
-      end_id.clone_from(&records.last().as_ref().unwrap().id);
-
-      let mut should_break = false;
-      let data = if let Some(last) = last_end_id {
-        let mut temp = Vec::with_capacity(records.len());
-        for record in records {
-          if last.cmp(&record.id).is_lt() {
-            temp.push(record);
-          } else {
-            should_break = true;
+  for (mut end_id, last_end_id, full_update) in ranges {
+    let last_end_id = last_end_id.as_deref();
+
+    loop {
+      // Stop cleanly between pages
+      if cancellation.is_cancelled() {
+        info!(
+          message = "Cancelled while pulling gacha records",
+          ?category,
+          pagination
+        );
+        return Ok(false);
+      }
+
+      // Avoid visit too frequently, the request will wait for the rate limiter
+      if super::gacha_url::is_gacha_url_rate_limited(gacha_url, Some(&gacha_type.to_string())) {
+        info!("Rate limit reached. Wait a moment...");
+        sender.send(Fragment::Sleeping).await.unwrap();
+      }
+
+      pagination += 1;
+      info!("Start fetching page {pagination} data...");
+      sender.send(Fragment::Pagination(pagination)).await.unwrap();
+
+      if let Some(page) = super::gacha_url::fetch_gacha_records(
+        transport,
+        gacha_url,
+        Some(&format!("{}", *gacha_type)),
+        Some(&end_id),
+        None,
+      )
+      .await?
+      {
+        // The gacha records is always not empty.
+        // See: `super::gacha_url::fetch_gacha_records`
+        let older_than = std::mem::replace(&mut end_id, page.end_id);
+
+        let mut should_break = false;
+        let (data, pending) = if let Some(last) = last_end_id {
+          let mut temp = Vec::with_capacity(page.records.len());
+          for record in page.records {
+            if last.cmp(&record.id).is_lt() {
+              temp.push(record);
+            } else {
+              should_break = true;
+            }
+          }
+
+          let mut temp_pending = Vec::with_capacity(page.pending.len());
+          for record in page.pending {
+            if last.cmp(&record.id).is_lt() {
+              temp_pending.push(record);
+            } else {
+              should_break = true;
+            }
           }
+
+          (temp, temp_pending)
+        } else {
+          (page.records, page.pending)
+        };
+
+        let pending_len = pending.len();
+
+        // Commit it before the checkpoint, so that a failure is retried from this page.
+        if let Some(streaming) = streaming.as_deref_mut() {
+          info!("Commit {} pieces of data to the database...", data.len());
+          streaming
+            .commit(
+              business,
+              uid,
+              *gacha_type,
+              data.clone(),
+              full_update,
+              &end_id,
+              &older_than,
+            )
+            .await?;
+
+          if !pending.is_empty() {
+            info!("Quarantine {} pieces of pending data...", pending.len());
+            PendingGachaRecordQuestioner::create_pending_gacha_records(
+              &streaming.database,
+              pending,
+            )
+            .await?;
+          }
+
+          GachaRecordsCheckpointQuestioner::upsert_gacha_records_checkpoint(
+            &streaming.database,
+            business,
+            uid,
+            *gacha_type,
+            end_id.clone(),
+            last_end_id.map(ToOwned::to_owned),
+            full_update,
+            streaming.gacha_url_creation_time,
+          )
+          .await?;
+        } else {
+          pending_records.extend(pending);
         }
-        temp
-      } else {
-        records
-      };
 
-      info!("Send {} pieces of data to the channel...", data.len());
-      sender.send(Fragment::Data(data)).await.unwrap();
+        info!("Send {} pieces of data to the channel...", data.len());
+        sender.send(Fragment::Data(data)).await.unwrap();
 
-      if should_break {
-        info!("Break loop. Data reaches the last end id: {last_end_id:?}");
-        break;
-      } else {
-        continue;
+        if pending_len > 0 {
+          sender.send(Fragment::Pending(pending_len)).await.unwrap();
+        }
+
+        if should_break {
+          info!("Break loop. Data reaches the last end id: {last_end_id:?}");
+          break;
+        } else {
+          continue;
+        }
       }
+
+      // None gacha records. break loop
+      break;
     }
 
-    // None gacha records. break loop
-    break;
+    // The range is completed
+    if let Some(streaming) = streaming.as_deref() {
+      GachaRecordsCheckpointQuestioner::delete_gacha_records_checkpoint(
+        &streaming.database,
+        business,
+        uid,
+        *gacha_type,
+      )
+      .await?;
+    }
   }
 
   // Completed gacha type
   info!(message = "Gacha type completed", ?category);
   sender.send(Fragment::Completed(category)).await.unwrap();
 
-  Ok(())
+  Ok(true)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use std::str::FromStr;
+
+  use super::*;
+  use crate::business::{GachaUrlExchange, ParsedGachaUrl, ReplayGachaUrlTransport};
+  use crate::utilities::progress_sink::ChannelProgressSink;
+  use crate::utilities::redact::redact_authkeys;
+
+  #[test]
+  fn test_cancel_gacha_records_fetcher() {
+    let registration = GachaRecordsFetcherRegistration::register(Some("test".into()), false);
+    assert!(!registration.cancellation.is_cancelled());
+
+    assert!(cancel_gacha_records_fetcher("test", true).unwrap());
+    assert!(registration.cancellation.is_cancelled());
+    assert!(registration.cancellation.is_discard());
+
+    // Unregistered on drop
+    drop(registration);
+    assert!(!cancel_gacha_records_fetcher("test", false).unwrap());
+  }
+
+  #[test]
+  fn test_cancel_streaming_gacha_records_fetcher() {
+    let id = "test_cancel_streaming_gacha_records_fetcher";
+    let registration = GachaRecordsFetcherRegistration::register(Some(id.into()), true);
+
+    // Rejected without cancelling it
+    let error = cancel_gacha_records_fetcher(id, true).unwrap_err();
+    assert!(matches!(
+      error.as_ref(),
+      GachaRecordsFetcherCancelErrorKind::DiscardStreaming
+    ));
+    assert!(!registration.cancellation.is_cancelled());
+
+    assert!(cancel_gacha_records_fetcher(id, false).unwrap());
+    assert!(registration.cancellation.is_cancelled());
+    assert!(!registration.cancellation.is_discard());
+  }
+
+  // Cancel the fetcher once the url is requested, so that it stops after the page.
+  struct CancellingTransport {
+    inner: ReplayGachaUrlTransport,
+    id: &'static str,
+    url: String,
+    discard: bool,
+  }
+
+  #[async_trait::async_trait]
+  impl GachaUrlTransport for CancellingTransport {
+    async fn get(
+      &self,
+      url: &url::Url,
+      timeout: std::time::Duration,
+    ) -> Result<String, GachaUrlError> {
+      if redact_authkeys(url.as_str()) == self.url {
+        cancel_gacha_records_fetcher(self.id, self.discard).unwrap();
+      }
+
+      self.inner.get(url, timeout).await
+    }
+  }
+
+  #[tokio::test]
+  async fn test_cancel_gacha_records_fetcher_mid_type() {
+    const GACHA_URL: &str = "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey_ver=1&sign_type=2&authkey=SECRET&lang=zh-cn&region=cn_gf01&game_biz=hk4e_cn&gacha_type=200";
+
+    let url = |gacha_type: &str, end_id: &str| {
+      redact_authkeys(
+        ParsedGachaUrl::from_str(GACHA_URL)
+          .unwrap()
+          .to_request_url(Some(gacha_type), Some(end_id), None)
+          .as_str(),
+      )
+      .into_owned()
+    };
+
+    let exchange = |gacha_type: &str, end_id: &str, list: Vec<(&str, &str)>| {
+      let list = list
+        .into_iter()
+        .map(|(id, gacha_type)| {
+          serde_json::json!({
+            "id": id,
+            "uid": "100000001",
+            "gacha_type": gacha_type,
+            "rank_type": "3",
+            "count": "1",
+            "time": "2024-01-01 00:00:00",
+            "lang": "zh-cn",
+            "name": "Name",
+            "item_type": "Weapon",
+            "item_id": "11301",
+          })
+        })
+        .collect::<Vec<_>>();
+
+      GachaUrlExchange {
+        url: url(gacha_type, end_id),
+        response: serde_json::json!({
+          "retcode": 0,
+          "message": "OK",
+          "data": {
+            "region": "cn_gf01",
+            "list": list,
+          },
+        })
+        .to_string(),
+      }
+    };
+
+    for discard in [false, true] {
+      let id = "test_cancel_gacha_records_fetcher_mid_type";
+      let transport = CancellingTransport {
+        inner: ReplayGachaUrlTransport::new([
+          exchange("200", "0", vec![("1000000000000000001", "200")]),
+          exchange("200", "1000000000000000001", vec![]),
+          exchange("301", "0", vec![("1000000000000000003", "301")]),
+        ]),
+        id,
+        // The first page of the character gacha type
+        url: url("301", "0"),
+        discard,
+      };
+
+      let (progress_sink, mut receiver) = ChannelProgressSink::<GachaRecordsFetcherFragment>::new();
+      let fetched = create_gacha_records_fetcher(
+        Business::GenshinImpact,
+        BusinessRegion::Official,
+        100_000_001,
+        Arc::new(transport),
+        GACHA_URL.into(),
+        vec![(200, None), (301, None)],
+        false,
+        &progress_sink,
+        Some(id.into()),
+        None,
+      )
+      .await
+      .unwrap();
+
+      let mut fragments = Vec::new();
+      while let Ok(fragment) = receiver.try_recv() {
+        // Depends on the rate limiter shared by the tests
+        if !matches!(fragment, GachaRecordsFetcherFragment::Sleeping) {
+          fragments.push(format!("{fragment:?}"));
+        }
+      }
+
+      assert_eq!(
+        fragments,
+        [
+          "Ready(Permanent)",
+          "Pagination(1)",
+          "DataRef(1)",
+          "Pagination(2)",
+          "Completed(Permanent)",
+          "Ready(Character)",
+          "Pagination(1)",
+          "DataRef(1)",
+          "Cancelled",
+        ]
+      );
+
+      // The page of the character gacha type in progress is dropped
+      let ids = fetched
+        .records
+        .iter()
+        .map(|record| record.id.as_str())
+        .collect::<Vec<_>>();
+
+      if discard {
+        assert!(ids.is_empty());
+      } else {
+        assert_eq!(ids, ["1000000000000000001"]);
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn test_fetch_unknown_gacha_types() {
+    const GACHA_URL: &str = "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey_ver=1&sign_type=2&authkey=SECRET&lang=zh-cn&region=cn_gf01&game_biz=hk4e_cn&gacha_type=301";
+
+    let exchange = |end_id: &str, list: serde_json::Value| GachaUrlExchange {
+      url: redact_authkeys(
+        ParsedGachaUrl::from_str(GACHA_URL)
+          .unwrap()
+          .to_request_url(Some("999"), Some(end_id), None)
+          .as_str(),
+      )
+      .into_owned(),
+      response: serde_json::json!({
+        "retcode": 0,
+        "message": "OK",
+        "data": {
+          "region": "cn_gf01",
+          "list": list,
+        },
+      })
+      .to_string(),
+    };
+
+    let item = serde_json::json!({
+      "id": "1000000000000000001",
+      "uid": "100000001",
+      "gacha_type": "999",
+      "rank_type": "3",
+      "count": "1",
+      "time": "2024-01-01 00:00:00",
+      "lang": "zh-cn",
+      "name": "Name",
+      "item_type": "Weapon",
+      "item_id": "11301",
+    });
+
+    for fetch_unknown_gacha_types in [false, true] {
+      // Skipped without any request, otherwise the replay panics
+      let transport = if fetch_unknown_gacha_types {
+        ReplayGachaUrlTransport::new([
+          exchange("0", serde_json::json!([item])),
+          exchange("1000000000000000001", serde_json::json!([])),
+        ])
+      } else {
+        ReplayGachaUrlTransport::new([])
+      };
+
+      let (progress_sink, mut receiver) = ChannelProgressSink::<GachaRecordsFetcherFragment>::new();
+      let fetched = create_gacha_records_fetcher(
+        Business::GenshinImpact,
+        BusinessRegion::Official,
+        100_000_001,
+        Arc::new(transport),
+        GACHA_URL.into(),
+        vec![(999, None)],
+        fetch_unknown_gacha_types,
+        &progress_sink,
+        None,
+        None,
+      )
+      .await
+      .unwrap();
+
+      let mut fragments = Vec::new();
+      while let Ok(fragment) = receiver.try_recv() {
+        // Depends on the rate limiter shared by the tests
+        if !matches!(fragment, GachaRecordsFetcherFragment::Sleeping) {
+          fragments.push(format!("{fragment:?}"));
+        }
+      }
+
+      if fetch_unknown_gacha_types {
+        assert_eq!(fetched.records.len(), 1);
+        assert_eq!(fetched.records[0].gacha_type, 999);
+        assert_eq!(
+          fragments,
+          [
+            "UnknownGachaType(999)",
+            "Ready(Unknown)",
+            "Pagination(1)",
+            "DataRef(1)",
+            "Pagination(2)",
+            "Completed(Unknown)",
+            "Finished",
+          ]
+        );
+      } else {
+        assert!(fetched.records.is_empty());
+        assert_eq!(fragments, ["UnknownGachaType(999)", "Finished"]);
+      }
+    }
+  }
 }
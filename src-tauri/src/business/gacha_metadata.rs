@@ -15,6 +15,7 @@ use tracing::info;
 
 use crate::consts;
 use crate::models::{Business, GachaRecord};
+use crate::utilities::http_client::http_client;
 
 // region: Raw Json Metadata
 
@@ -472,7 +473,7 @@ fn raw_banners_into_banner_groups(
   result
 }
 
-fn sha1sum(slice: impl AsRef<[u8]>) -> String {
+pub(crate) fn sha1sum(slice: impl AsRef<[u8]>) -> String {
   Sha1::digest(slice.as_ref())
     .into_iter()
     .fold(String::with_capacity(40), |mut output, b| {
@@ -608,12 +609,12 @@ impl GachaMetadata {
     }
     let _update_guard = UpdateGuard;
 
-    const API_BASE_URL: &str = "https://hoyo-gacha-v1.lgou2w.com/GachaMetadata/v2";
+    let api_base_url = super::gacha_metadata_api_base_url();
     const API_TIMEOUT: Duration = Duration::from_secs(15);
 
     info!("Checking for latest gacha metadata...");
-    let metadata_index = consts::REQWEST
-      .get(format!("{API_BASE_URL}/index.json"))
+    let metadata_index = http_client()
+      .get(format!("{api_base_url}/index.json"))
       .timeout(API_TIMEOUT)
       .send()
       .await?
@@ -635,8 +636,8 @@ impl GachaMetadata {
     }
 
     let start = Instant::now();
-    let latest_metadata_res = consts::REQWEST
-      .get(format!("{API_BASE_URL}/{}.json", metadata_index.latest))
+    let latest_metadata_res = http_client()
+      .get(format!("{api_base_url}/{}.json", metadata_index.latest))
       .timeout(API_TIMEOUT)
       .send()
       .await?
@@ -32,6 +32,7 @@ pub enum PrettyCategory {
   Bangboo,                // 'Zenless Zone Zero' only
   CollaborationCharacter, // 'Honkai: Star Rail' only
   CollaborationWeapon,    // 'Honkai: Star Rail' only
+  Unknown,                // Unknown gacha types, until they are classified
 }
 
 impl PrettyCategory {
@@ -118,6 +119,67 @@ impl PrettyCategory {
   }
 }
 
+/// Gacha type of the request, whose records include the given gacha type.
+pub fn gacha_type_of_request(business: Business, gacha_type: u32) -> u32 {
+  // HACK: Genshin Impact: 400 is requested together with 301
+  if business == Business::GenshinImpact && gacha_type == GENSHIN_IMPACT_CHARACTER2 {
+    301
+  } else {
+    gacha_type
+  }
+}
+
+/// Gacha types of the records returned by the request, the inverse of `gacha_type_of_request`.
+pub fn gacha_types_of_request(business: Business, gacha_type: u32) -> Vec<u32> {
+  let mut gacha_types = vec![gacha_type];
+  if business == Business::GenshinImpact
+    && gacha_type == gacha_type_of_request(business, GENSHIN_IMPACT_CHARACTER2)
+  {
+    gacha_types.push(GENSHIN_IMPACT_CHARACTER2);
+  }
+
+  gacha_types
+}
+
+/// Gacha types of the requests and their last end ids, computed from the records of the account.
+/// Same as `computeGachaTypeAndLastEndIdMappings` of the frontend.
+pub fn compute_gacha_type_and_last_end_id_mappings(
+  business: Business,
+  records: &[GachaRecord],
+  exclude_beginner: bool,
+) -> Vec<(u32, Option<String>)> {
+  let mut mappings = KNOWN_CATEGORIZEDS
+    .get(&business)
+    .unwrap() // SAFETY
+    .iter()
+    .filter(|(gacha_type, _)| gacha_type_of_request(business, **gacha_type) == **gacha_type)
+    .filter_map(|(gacha_type, category)| {
+      let records = records
+        .iter()
+        .filter(|record| gacha_type_of_request(business, record.gacha_type) == *gacha_type)
+        .collect::<Vec<_>>();
+
+      // HACK:
+      //   Genshin Impact    : Beginner Gacha Banner = 20 times
+      //   Honkai: Star Rail :                       = 50 times
+      //   Zenless Zone Zero : Useless
+      let exclude = exclude_beginner
+        && *category == PrettyCategory::Beginner
+        && match business {
+          Business::GenshinImpact => records.len() >= 20,
+          Business::HonkaiStarRail => records.len() >= 50,
+          Business::ZenlessZoneZero => false,
+        };
+
+      let last_end_id = records.iter().map(|record| &record.id).max().cloned();
+      (!exclude).then_some((*gacha_type, last_end_id))
+    })
+    .collect::<Vec<_>>();
+
+  mappings.sort_by_key(|(gacha_type, _)| *gacha_type);
+  mappings
+}
+
 #[derive(Clone, Debug, Serialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct PrettyGachaRecord {
@@ -343,17 +405,30 @@ impl PrettiedGachaRecords {
         acc
       });
 
+    // The unknown gacha types are merged into one category, so each of them is mapped.
+    let unknown_gacha_types = gacha_type_records
+      .keys()
+      .copied()
+      .filter(|gacha_type| PrettyCategory::from_gacha_type(&business, *gacha_type).is_none())
+      .collect::<Vec<_>>();
+
     let categorizeds =
       Self::compute_categorizeds(metadata, business, gacha_type_records, custom_locale)?;
     let aggregated = Self::compute_aggregated(business, records, &categorizeds);
-    let gacha_type_categories = categorizeds.values().fold(
-      HashMap::with_capacity(categorizeds.len()),
+    let mut gacha_type_categories = categorizeds.values().fold(
+      HashMap::with_capacity(categorizeds.len() + unknown_gacha_types.len()),
       |mut acc, categorized| {
         acc.insert(categorized.gacha_type, categorized.category);
         acc
       },
     );
 
+    gacha_type_categories.extend(
+      unknown_gacha_types
+        .into_iter()
+        .map(|gacha_type| (gacha_type, PrettyCategory::Unknown)),
+    );
+
     Ok(Self {
       business,
       uid,
@@ -418,6 +493,43 @@ impl PrettiedGachaRecords {
       );
     }
 
+    // The remaining records are of the unknown gacha types, fetched anyway.
+    if !gacha_type_records.is_empty() {
+      let category = PrettyCategory::Unknown;
+      let gacha_type = *gacha_type_records.keys().min().unwrap(); // SAFETY
+      let is_single_gacha_type = gacha_type_records.len() == 1;
+
+      let mut records = gacha_type_records
+        .into_values()
+        .flatten()
+        .collect::<Vec<_>>();
+      records.sort_by(|a, b| a.id.cmp(&b.id));
+
+      let total = records.len() as u64;
+      let start_time = records.first().map(|record| record.time);
+      let end_time = records.last().map(|record| record.time);
+      // The last id of the mixed gacha types is not the end id of any of them
+      let last_end_id = records
+        .last()
+        .filter(|_| is_single_gacha_type)
+        .map(|record| record.id.clone());
+      let rankings =
+        Self::compute_categorized_rankings(metadata, &category, records, custom_locale)?;
+
+      categorizeds.insert(
+        category,
+        CategorizedMetadata {
+          category,
+          total,
+          gacha_type,
+          start_time,
+          end_time,
+          last_end_id,
+          rankings,
+        },
+      );
+    }
+
     Ok(categorizeds)
   }
 
@@ -610,7 +722,13 @@ impl PrettiedGachaRecords {
 
     for categorized in categorizeds
       .values() // HACK: See the HACK note above
-      .filter(|categorized| categorized.category != PrettyCategory::Bangboo)
+      .filter(|categorized| {
+        // The pity of the unknown gacha types is not known either
+        !matches!(
+          categorized.category,
+          PrettyCategory::Bangboo | PrettyCategory::Unknown
+        )
+      })
     {
       blue_sum += categorized.rankings.blue.sum;
       // blue_values.extend_from_slice(&categorized.rankings.blue.values);
@@ -787,3 +905,108 @@ impl PrettiedGachaRecords {
     tags
   }
 }
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_compute_gacha_type_and_last_end_id_mappings() {
+    let mut records = vec![
+      GachaRecord::fixture("1001", 301),
+      GachaRecord::fixture("1003", 400),
+      GachaRecord::fixture("1002", 301),
+      GachaRecord::fixture("1004", 302),
+    ];
+
+    // 400 is requested together with 301
+    assert_eq!(
+      compute_gacha_type_and_last_end_id_mappings(Business::GenshinImpact, &records, true),
+      vec![
+        (100, None),
+        (200, None),
+        (301, Some("1003".into())),
+        (302, Some("1004".into())),
+        (500, None),
+      ]
+    );
+
+    // Beginner has reached the limit
+    records.extend((0..20).map(|n| GachaRecord::fixture(&format!("09{n:02}"), 100)));
+    let mappings =
+      compute_gacha_type_and_last_end_id_mappings(Business::GenshinImpact, &records, true);
+    assert_eq!(mappings[0], (200, None));
+
+    let mappings =
+      compute_gacha_type_and_last_end_id_mappings(Business::GenshinImpact, &records, false);
+    assert_eq!(mappings[0], (100, Some("0919".into())));
+  }
+
+  #[test]
+  fn test_pretty_unknown_gacha_types() {
+    let metadata = GachaMetadata::current();
+    let pretty = |records: &[GachaRecord]| {
+      PrettiedGachaRecords::pretty(
+        metadata,
+        Business::GenshinImpact,
+        100_000_001,
+        records,
+        None,
+      )
+      .unwrap()
+    };
+
+    // Single unknown gacha type
+    let prettied = pretty(&[
+      GachaRecord::fixture("1001", 301),
+      GachaRecord::fixture("1002", 999),
+      GachaRecord::fixture("1003", 999),
+    ]);
+
+    let unknown = &prettied.categorizeds[&PrettyCategory::Unknown];
+    assert_eq!(unknown.gacha_type, 999);
+    assert_eq!(unknown.total, 2);
+    assert_eq!(unknown.last_end_id.as_deref(), Some("1003"));
+    assert_eq!(prettied.categorizeds[&PrettyCategory::Character].total, 1);
+    assert_eq!(
+      prettied.gacha_type_categories[&999],
+      PrettyCategory::Unknown
+    );
+
+    // Mixed unknown gacha types, each of them is mapped
+    let prettied = pretty(&[
+      GachaRecord::fixture("1001", 1000),
+      GachaRecord::fixture("1002", 999),
+      GachaRecord::fixture("1003", 1000),
+    ]);
+
+    let unknown = &prettied.categorizeds[&PrettyCategory::Unknown];
+    assert_eq!(unknown.gacha_type, 999);
+    assert_eq!(unknown.total, 3);
+    assert_eq!(unknown.last_end_id, None);
+    assert_eq!(
+      prettied.gacha_type_categories[&999],
+      PrettyCategory::Unknown
+    );
+    assert_eq!(
+      prettied.gacha_type_categories[&1000],
+      PrettyCategory::Unknown
+    );
+
+    // No unknown bucket without the unknown records
+    let prettied = pretty(&[GachaRecord::fixture("1001", 301)]);
+    assert!(!prettied.categorizeds.contains_key(&PrettyCategory::Unknown));
+  }
+
+  #[test]
+  fn test_gacha_types_of_request() {
+    assert_eq!(
+      gacha_types_of_request(Business::GenshinImpact, 301),
+      [301, 400]
+    );
+    assert_eq!(gacha_types_of_request(Business::GenshinImpact, 302), [302]);
+    assert_eq!(gacha_types_of_request(Business::HonkaiStarRail, 11), [11]);
+  }
+}
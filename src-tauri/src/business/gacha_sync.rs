@@ -0,0 +1,400 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::database::{
+  AccountQuestioner, Database, GachaRecordQuestioner, GachaRecordSaveOnConflict, SqlxError,
+};
+use crate::models::{Account, Business};
+use crate::utilities::progress_sink::ProgressSink;
+
+use super::{
+  GachaRecordsFetcherError, GachaRecordsFetcherFragment, GachaRecordsStreaming, GachaUrlErrorKind,
+  GachaUrlTransport, ParsedGachaUrl, compute_gacha_type_and_last_end_id_mappings,
+  create_gacha_records_fetcher,
+};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind")]
+pub enum GachaRecordsSyncOutcome {
+  Synced { changes: i64 },
+  // No valid gacha url of the account, e.g. it has expired.
+  // The gacha records have to be opened in the game again.
+  Expired,
+  // Not an error, e.g. no new gacha records, or the game is not installed here.
+  Skipped { reason: serde_json::Value },
+  Failed { error: serde_json::Value },
+}
+
+impl GachaRecordsSyncOutcome {
+  // The skipped account is as same as synced, there is nothing to sync.
+  pub fn is_succeeded(&self) -> bool {
+    matches!(self, Self::Synced { .. } | Self::Skipped { .. })
+  }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaRecordsSyncResult {
+  pub business: Business,
+  pub uid: u32,
+  pub outcome: GachaRecordsSyncOutcome,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "PascalCase")] // Enum name
+pub enum GachaRecordsSyncFragment {
+  Account {
+    business: Business,
+    uid: u32,
+  },
+  Fetcher {
+    business: Business,
+    uid: u32,
+    fragment: GachaRecordsFetcherFragment,
+  },
+  Completed(GachaRecordsSyncResult),
+  Finished,
+}
+
+// Report the fragments of the fetcher on behalf of the account.
+struct AccountProgressSink<'a> {
+  business: Business,
+  uid: u32,
+  inner: &'a dyn ProgressSink<GachaRecordsSyncFragment>,
+}
+
+impl ProgressSink<GachaRecordsFetcherFragment> for AccountProgressSink<'_> {
+  fn report(&self, fragment: GachaRecordsFetcherFragment) {
+    self.inner.report(GachaRecordsSyncFragment::Fetcher {
+      business: self.business,
+      uid: self.uid,
+      fragment,
+    });
+  }
+}
+
+/// Sync the gacha records of every account into the database, one account at a time.
+/// The failure of an account does not stop the others, it is reported in its result.
+#[tracing::instrument(skip_all)]
+pub async fn sync_all_accounts(
+  database: &Arc<Database>,
+  transport: Arc<dyn GachaUrlTransport>,
+  progress_sink: &dyn ProgressSink<GachaRecordsSyncFragment>,
+) -> Result<Vec<GachaRecordsSyncResult>, SqlxError> {
+  let accounts = AccountQuestioner::find_accounts(database).await?;
+  Ok(sync_accounts(database, transport, accounts, progress_sink).await)
+}
+
+/// Same as [`sync_all_accounts`], but only the given accounts.
+#[tracing::instrument(skip_all, fields(accounts = accounts.len()))]
+pub async fn sync_accounts(
+  database: &Arc<Database>,
+  transport: Arc<dyn GachaUrlTransport>,
+  accounts: Vec<Account>,
+  progress_sink: &dyn ProgressSink<GachaRecordsSyncFragment>,
+) -> Vec<GachaRecordsSyncResult> {
+  info!(
+    "Syncing the gacha records of {} accounts...",
+    accounts.len()
+  );
+
+  let mut results = Vec::with_capacity(accounts.len());
+  for account in accounts {
+    let Account { business, uid, .. } = account;
+    progress_sink.report(GachaRecordsSyncFragment::Account { business, uid });
+
+    let account_progress_sink = AccountProgressSink {
+      business,
+      uid,
+      inner: progress_sink,
+    };
+
+    let outcome =
+      match sync_account(database, &transport, business, uid, &account_progress_sink).await {
+        Ok(changes) => {
+          info!(message = "Account synced", %business, uid, changes);
+          GachaRecordsSyncOutcome::Synced { changes }
+        }
+        Err(GachaRecordsFetcherError::GachaUrl(error))
+          if matches!(
+            error.as_ref(),
+            GachaUrlErrorKind::NotFound | GachaUrlErrorKind::AuthkeyTimeout
+          ) =>
+        {
+          info!(message = "Account skipped, no valid gacha url", %business, uid);
+          GachaRecordsSyncOutcome::Expired
+        }
+        Err(GachaRecordsFetcherError::GachaUrl(error))
+          if matches!(
+            error.as_ref(),
+            GachaUrlErrorKind::EmptyData | GachaUrlErrorKind::WebCachesNotFound { .. }
+          ) =>
+        {
+          info!(message = "Account skipped", %business, uid, %error);
+          GachaRecordsSyncOutcome::Skipped {
+            reason: serde_json::to_value(&*error.boxed()).unwrap_or_default(),
+          }
+        }
+        Err(error) => {
+          warn!(message = "Error syncing account", %business, uid, %error);
+          GachaRecordsSyncOutcome::Failed {
+            error: serde_json::to_value(&*error.boxed()).unwrap_or_default(),
+          }
+        }
+      };
+
+    let result = GachaRecordsSyncResult {
+      business,
+      uid,
+      outcome,
+    };
+
+    progress_sink.report(GachaRecordsSyncFragment::Completed(result.clone()));
+    results.push(result);
+  }
+
+  progress_sink.report(GachaRecordsSyncFragment::Finished);
+  results
+}
+
+// Incremental sync from the last end id of each gacha type in the database,
+// and the interrupted sync of the account is resumed.
+#[tracing::instrument(skip(database, transport, progress_sink))]
+async fn sync_account(
+  database: &Arc<Database>,
+  transport: &Arc<dyn GachaUrlTransport>,
+  business: Business,
+  uid: u32,
+  progress_sink: &dyn ProgressSink<GachaRecordsFetcherFragment>,
+) -> Result<i64, GachaRecordsFetcherError> {
+  let gacha_url = super::resolve_gacha_url(database, transport.as_ref(), business, uid).await?;
+  let region = ParsedGachaUrl::from_str(&gacha_url)?.biz.1;
+
+  let records =
+    GachaRecordQuestioner::find_gacha_records_by_business_and_uid(database, business, uid).await?;
+  let gacha_type_and_last_end_id_mappings =
+    compute_gacha_type_and_last_end_id_mappings(business, &records, true);
+
+  let gacha_url_creation_time =
+    super::stored_gacha_url_creation_time(database, business, uid, &gacha_url).await?;
+
+  let fetched = create_gacha_records_fetcher(
+    business,
+    region,
+    uid,
+    transport.clone(),
+    gacha_url.clone(),
+    gacha_type_and_last_end_id_mappings,
+    false,
+    progress_sink,
+    None,
+    Some(GachaRecordsStreaming::new(
+      database.clone(),
+      GachaRecordSaveOnConflict::Nothing,
+      false,
+      gacha_url_creation_time,
+    )),
+  )
+  .await;
+
+  super::settle_stored_gacha_url(database, business, uid, &gacha_url, &fetched).await;
+
+  Ok(fetched?.changes)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use time::OffsetDateTime;
+
+  use super::*;
+  use crate::business::{GachaUrlExchange, ReplayGachaUrlTransport};
+  use crate::database::{StoredGachaUrlQuestioner, StoredGachaUrlQuestionerAdditions};
+  use crate::utilities::progress_sink::ChannelProgressSink;
+  use crate::utilities::redact::redact_authkeys;
+
+  #[tokio::test]
+  async fn test_sync_all_accounts() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let database = Arc::new(Database::new_with(temp_dir.path().join("test.db")).await);
+
+    // Synced, expired and failed. The redacted urls differ in the language only.
+    // The last account has no stored gacha url, and no webcaches either.
+    let accounts = [
+      (100_000_001, "zh-cn", 0),
+      (100_000_002, "en-us", -101),
+      (100_000_003, "ja-jp", -1),
+    ];
+
+    let gacha_url = |lang: &str| {
+      format!(
+        "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey_ver=1&sign_type=2&authkey=SECRET&lang={lang}&region=cn_gf01&game_biz=hk4e_cn&gacha_type=301"
+      )
+    };
+
+    let exchange =
+      |lang: &str, gacha_type: &str, end_id: &str, response: serde_json::Value| GachaUrlExchange {
+        url: redact_authkeys(
+          ParsedGachaUrl::from_str(&gacha_url(lang))
+            .unwrap()
+            .to_request_url(Some(gacha_type), Some(end_id), None)
+            .as_str(),
+        )
+        .into_owned(),
+        response: response.to_string(),
+      };
+
+    let response = |retcode: i32, list: serde_json::Value| {
+      serde_json::json!({
+        "retcode": retcode,
+        "message": "Message",
+        "data": (retcode == 0).then(|| serde_json::json!({
+          "region": "cn_gf01",
+          "list": list,
+        })),
+      })
+    };
+
+    let item = serde_json::json!({
+      "id": "1000000000000000001",
+      "uid": "100000001",
+      "gacha_type": "301",
+      "rank_type": "3",
+      "count": "1",
+      "time": "2024-01-01 00:00:00",
+      "lang": "zh-cn",
+      "name": "Cool Steel",
+      "item_type": "Weapon",
+      "item_id": "11301",
+    });
+
+    let mut exchanges = Vec::new();
+    for (uid, lang, retcode) in accounts {
+      AccountQuestioner::create_account(
+        &database,
+        Business::GenshinImpact,
+        uid,
+        temp_dir.path().to_string_lossy().into_owned(),
+        None,
+      )
+      .await
+      .unwrap();
+
+      StoredGachaUrlQuestioner::upsert_stored_gacha_url(
+        &database,
+        Business::GenshinImpact,
+        uid,
+        ParsedGachaUrl::from_str(&gacha_url(lang))
+          .unwrap()
+          .to_url(None, None, None)
+          .to_string(),
+        OffsetDateTime::now_utc(),
+      )
+      .await
+      .unwrap();
+
+      for gacha_type in ["100", "200", "301", "302", "500"] {
+        let list = if retcode == 0 && gacha_type == "301" {
+          exchanges.push(exchange(
+            lang,
+            gacha_type,
+            "1000000000000000001",
+            response(0, serde_json::json!([])),
+          ));
+          serde_json::json!([item])
+        } else {
+          serde_json::json!([])
+        };
+
+        exchanges.push(exchange(lang, gacha_type, "0", response(retcode, list)));
+      }
+    }
+
+    AccountQuestioner::create_account(
+      &database,
+      Business::GenshinImpact,
+      100_000_004,
+      temp_dir.path().to_string_lossy().into_owned(),
+      None,
+    )
+    .await
+    .unwrap();
+
+    let (progress_sink, mut receiver) = ChannelProgressSink::<GachaRecordsSyncFragment>::new();
+    let results = sync_all_accounts(
+      &database,
+      Arc::new(ReplayGachaUrlTransport::new(exchanges)),
+      &progress_sink,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(results.len(), 4);
+    assert!(matches!(
+      results[0].outcome,
+      GachaRecordsSyncOutcome::Synced { changes: 1 }
+    ));
+    assert!(matches!(
+      results[1].outcome,
+      GachaRecordsSyncOutcome::Expired
+    ));
+    assert!(matches!(
+      results[2].outcome,
+      GachaRecordsSyncOutcome::Failed { .. }
+    ));
+    assert!(matches!(
+      &results[3].outcome,
+      GachaRecordsSyncOutcome::Skipped { reason }
+        if reason["details"]["kind"] == "WebCachesNotFound"
+    ));
+
+    // The stored gacha url of the expired account is deleted
+    assert!(
+      StoredGachaUrlQuestioner::find_stored_gacha_url_by_business_and_uid(
+        &database,
+        Business::GenshinImpact,
+        100_000_002
+      )
+      .await
+      .unwrap()
+      .is_none()
+    );
+
+    // The fragments of the fetcher are collapsed
+    let mut fragments = Vec::<String>::new();
+    while let Ok(fragment) = receiver.try_recv() {
+      let fragment = match fragment {
+        GachaRecordsSyncFragment::Account { uid, .. } => format!("Account({uid})"),
+        GachaRecordsSyncFragment::Fetcher { uid, .. } => format!("Fetcher({uid})"),
+        GachaRecordsSyncFragment::Completed(result) => format!("Completed({})", result.uid),
+        GachaRecordsSyncFragment::Finished => "Finished".into(),
+      };
+
+      if fragments.last() != Some(&fragment) {
+        fragments.push(fragment);
+      }
+    }
+
+    assert_eq!(
+      fragments,
+      [
+        "Account(100000001)",
+        "Fetcher(100000001)",
+        "Completed(100000001)",
+        "Account(100000002)",
+        "Fetcher(100000002)",
+        "Completed(100000002)",
+        "Account(100000003)",
+        "Fetcher(100000003)",
+        "Completed(100000003)",
+        "Account(100000004)",
+        "Completed(100000004)",
+        "Finished",
+      ]
+    );
+  }
+}
@@ -1,13 +1,11 @@
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Debug};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
 use std::time::Duration;
 
-use exponential_backoff::Backoff;
-use futures_util::FutureExt;
-use futures_util::future::BoxFuture;
 use regex::Regex;
 use serde::ser::SerializeStruct;
 use serde::{Deserialize, Serialize};
@@ -16,11 +14,18 @@ use time::{OffsetDateTime, PrimitiveDateTime};
 use tracing::{error, info, warn};
 use url::Url;
 
-use crate::business::disk_cache::{BlockFile, IndexFile};
+use crate::business::disk_cache::{
+  BlockFile, BlockFiles, DiskCacheFormat, IndexFile, SimpleEntryFile, SimpleIndexFile,
+};
+use crate::business::gacha_url_transport::GachaUrlTransport;
 use crate::business::{GachaMetadata, PrettyCategory, gacha_time_format};
 use crate::consts;
 use crate::error::declare_error_kinds;
-use crate::models::{BizInternals, Business, BusinessRegion, GachaRecord, ServerRegion};
+use crate::models::{
+  BizInternals, Business, BusinessRegion, GachaRecord, PendingGachaRecord, ServerRegion,
+};
+use crate::utilities::rate_limiter::{RateLimiter, RateLimiterSettings};
+use crate::utilities::redact::{REDACTED, RedactedUrl, redact_authkeys};
 use crate::utilities::serde_helper;
 
 declare_error_kinds! {
@@ -76,6 +81,11 @@ declare_error_kinds! {
       cause: reqwest::Error => cause.to_string()
     },
 
+    #[error("Error deserializing gacha url response: {cause}")]
+    DeserializeResponse {
+      cause: serde_json::Error => cause.to_string()
+    },
+
     #[error("Authkey timeout for gacha url")]
     AuthkeyTimeout,
 
@@ -88,13 +98,6 @@ declare_error_kinds! {
       message: String
     },
 
-    #[error("Missing metadata entry: {business}, locale: {locale}, name: {name}")]
-    MissingMetadataEntry {
-      business: Business,
-      locale: String,
-      name: String
-    },
-
     #[error("Owner uid of the gacha url does not match: {actuals:?} (Expected: {expected})")]
     InconsistentUid {
       expected: u32,
@@ -114,8 +117,17 @@ static REGEX_WEBCACHES_VERSION: LazyLock<Regex> = LazyLock::new(|| {
 });
 
 /// `WebCaches` version number. For example: `x.y.z` or `x.y.z.a`
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-struct WebCachesVersion(u8, u8, u8, Option<u8>);
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WebCachesVersion(u8, u8, u8, Option<u8>);
+
+impl Serialize for WebCachesVersion {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: serde::Serializer,
+  {
+    serializer.collect_str(self)
+  }
+}
 
 impl fmt::Display for WebCachesVersion {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -150,10 +162,49 @@ impl FromStr for WebCachesVersion {
   }
 }
 
-#[derive(Debug)]
+/// Find the newest `webCaches` version in the data folder.
+pub(crate) fn latest_webcaches_version(data_folder: impl AsRef<Path>) -> Option<WebCachesVersion> {
+  std::fs::read_dir(data_folder.as_ref().join("webCaches"))
+    .ok()?
+    .filter_map(Result::ok)
+    .filter(|entry| entry.path().is_dir())
+    .filter_map(|entry| WebCachesVersion::from_str(entry.file_name().to_str()?).ok())
+    .max()
+}
+
 pub struct DirtyGachaUrl {
   pub creation_time: Option<OffsetDateTime>,
   pub value: String,
+  /// Version of the webcaches where the url came from.
+  pub webcaches_version: Option<WebCachesVersion>,
+}
+
+impl Debug for DirtyGachaUrl {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("DirtyGachaUrl")
+      .field("creation_time", &self.creation_time)
+      .field("value", &RedactedUrl(&self.value))
+      .field("webcaches_version", &self.webcaches_version)
+      .finish()
+  }
+}
+
+/// Gacha urls read from the disk cache of the webcaches.
+#[derive(Debug, Default)]
+pub struct WebCachesGachaUrls {
+  pub urls: Vec<DirtyGachaUrl>,
+  /// Number of the cache entries skipped, because they are corrupted or being written.
+  pub skipped: usize,
+  /// Whether the index is unusable, and the urls are salvaged from the raw cache data.
+  pub salvaged: bool,
+}
+
+/// Keys of the disk cache entries, with the creation time if known.
+#[derive(Debug, Default)]
+struct DiskCacheKeys {
+  keys: Vec<(Option<OffsetDateTime>, String)>,
+  skipped: usize,
+  salvaged: bool,
 }
 
 impl DirtyGachaUrl {
@@ -161,20 +212,67 @@ impl DirtyGachaUrl {
   pub async fn from_webcaches(
     data_folder: impl AsRef<Path> + Debug,
     skip_expired: bool,
-  ) -> Result<Vec<Self>, GachaUrlError> {
+  ) -> Result<WebCachesGachaUrls, GachaUrlError> {
     info!("Reading gacha urls from webcaches...");
 
-    let cache_data_folder = Self::combie_cache_data_folder(data_folder).await?;
-    let gacha_urls = Self::read_cache_data_gacha_urls(cache_data_folder, skip_expired)?;
+    let cache_data_folders = Self::combie_cache_data_folders(data_folder).await?;
+
+    let mut merged = WebCachesGachaUrls::default();
+    let mut last_error = None;
+    let mut succeeded = false;
 
-    Ok(gacha_urls)
+    // After a game patch, the cache of the newest version is often empty,
+    // while a valid url still exists in the previous versions.
+    for (version, cache_data_folder) in cache_data_folders {
+      let gacha_urls = match Self::read_cache_data_gacha_urls(&cache_data_folder, skip_expired) {
+        Ok(gacha_urls) => gacha_urls,
+        Err(error) => {
+          warn!("Error reading gacha urls from webcaches {version}: {error:?}");
+          last_error.get_or_insert(error);
+          continue;
+        }
+      };
+
+      succeeded = true;
+      merged.skipped += gacha_urls.skipped;
+      merged.salvaged |= gacha_urls.salvaged;
+      merged
+        .urls
+        .extend(gacha_urls.urls.into_iter().map(|url| Self {
+          webcaches_version: Some(version.clone()),
+          ..url
+        }));
+    }
+
+    // All versions failed, then the first error is the cause
+    if !succeeded {
+      if let Some(error) = last_error {
+        return Err(error);
+      }
+    }
+
+    // The same url may exist in multiple versions, keep the newest version.
+    // Then sort by creation time desc
+    let mut seen = HashSet::with_capacity(merged.urls.len());
+    merged.urls.retain(|url| seen.insert(url.value.clone()));
+    merged.urls.sort_by_key(|url| Reverse(url.creation_time));
+
+    info!(
+      "Total number of gacha urls found in webcaches: {}",
+      merged.urls.len()
+    );
+
+    Ok(merged)
   }
 
+  /// Maximum number of the newest webcaches versions to search.
+  const WEBCACHES_MAX_VERSIONS: usize = 5;
+
   #[tracing::instrument]
-  async fn combie_cache_data_folder(
+  async fn combie_cache_data_folders(
     data_folder: impl AsRef<Path> + Debug,
-  ) -> Result<PathBuf, GachaUrlError> {
-    info!("Finding the webcaches data folder from the data folder");
+  ) -> Result<Vec<(WebCachesVersion, PathBuf)>, GachaUrlError> {
+    info!("Finding the webcaches data folders from the data folder");
 
     let webcaches_folder = data_folder.as_ref().join("webCaches");
     if !webcaches_folder.is_dir() {
@@ -207,106 +305,112 @@ impl DirtyGachaUrl {
       })?;
     }
 
-    // Sort by version asc
-    versions.sort();
-
-    // Get the latest version
-    let latest_version = versions.last().unwrap().to_string(); // SAFETY
-    info!("Retrieve the latest version of webcaches: {latest_version}");
+    // Sort by version desc, and get the newest versions
+    versions.sort_by(|a, b| b.cmp(a));
+    versions.truncate(Self::WEBCACHES_MAX_VERSIONS);
+    info!("Retrieve the newest versions of webcaches: {versions:?}");
 
     Ok(
-      webcaches_folder
-        .join(latest_version)
-        .join("Cache")
-        .join("Cache_Data"),
+      versions
+        .into_iter()
+        .map(|version| {
+          let cache_data_folder = webcaches_folder
+            .join(version.to_string())
+            .join("Cache")
+            .join("Cache_Data");
+
+          (version, cache_data_folder)
+        })
+        .collect(),
     )
   }
 
+  /// Newest modification time of the webcaches cache data,
+  /// e.g. it is modified once the gacha records are opened in the game.
+  pub async fn webcaches_modified(
+    data_folder: impl AsRef<Path> + Debug,
+  ) -> Result<Option<OffsetDateTime>, GachaUrlError> {
+    let cache_data_folders = Self::combie_cache_data_folders(data_folder).await?;
+
+    let mut modified = None;
+    for (_, cache_data_folder) in cache_data_folders {
+      let Ok(mut walk_dir) = tokio::fs::read_dir(&cache_data_folder).await else {
+        continue;
+      };
+
+      // The folder itself is modified once the entries are created or deleted,
+      // and the files of the block file are modified in place.
+      let mut times = Vec::new();
+      if let Ok(time) = tokio::fs::metadata(&cache_data_folder)
+        .await
+        .and_then(|metadata| metadata.modified())
+      {
+        times.push(time);
+      }
+
+      while let Ok(Some(entry)) = walk_dir.next_entry().await {
+        if let Ok(time) = entry
+          .metadata()
+          .await
+          .and_then(|metadata| metadata.modified())
+        {
+          times.push(time);
+        }
+      }
+
+      modified = modified.max(times.into_iter().max().map(OffsetDateTime::from));
+    }
+
+    Ok(modified)
+  }
+
   #[tracing::instrument]
   fn read_cache_data_gacha_urls(
     cache_data_folder: impl AsRef<Path> + Debug,
     skip_expired: bool,
-  ) -> Result<Vec<Self>, GachaUrlError> {
+  ) -> Result<WebCachesGachaUrls, GachaUrlError> {
     info!("Starting to read disk cache gacha urls...");
     let cache_data_folder = cache_data_folder.as_ref();
 
-    info!("Reading index file...");
-    let index_file = cache_data_folder.join("index");
-    let index_file =
-      IndexFile::from_file(&index_file).map_err(|cause| GachaUrlErrorKind::ReadDiskCache {
-        path: index_file,
-        cause,
-      })?;
-
-    info!("Reading block data_1 file...");
-    let block_file1_path = cache_data_folder.join("data_1");
-    let block_file1 = BlockFile::from_file(&block_file1_path).map_err(|cause| {
-      GachaUrlErrorKind::ReadDiskCache {
-        path: block_file1_path.clone(),
-        cause,
+    let format = match DiskCacheFormat::detect(cache_data_folder) {
+      Ok(format) => format,
+      // The index file of the block file cache may be corrupted,
+      // but the block data can still be salvaged.
+      Err(cause)
+        if cause.kind() != std::io::ErrorKind::NotFound
+          && cache_data_folder.join("data_2").is_file() =>
+      {
+        warn!("Error detecting disk cache format, fallback to block file: {cause}");
+        DiskCacheFormat::BlockFile
       }
-    })?;
-
-    info!("Reading block data_2 file...");
-    let block_file2_path = cache_data_folder.join("data_2");
-    let block_file2 = BlockFile::from_file(&block_file2_path).map_err(|cause| {
-      GachaUrlErrorKind::ReadDiskCache {
-        path: block_file2_path.clone(),
-        cause,
+      Err(cause) => {
+        return Err(GachaUrlErrorKind::ReadDiskCache {
+          path: cache_data_folder.join("index"),
+          cause,
+        })?;
       }
-    })?;
+    };
+
+    info!("Detected disk cache format: {format:?}");
+    let DiskCacheKeys {
+      keys,
+      skipped,
+      salvaged,
+    } = match format {
+      DiskCacheFormat::BlockFile => Self::read_block_file_cache_keys(cache_data_folder)?,
+      DiskCacheFormat::Simple => Self::read_simple_cache_keys(cache_data_folder)?,
+    };
 
     let mut urls = Vec::new();
     let now_local = OffsetDateTime::now_utc().to_offset(*consts::LOCAL_OFFSET);
 
-    info!("Foreach the cache address table of the index file...");
-    for addr in index_file.table {
-      // The previous places should not print logs.
-      // Because the table of cache address is too large.
-      //debug!("Read the entry store at cache address: {addr:?}");
-
-      // Read the entry store from the data_1 block file by cache address
-      let entry_store =
-        block_file1
-          .read_entry_store(&addr)
-          .map_err(|cause| GachaUrlErrorKind::ReadDiskCache {
-            path: block_file1_path.join(format!("{addr:?}")),
-            cause,
-          })?;
-
-      // Gacha url must be a long key and stored in the data_2 block file,
-      // So the long key of entry store must not be zero.
-      if !entry_store.has_long_key() {
-        continue;
-      }
-
-      // Maybe the long key points to data_3 or something else
-      // See: https://github.com/lgou2w/HoYo.Gacha/issues/15
-      if entry_store.long_key.file_number() != block_file2.header.this_file as u32 {
-        continue;
-      }
-
-      // Convert creation time
-      let creation_time = {
-        let timestamp = (entry_store.creation_time / 1_000_000) as i64 - 11_644_473_600;
-        OffsetDateTime::from_unix_timestamp(timestamp)
-          .unwrap() // FIXME: SAFETY?
-          .to_offset(*consts::LOCAL_OFFSET)
-      };
-
+    for (creation_time, url) in keys {
       // By default, this gacha url is valid for 1 day.
-      if skip_expired && creation_time + time::Duration::DAY < now_local {
+      // The creation time of the salvaged url is unknown, so it is kept.
+      if skip_expired && creation_time.is_some_and(|time| time + time::Duration::DAY < now_local) {
         continue; // It's expired
       }
 
-      // Read the long key of entry store from the data_2 block file
-      let url = entry_store.read_long_key(&block_file2).map_err(|cause| {
-        GachaUrlErrorKind::ReadDiskCache {
-          path: block_file2_path.join(format!("{:?}", entry_store.long_key)),
-          cause,
-        }
-      })?;
-
       // These url start with '1/0/', only get the later part
       let url = if let Some(stripped) = url.strip_prefix("1/0/") {
         stripped
@@ -320,27 +424,225 @@ impl DirtyGachaUrl {
       }
 
       info!(
-        message = "Valid gacha url exist in the cache address",
-        ?addr,
-        ?entry_store.long_key,
+        message = "Valid gacha url exist in the disk cache",
         ?creation_time,
-        url
+        url = %RedactedUrl(url)
       );
 
       urls.push(Self {
-        creation_time: Some(creation_time),
+        creation_time,
         value: url.to_owned(),
+        webcaches_version: None,
       });
     }
 
     // Sort by creation time desc
     urls.sort_by(|a, b| b.creation_time.cmp(&a.creation_time));
 
-    info!("Total number of gacha urls found: {}", urls.len());
-    Ok(urls)
+    info!(
+      message = "Total number of gacha urls found",
+      urls = urls.len(),
+      skipped,
+      salvaged
+    );
+
+    Ok(WebCachesGachaUrls {
+      urls,
+      skipped,
+      salvaged,
+    })
+  }
+
+  /// Read the long keys and creation time of the entries in the block file cache.
+  fn read_block_file_cache_keys(cache_data_folder: &Path) -> Result<DiskCacheKeys, GachaUrlError> {
+    info!("Reading index file...");
+    let index_file_path = cache_data_folder.join("index");
+    let index_file = match IndexFile::from_file(&index_file_path) {
+      Ok(index_file) => index_file,
+      Err(cause) if cause.kind() != std::io::ErrorKind::NotFound => {
+        warn!("Index file is unusable, salvage from the raw block data: {cause}");
+        return Self::salvage_block_file_cache_keys(cache_data_folder);
+      }
+      Err(cause) => {
+        return Err(GachaUrlErrorKind::ReadDiskCache {
+          path: index_file_path,
+          cause,
+        })?;
+      }
+    };
+
+    // Entry stores are in the data_1 block file, and the long keys may be in
+    // any other block file or separate file. They are opened on demand.
+    let mut block_files = BlockFiles::new(cache_data_folder);
+    let mut keys = DiskCacheKeys::default();
+
+    info!("Foreach the cache address table of the index file...");
+    for addr in index_file.table {
+      // The previous places should not print logs.
+      // Because the table of cache address is too large.
+      //debug!("Read the entry store at cache address: {addr:?}");
+
+      // Read the entry store from the block file by cache address.
+      // When the game is running or crashed, the entry may be corrupted.
+      let entry_store = match block_files.read_entry_store(&addr) {
+        Ok(entry_store) => entry_store,
+        Err(cause) => {
+          warn!("Skip the entry store at cache address {addr:?}: {cause}");
+          keys.skipped += 1;
+          continue;
+        }
+      };
+
+      // Gacha url must be a long key, So the long key of entry store must not be zero.
+      if !entry_store.has_long_key() {
+        continue;
+      }
+
+      // Read the long key of entry store from the block file or separate file.
+      // See: https://github.com/lgou2w/HoYo.Gacha/issues/15
+      let url = match entry_store.read_long_key(&mut block_files) {
+        Ok(url) => url,
+        Err(cause) => {
+          warn!(
+            "Skip the long key of the entry store at cache address {:?} in {:?}: {cause}",
+            entry_store.long_key,
+            block_files.file_path(&entry_store.long_key)
+          );
+          keys.skipped += 1;
+          continue;
+        }
+      };
+
+      keys
+        .keys
+        .push((chromium_time_to_local(entry_store.creation_time), url));
+    }
+
+    Ok(keys)
+  }
+
+  /// Salvage the long keys from the raw data of the data_2 block file,
+  /// where the gacha urls are mostly stored. The creation time is unknown.
+  fn salvage_block_file_cache_keys(
+    cache_data_folder: &Path,
+  ) -> Result<DiskCacheKeys, GachaUrlError> {
+    info!("Salvaging block data_2 file...");
+    let block_file2_path = cache_data_folder.join("data_2");
+    let block_file2 = BlockFile::from_file(&block_file2_path).map_err(|cause| {
+      GachaUrlErrorKind::ReadDiskCache {
+        path: block_file2_path,
+        cause,
+      }
+    })?;
+
+    let mut seen = HashSet::new();
+    let keys = block_file2
+      .scan_strings()
+      .filter_map(|string| {
+        string
+          .find("https://")
+          .map(|start| string[start..].to_owned())
+      })
+      .filter(|url| REGEX_GACHA_URL.is_match(url) && seen.insert(url.clone()))
+      .map(|url| (None, url))
+      .collect();
+
+    Ok(DiskCacheKeys {
+      keys,
+      skipped: 0,
+      salvaged: true,
+    })
+  }
+
+  /// Read the keys and last used time of the entries in the simple cache.
+  ///
+  /// The index is written lazily, so the entry files are enumerated from the folder,
+  /// and the index is only used to look up the last used time of the entries.
+  fn read_simple_cache_keys(cache_data_folder: &Path) -> Result<DiskCacheKeys, GachaUrlError> {
+    info!("Reading simple index file...");
+    let index_file_path = cache_data_folder.join("index-dir").join("the-real-index");
+    let (last_used_times, salvaged) = match SimpleIndexFile::from_file(&index_file_path) {
+      Ok(index_file) => (
+        index_file
+          .entries
+          .into_iter()
+          .map(|entry| (entry.file_name(), entry.last_used_time))
+          .collect::<HashMap<_, _>>(),
+        false,
+      ),
+      Err(cause) => {
+        warn!("Simple index file is unusable, the last used time is unknown: {cause}");
+        (HashMap::new(), true)
+      }
+    };
+
+    let entry_files = Self::simple_cache_entry_files(cache_data_folder)?;
+    let mut keys = DiskCacheKeys {
+      keys: Vec::with_capacity(entry_files.len()),
+      skipped: 0,
+      salvaged,
+    };
+
+    info!("Foreach the entry files of the simple cache...");
+    for file_name in entry_files {
+      // The simple cache does not record the creation time of the entry,
+      // so the last used time is the closest.
+      let last_used_time = last_used_times
+        .get(&file_name)
+        .and_then(|&time| u64::try_from(time).ok())
+        .and_then(chromium_time_to_local);
+
+      let entry_file_path = cache_data_folder.join(file_name);
+      let entry_file = match SimpleEntryFile::from_file(&entry_file_path) {
+        Ok(entry_file) => entry_file,
+        // The entry has been evicted after the folder is enumerated
+        Err(cause) if cause.kind() == std::io::ErrorKind::NotFound => continue,
+        Err(cause) => {
+          warn!("Skip the simple entry file {entry_file_path:?}: {cause}");
+          keys.skipped += 1;
+          continue;
+        }
+      };
+
+      keys
+        .keys
+        .push((last_used_time, entry_file.read_key().into_owned()));
+    }
+
+    Ok(keys)
+  }
+
+  /// File names of all the entry files `<hash>_0` in the simple cache folder.
+  fn simple_cache_entry_files(cache_data_folder: &Path) -> Result<Vec<String>, GachaUrlError> {
+    let entries =
+      std::fs::read_dir(cache_data_folder).map_err(|cause| GachaUrlErrorKind::ReadDiskCache {
+        path: cache_data_folder.to_owned(),
+        cause,
+      })?;
+
+    Ok(
+      entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|file_name| {
+          file_name
+            .strip_suffix("_0")
+            .is_some_and(|hash| hash.len() == 16 && u64::from_str_radix(hash, 16).is_ok())
+        })
+        .collect(),
+    )
   }
 }
 
+/// Convert the Chromium time (microseconds since 1601-01-01) to the local time.
+/// None if the time is out of range, e.g. a corrupted entry.
+fn chromium_time_to_local(time: u64) -> Option<OffsetDateTime> {
+  let timestamp = i64::try_from(time / 1_000_000).ok()? - 11_644_473_600;
+  OffsetDateTime::from_unix_timestamp(timestamp)
+    .ok()
+    .and_then(|time| time.checked_to_offset(*consts::LOCAL_OFFSET))
+}
+
 #[derive(Debug)]
 pub struct GachaUrl {
   pub url: ParsedGachaUrl,
@@ -376,35 +678,50 @@ impl Serialize for GachaUrl {
 impl GachaUrl {
   // Read all valid gacha urls from the webcaches data folder
   // and check for timeliness and consistency to get the latest gacha url.
-  #[tracing::instrument]
+  #[tracing::instrument(skip(transport))]
   pub async fn from_webcaches(
+    transport: &dyn GachaUrlTransport,
     data_folder: impl AsRef<Path> + Debug,
     expected_uid: u32,
   ) -> Result<Self, GachaUrlError> {
-    let dirty_urls = DirtyGachaUrl::from_webcaches(
+    let WebCachesGachaUrls {
+      urls: dirty_urls,
+      skipped,
+      salvaged,
+    } = DirtyGachaUrl::from_webcaches(
       data_folder,
       true, // No need for expired urls
     )
     .await?;
 
-    Self::consistency_check(dirty_urls, expected_uid, false).await
+    if skipped > 0 || salvaged {
+      warn!("Disk cache is incomplete, skipped entries: {skipped}, salvaged: {salvaged}");
+    }
+
+    Self::consistency_check(transport, dirty_urls, expected_uid, false).await
   }
 
   // Verifying timeliness and consistency from a dirty gacha url
-  #[tracing::instrument]
-  pub async fn from_dirty(dirty_url: String, expected_uid: u32) -> Result<Self, GachaUrlError> {
+  #[tracing::instrument(skip(transport, dirty_url), fields(dirty_url = %RedactedUrl(&dirty_url)))]
+  pub async fn from_dirty(
+    transport: &dyn GachaUrlTransport,
+    dirty_url: String,
+    expected_uid: u32,
+  ) -> Result<Self, GachaUrlError> {
     let dirty_urls = vec![DirtyGachaUrl {
       // Because the creation time is not known from the dirty gacha url.
       // The server will not return the creation time.
       creation_time: None,
       value: dirty_url,
+      webcaches_version: None,
     }];
 
-    Self::consistency_check(dirty_urls, expected_uid, true).await
+    Self::consistency_check(transport, dirty_urls, expected_uid, true).await
   }
 
-  #[tracing::instrument(skip(dirty_urls), fields(urls = dirty_urls.len(), ?expected_uid))]
+  #[tracing::instrument(skip(transport, dirty_urls), fields(urls = dirty_urls.len(), ?expected_uid))]
   async fn consistency_check(
+    transport: &dyn GachaUrlTransport,
     dirty_urls: Vec<DirtyGachaUrl>,
     expected_uid: u32,
     spread: bool,
@@ -427,9 +744,8 @@ impl GachaUrl {
         }
       };
 
-      let url = parsed.to_url(None, None, Some(1));
-      let response = match request_gacha_url_with_retry(url, None).await {
-        Ok(response) => response,
+      let owner_uid = match Self::probe_owner_uid(transport, &parsed).await {
+        Ok(owner_uid) => owner_uid,
         Err(error) => {
           warn!("Error requesting gacha url: {error:?}");
           if spread {
@@ -440,11 +756,7 @@ impl GachaUrl {
         }
       };
 
-      let Some(record) = response.data.as_ref().and_then(|page| page.list.first()) else {
-        // It's possible. For example:
-        //   There are no gacha records.
-        //   Server is not synchronising data (1 hour delay or more)
-        warn!("Gacha url responded with empty record data");
+      let Some(owner_uid) = owner_uid else {
         contains_empty = true;
 
         // FIXME: continue may not be secure.
@@ -453,12 +765,13 @@ impl GachaUrl {
         continue;
       };
 
-      if record.uid == expected_uid {
+      if owner_uid == expected_uid {
         info!(
           message = "Capture the gacha url with the expected uid",
           expected_uid,
           creation_time = ?dirty.creation_time,
-          url = ?dirty.value,
+          webcaches_version = ?dirty.webcaches_version,
+          url = %RedactedUrl(&dirty.value),
         );
 
         return Ok(GachaUrl {
@@ -468,7 +781,7 @@ impl GachaUrl {
         });
       } else {
         // The gacha url does not match the expected uid
-        actuals.insert(record.uid);
+        actuals.insert(owner_uid);
       }
     }
 
@@ -498,9 +811,113 @@ impl GachaUrl {
       })?
     }
   }
+
+  // Request the first record of the gacha url to get the owner uid.
+  async fn probe_owner_uid(
+    transport: &dyn GachaUrlTransport,
+    parsed: &ParsedGachaUrl,
+  ) -> Result<Option<u32>, GachaUrlError> {
+    let url = parsed.to_request_url(None, None, Some(1));
+    let response = request_gacha_url_with_retry(transport, url, None).await?;
+
+    match response.data.as_ref().and_then(|page| page.list.first()) {
+      Some(record) => Ok(Some(record.uid)),
+      None => {
+        // It's possible. For example:
+        //   There are no gacha records.
+        //   Server is not synchronising data (1 hour delay or more)
+        warn!("Gacha url responded with empty record data");
+        Ok(None)
+      }
+    }
+  }
+
+  // Probe all valid gacha urls from the webcaches data folder,
+  // and get the latest gacha url of each owner.
+  #[tracing::instrument(skip(transport))]
+  pub async fn discover_from_webcaches(
+    transport: &dyn GachaUrlTransport,
+    data_folder: impl AsRef<Path> + Debug,
+  ) -> Result<Vec<Self>, GachaUrlError> {
+    let WebCachesGachaUrls {
+      urls: dirty_urls,
+      skipped,
+      salvaged,
+    } = DirtyGachaUrl::from_webcaches(
+      data_folder,
+      true, // No need for expired urls
+    )
+    .await?;
+
+    if skipped > 0 || salvaged {
+      warn!("Disk cache is incomplete, skipped entries: {skipped}, salvaged: {salvaged}");
+    }
+
+    Ok(Self::discover(transport, dirty_urls).await)
+  }
+
+  #[tracing::instrument(skip_all, fields(urls = dirty_urls.len()))]
+  async fn discover(
+    transport: &dyn GachaUrlTransport,
+    dirty_urls: Vec<DirtyGachaUrl>,
+  ) -> Vec<Self> {
+    info!("Discover the owners of gacha urls...");
+
+    let mut probed_authkeys = HashSet::with_capacity(dirty_urls.len());
+    let mut owners = HashSet::new();
+    let mut discovered = Vec::new();
+
+    // Dirty urls are sorted by creation time desc,
+    // so the first gacha url of each owner is the latest.
+    for dirty in dirty_urls {
+      let parsed = match ParsedGachaUrl::from_str(&dirty.value) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+          warn!("Error parsing gacha url: {error:?}");
+          continue;
+        }
+      };
+
+      // The authkey belongs to only one account, no need to probe it again.
+      if !probed_authkeys.insert((parsed.biz, parsed.authkey.clone())) {
+        continue;
+      }
+
+      let owner_uid = match Self::probe_owner_uid(transport, &parsed).await {
+        Ok(Some(owner_uid)) => owner_uid,
+        Ok(None) => continue,
+        Err(error) => {
+          warn!("Error requesting gacha url: {error:?}");
+          continue;
+        }
+      };
+
+      if owners.insert((parsed.biz.0, owner_uid)) {
+        info!(
+          message = "Discover the owner of gacha url",
+          biz = ?parsed.biz,
+          owner_uid,
+          creation_time = ?dirty.creation_time,
+        );
+
+        discovered.push(GachaUrl {
+          url: parsed,
+          owner_uid,
+          creation_time: dirty.creation_time,
+        });
+      }
+    }
+
+    info!(
+      "Total number of gacha url owners discovered: {}",
+      discovered.len()
+    );
+
+    discovered
+  }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(PartialEq, Eq)]
 pub struct ParsedGachaUrl {
   pub biz: (Business, BusinessRegion),
   // Required params
@@ -513,6 +930,42 @@ pub struct ParsedGachaUrl {
   pub gacha_type: (&'static str, String), // key:val
 }
 
+// The authkey is the credential of the account, it must never be leaked.
+// See: crate::utilities::redact
+
+impl Debug for ParsedGachaUrl {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ParsedGachaUrl")
+      .field("biz", &self.biz)
+      .field("sign_type", &self.sign_type)
+      .field("authkey_ver", &self.authkey_ver)
+      .field("authkey", &REDACTED)
+      .field("game_biz", &self.game_biz)
+      .field("region", &self.region)
+      .field("lang", &self.lang)
+      .field("gacha_type", &self.gacha_type)
+      .finish()
+  }
+}
+
+impl Serialize for ParsedGachaUrl {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: serde::Serializer,
+  {
+    let mut state = serializer.serialize_struct("ParsedGachaUrl", 8)?;
+    state.serialize_field("biz", &self.biz)?;
+    state.serialize_field("sign_type", &self.sign_type)?;
+    state.serialize_field("authkey_ver", &self.authkey_ver)?;
+    state.serialize_field("authkey", REDACTED)?;
+    state.serialize_field("game_biz", &self.game_biz)?;
+    state.serialize_field("region", &self.region)?;
+    state.serialize_field("lang", &self.lang)?;
+    state.serialize_field("gacha_type", &self.gacha_type)?;
+    state.end()
+  }
+}
+
 impl FromStr for ParsedGachaUrl {
   type Err = GachaUrlError;
 
@@ -520,7 +973,7 @@ impl FromStr for ParsedGachaUrl {
     let query_start = dirty.find('?');
     if query_start.is_none() || !REGEX_GACHA_URL.is_match(dirty) {
       return Err(GachaUrlErrorKind::IllegalUrl {
-        url: dirty.to_owned(),
+        url: redact_authkeys(dirty).into_owned(),
       })?;
     }
 
@@ -550,10 +1003,11 @@ impl FromStr for ParsedGachaUrl {
     let lang = required_param!("lang").to_owned();
 
     // Game Business
-    let biz = BizInternals::from_codename(&game_biz).ok_or(GachaUrlErrorKind::IllegalGameBiz {
-      url: dirty.to_owned(),
-      value: game_biz.clone(),
-    })?;
+    let biz =
+      BizInternals::from_codename(&game_biz).ok_or_else(|| GachaUrlErrorKind::IllegalGameBiz {
+        url: redact_authkeys(dirty).into_owned(),
+        value: game_biz.clone(),
+      })?;
 
     let (gacha_type_field, init_gacha_type_field) = biz.gacha_type_fields();
     let gacha_type = queries
@@ -562,7 +1016,8 @@ impl FromStr for ParsedGachaUrl {
       .or(queries.get(init_gacha_type_field).cloned())
       .ok_or_else(|| {
         warn!(
-          "Gacha url missing important '{gacha_type_field}' or '{init_gacha_type_field}' parameters: {queries:?}"
+          "Gacha url missing important '{gacha_type_field}' or '{init_gacha_type_field}' parameters: {:?}",
+          queries.keys()
         );
 
         GachaUrlErrorKind::InvalidParams {
@@ -584,11 +1039,35 @@ impl FromStr for ParsedGachaUrl {
 }
 
 impl ParsedGachaUrl {
+  /// Url on the official endpoint, e.g. to store or to return to the frontend.
+  /// It is always parsed again, regardless of the endpoint overrides.
   pub fn to_url(
     &self,
     gacha_type: Option<&str>,
     end_id: Option<&str>,
     page_size: Option<u8>,
+  ) -> Url {
+    let biz = BizInternals::mapped(self.biz.0, self.biz.1);
+    self.to_url_on(biz.base_gacha_url.to_owned(), gacha_type, end_id, page_size)
+  }
+
+  /// Url of the actual request, on the endpoint override or the mirror if any.
+  pub fn to_request_url(
+    &self,
+    gacha_type: Option<&str>,
+    end_id: Option<&str>,
+    page_size: Option<u8>,
+  ) -> Url {
+    let biz = BizInternals::mapped(self.biz.0, self.biz.1);
+    self.to_url_on(super::base_gacha_url(biz), gacha_type, end_id, page_size)
+  }
+
+  fn to_url_on(
+    &self,
+    base_gacha_url: String,
+    gacha_type: Option<&str>,
+    end_id: Option<&str>,
+    page_size: Option<u8>,
   ) -> Url {
     let Self {
       biz,
@@ -608,11 +1087,11 @@ impl ParsedGachaUrl {
       && let Some(category) = PrettyCategory::from_gacha_type(&biz.business, value)
       && category.is_hkrpg_collaboration()
     {
-      biz.base_gacha_url_to_hkrpg_collaboration()
+      biz.base_gacha_url_to_hkrpg_collaboration(&base_gacha_url)
     } else {
       None
     }
-    .unwrap_or(biz.base_gacha_url.to_owned());
+    .unwrap_or(base_gacha_url);
 
     Url::parse_with_params(
       &base_gacha_url,
@@ -677,20 +1156,69 @@ struct GachaRecordsPagination {
   region: String,
 }
 
-#[tracing::instrument(skip(url))]
+// region: Rate limiter
+
+static GACHA_URL_RATE_LIMITER_SETTINGS: LazyLock<RwLock<RateLimiterSettings>> =
+  LazyLock::new(Default::default);
+
+// Shared by all requests of the same api host, e.g. syncing several accounts.
+static GACHA_URL_RATE_LIMITERS: LazyLock<Mutex<HashMap<String, Arc<RateLimiter>>>> =
+  LazyLock::new(Default::default);
+
+fn gacha_url_rate_limiter(url: &Url) -> Arc<RateLimiter> {
+  let host = url.host_str().unwrap_or_default();
+  GACHA_URL_RATE_LIMITERS
+    .lock()
+    .unwrap()
+    .entry(host.to_owned())
+    .or_insert_with(|| {
+      let settings = GACHA_URL_RATE_LIMITER_SETTINGS.read().unwrap().clone();
+      Arc::new(RateLimiter::new(settings))
+    })
+    .clone()
+}
+
+pub fn gacha_url_rate_limiter_settings() -> RateLimiterSettings {
+  GACHA_URL_RATE_LIMITER_SETTINGS.read().unwrap().clone()
+}
+
+#[tracing::instrument]
+pub fn set_gacha_url_rate_limiter_settings(settings: RateLimiterSettings) -> RateLimiterSettings {
+  let settings = settings.sanitized();
+  info!("Setting the gacha url rate limiter");
+  for rate_limiter in GACHA_URL_RATE_LIMITERS.lock().unwrap().values() {
+    rate_limiter.reconfigure(settings.clone());
+  }
+
+  *GACHA_URL_RATE_LIMITER_SETTINGS.write().unwrap() = settings.clone();
+  settings
+}
+
+/// Whether the next request of the gacha url has to wait for the rate limiter.
+pub fn is_gacha_url_rate_limited(gacha_url: &str, gacha_type: Option<&str>) -> bool {
+  ParsedGachaUrl::from_str(gacha_url).is_ok_and(|parsed| {
+    let url = parsed.to_request_url(gacha_type, None, None);
+    !gacha_url_rate_limiter(&url).is_available()
+  })
+}
+
+// endregion
+
+#[tracing::instrument(skip(transport, url))]
 async fn request_gacha_url(
+  transport: &dyn GachaUrlTransport,
   url: Url,
   timeout: Option<Duration>,
 ) -> Result<GachaRecordsResponse, GachaUrlError> {
-  let response: GachaRecordsResponse = consts::REQWEST
-    .get(url)
-    .timeout(timeout.unwrap_or(Duration::from_secs(10)))
-    .send()
-    .await
-    .map_err(|cause| GachaUrlErrorKind::Reqwest { cause })?
-    .json()
-    .await
-    .map_err(|cause| GachaUrlErrorKind::Reqwest { cause })?;
+  let rate_limiter = gacha_url_rate_limiter(&url);
+  rate_limiter.acquire().await;
+
+  let response = transport
+    .get(&url, timeout.unwrap_or(Duration::from_secs(10)))
+    .await?;
+
+  let response: GachaRecordsResponse = serde_json::from_str(&response)
+    .map_err(|cause| GachaUrlErrorKind::DeserializeResponse { cause })?;
 
   if response.retcode != 0 {
     let retcode = response.retcode;
@@ -699,6 +1227,7 @@ async fn request_gacha_url(
     if retcode == -101 || message.contains("authkey") || message.contains("auth key") {
       Err(GachaUrlErrorKind::AuthkeyTimeout)?
     } else if retcode == -110 || message.contains("visit too frequently") {
+      rate_limiter.throttled();
       Err(GachaUrlErrorKind::VisitTooFrequently)?
     } else {
       Err(GachaUrlErrorKind::UnexpectedResponse {
@@ -707,65 +1236,69 @@ async fn request_gacha_url(
       })?
     }
   } else {
+    rate_limiter.succeeded();
     Ok(response)
   }
 }
 
-#[tracing::instrument(skip(url))]
-fn request_gacha_url_with_retry(
+#[tracing::instrument(skip(transport, url))]
+async fn request_gacha_url_with_retry(
+  transport: &dyn GachaUrlTransport,
   url: Url,
   retries: Option<u8>,
-) -> BoxFuture<'static, Result<GachaRecordsResponse, GachaUrlError>> {
+) -> Result<GachaRecordsResponse, GachaUrlError> {
   // HACK: Default maximum 5 attempts
   const RETRIES: u8 = 5;
-  const MIN: Duration = Duration::from_millis(200); // Min: 0.2s
-  const MAX: Duration = Duration::from_millis(5000); // Max: 5s
+  const TIMEOUT: Duration = Duration::from_secs(8);
 
   let retries = retries.unwrap_or(RETRIES);
-  let backoff = Backoff::new(retries as u32, MIN, MAX);
-  let timeout = MAX + Duration::from_secs(3);
-
-  async move {
-    for duration in &backoff {
-      match request_gacha_url(url.clone(), Some(timeout)).await {
-        // okay
-        Ok(response) => return Ok(response),
-
-        // Wait and retry only if the error is VisitTooFrequently.
-        Err(error) if matches!(error.as_ref(), GachaUrlErrorKind::VisitTooFrequently) => {
-          warn!("Requesting gacha url visit too frequently, retry...");
-          if let Some(duration) = duration {
-            tokio::time::sleep(duration).await;
-          }
-          continue;
-        }
 
-        // Other errors are returned
-        Err(error) => return Err(error),
+  for _ in 0..retries {
+    match request_gacha_url(transport, url.clone(), Some(TIMEOUT)).await {
+      // okay
+      Ok(response) => return Ok(response),
+
+      // Retry only if the error is VisitTooFrequently.
+      // The rate limiter has slowed down, so the next request will wait.
+      Err(error) if matches!(error.as_ref(), GachaUrlErrorKind::VisitTooFrequently) => {
+        warn!("Requesting gacha url visit too frequently, retry...");
+        continue;
       }
-    }
 
-    // Maximum number of retries reached
-    warn!("Maximum number of retries exceeded: {retries}");
-    Err(GachaUrlErrorKind::VisitTooFrequently)?
+      // Other errors are returned
+      Err(error) => return Err(error),
+    }
   }
-  .boxed()
+
+  // Maximum number of retries reached
+  warn!("Maximum number of retries exceeded: {retries}");
+  Err(GachaUrlErrorKind::VisitTooFrequently)?
+}
+
+#[derive(Debug)]
+pub struct GachaRecordsPage {
+  pub records: Vec<GachaRecord>,
+  // 'Genshin Impact' only, the item id is missing from the outdated metadata.
+  pub pending: Vec<PendingGachaRecord>,
+  // Id of the last record of the page, including the pending ones.
+  pub end_id: String,
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn fetch_gacha_records(
+  transport: &dyn GachaUrlTransport,
   gacha_url: &str,
   gacha_type: Option<&str>,
   end_id: Option<&str>,
   page_size: Option<u8>,
-) -> Result<Option<Vec<GachaRecord>>, GachaUrlError> {
+) -> Result<Option<GachaRecordsPage>, GachaUrlError> {
   info!("Fetching the gacha records...");
 
   let parsed = ParsedGachaUrl::from_str(gacha_url)?;
-  let url = parsed.to_url(gacha_type, end_id, page_size);
+  let url = parsed.to_request_url(gacha_type, end_id, page_size);
   let business = parsed.biz.0;
 
-  let pagination = match request_gacha_url_with_retry(url, None).await {
+  let pagination = match request_gacha_url_with_retry(transport, url, None).await {
     Err(error) => {
       warn!("Responded with an error while fetching the gacha records: {error:?}");
       return Err(error);
@@ -792,24 +1325,45 @@ pub async fn fetch_gacha_records(
   });
 
   let metadata = GachaMetadata::current();
+  let end_id = pagination.list.last().map(|item| item.id.clone()).unwrap(); // SAFETY
   let mut records = Vec::with_capacity(pagination.list.len());
+  let mut pending = Vec::new();
 
   for item in pagination.list {
     let item_id = match item.item_id {
       Some(v) => v,
       // HACK: Genshin Impact only
       //   Mandatory mapping of item ids.
-      //   If metadata is outdated, this error will be thrown.
-      None => metadata
+      //   If metadata is outdated, it is pending until the metadata is updated.
+      None => match metadata
         .locale(business, &item.lang)
         .and_then(|map| map.entry_from_name_first(&item.name))
-        .ok_or_else(|| GachaUrlErrorKind::MissingMetadataEntry {
-          business,
-          locale: item.lang.clone(),
-          name: item.name.clone(),
-        })?
-        .id
-        .to_owned(),
+      {
+        Some(entry) => entry.id,
+        None => {
+          warn!(
+            message = "Missing metadata entry, the record is pending",
+            %business,
+            locale = %item.lang,
+            name = %item.name,
+          );
+
+          pending.push(PendingGachaRecord {
+            business,
+            uid: item.uid,
+            id: item.id,
+            gacha_type: item.gacha_type,
+            gacha_id: item.gacha_id,
+            rank_type: item.rank_type,
+            count: item.count,
+            time: item.time.assume_offset(server_region.time_zone()),
+            lang: item.lang,
+            name: item.name,
+            item_type: item.item_type,
+          });
+          continue;
+        }
+      },
     };
 
     records.push(GachaRecord {
@@ -829,15 +1383,165 @@ pub async fn fetch_gacha_records(
     });
   }
 
-  Ok(Some(records))
+  Ok(Some(GachaRecordsPage {
+    records,
+    pending,
+    end_id,
+  }))
 }
 
 #[cfg(test)]
 mod tests {
+  use crate::business::{GachaUrlExchange, ReplayGachaUrlTransport};
   use crate::error::Error;
 
   use super::*;
 
+  #[test]
+  fn test_read_cache_data_gacha_urls() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR"))
+      .join("fixtures")
+      .join("disk_cache");
+
+    // Long keys of the block file are in: data_2, data_3, f_000001.
+    // And two illegal cache addresses are skipped.
+    // Salvaged urls have no creation time, so they are never expired.
+    for (format, gacha_types, skipped, salvaged) in [
+      ("blockfile", vec!["301", "302", "200"], 2, false),
+      ("blockfile_salvage", vec!["301"], 0, true),
+      ("simple", vec!["301"], 0, false),
+      ("simple_salvage", vec!["301"], 0, true),
+    ] {
+      let result = DirtyGachaUrl::read_cache_data_gacha_urls(fixtures.join(format), false).unwrap();
+
+      assert_eq!(
+        result
+          .urls
+          .iter()
+          .map(|url| ParsedGachaUrl::from_str(&url.value).unwrap().gacha_type.1)
+          .collect::<Vec<_>>(),
+        gacha_types,
+        "{format}"
+      );
+      assert_eq!(result.skipped, skipped, "{format}");
+      assert_eq!(result.salvaged, salvaged, "{format}");
+      assert_eq!(
+        result.urls[0]
+          .creation_time
+          .map(OffsetDateTime::unix_timestamp),
+        (!salvaged).then_some(1704067200),
+        "{format}"
+      );
+
+      // Fixtures are long expired
+      let result = DirtyGachaUrl::read_cache_data_gacha_urls(fixtures.join(format), true).unwrap();
+      assert_eq!(result.urls.len(), if salvaged { 1 } else { 0 }, "{format}");
+    }
+  }
+
+  #[test]
+  fn test_read_simple_cache_keys_unindexed_entry() {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+      .join("fixtures")
+      .join("disk_cache")
+      .join("simple");
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(temp_dir.path().join("index-dir")).unwrap();
+    for file_name in ["67d65d5b56707323_0", "d58fd1a6e0b0b0a9_0", "index"] {
+      std::fs::copy(fixture.join(file_name), temp_dir.path().join(file_name)).unwrap();
+    }
+    std::fs::copy(
+      fixture.join("index-dir").join("the-real-index"),
+      temp_dir.path().join("index-dir").join("the-real-index"),
+    )
+    .unwrap();
+
+    // Written after the index is flushed, so it is not in the index yet.
+    std::fs::copy(
+      fixture.join("67d65d5b56707323_0"),
+      temp_dir.path().join("0123456789abcdef_0"),
+    )
+    .unwrap();
+
+    let mut keys = DirtyGachaUrl::read_simple_cache_keys(temp_dir.path()).unwrap();
+    keys.keys.sort_by_key(|(time, _)| time.is_some());
+
+    assert_eq!(keys.keys.len(), 3);
+    assert_eq!(keys.skipped, 0);
+    assert!(!keys.salvaged);
+    assert_eq!(keys.keys[0].0, None);
+    assert!(keys.keys[1..].iter().all(|(time, _)| time.is_some()));
+    assert!(keys.keys[1..].iter().any(|(_, key)| *key == keys.keys[0].1));
+
+    temp_dir.close().unwrap();
+  }
+
+  #[test]
+  fn test_chromium_time_to_local() {
+    // 2024-01-01T00:00:00Z
+    assert_eq!(
+      chromium_time_to_local(13_348_540_800_000_000).map(OffsetDateTime::unix_timestamp),
+      Some(1704067200)
+    );
+
+    // Garbage or negative times of the corrupted entries
+    assert_eq!(chromium_time_to_local(u64::MAX), None);
+    assert_eq!(chromium_time_to_local(-1i64 as u64), None);
+  }
+
+  #[tokio::test]
+  async fn test_from_webcaches_all_versions() {
+    fn copy_dir(from: &Path, to: &Path) {
+      std::fs::create_dir_all(to).unwrap();
+      for entry in std::fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        if entry.path().is_dir() {
+          copy_dir(&entry.path(), &to.join(entry.file_name()));
+        } else {
+          std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+        }
+      }
+    }
+
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR"))
+      .join("fixtures")
+      .join("disk_cache");
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let webcaches = temp_dir.path().join("webCaches");
+    let cache_data = |version: &str| webcaches.join(version).join("Cache").join("Cache_Data");
+
+    // The newest version is empty after the game patch
+    std::fs::create_dir_all(cache_data("2.31.0.0")).unwrap();
+    copy_dir(&fixtures.join("simple"), &cache_data("2.30.0.0"));
+    copy_dir(&fixtures.join("blockfile"), &cache_data("2.29.0.0"));
+
+    let result = DirtyGachaUrl::from_webcaches(temp_dir.path(), false)
+      .await
+      .unwrap();
+
+    assert_eq!(
+      result
+        .urls
+        .iter()
+        .map(|url| (
+          ParsedGachaUrl::from_str(&url.value).unwrap().gacha_type.1,
+          url.webcaches_version.as_ref().unwrap().to_string()
+        ))
+        .collect::<Vec<_>>(),
+      vec![
+        ("301".to_owned(), "2.30.0.0".to_owned()),
+        ("302".to_owned(), "2.29.0.0".to_owned()),
+        ("200".to_owned(), "2.29.0.0".to_owned()),
+      ]
+    );
+    assert_eq!(result.skipped, 2);
+    assert!(!result.salvaged);
+
+    temp_dir.close().unwrap();
+  }
+
   #[test]
   fn test_parse_error_dirty_gacha_url() {
     assert!(matches!(
@@ -894,4 +1598,236 @@ mod tests {
       }
     );
   }
+
+  #[test]
+  fn test_redact_gacha_url_authkey() {
+    let url = "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey_ver=1&sign_type=2&authkey=SECRET&lang=zh-cn&region=cn_gf01&game_biz=hk4e_cn&gacha_type=301";
+    let gacha_url = ParsedGachaUrl::from_str(url).unwrap();
+
+    assert!(!format!("{gacha_url:?}").contains("SECRET"));
+    assert!(
+      !serde_json::to_string(&gacha_url)
+        .unwrap()
+        .contains("SECRET")
+    );
+
+    let dirty = DirtyGachaUrl {
+      creation_time: None,
+      value: url.to_owned(),
+      webcaches_version: None,
+    };
+
+    assert!(!format!("{dirty:?}").contains("SECRET"));
+
+    // But the actual url still contains the authkey
+    assert!(
+      gacha_url
+        .to_url(None, None, None)
+        .as_str()
+        .contains("SECRET")
+    );
+  }
+
+  #[tokio::test]
+  async fn test_discover_replay() {
+    fn dirty_url(authkey: &str) -> DirtyGachaUrl {
+      DirtyGachaUrl {
+        creation_time: None,
+        value: format!(
+          "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey_ver=1&sign_type=2&authkey={authkey}&lang=zh-cn&region=cn_gf01&game_biz=hk4e_cn&gacha_type=301"
+        ),
+        webcaches_version: None,
+      }
+    }
+
+    fn response(retcode: i32, message: &str, uid: Option<u32>) -> String {
+      let list = uid.map_or_else(Vec::new, |uid| {
+        vec![serde_json::json!({
+          "id": "1000000000000000001",
+          "uid": uid.to_string(),
+          "gacha_type": "301",
+          "rank_type": "3",
+          "count": "1",
+          "time": "2024-01-01 00:00:00",
+          "lang": "zh-cn",
+          "name": "Name",
+          "item_type": "Weapon",
+        })]
+      });
+
+      serde_json::json!({
+        "retcode": retcode,
+        "message": message,
+        "data": (retcode == 0).then(|| serde_json::json!({
+          "region": "cn_gf01",
+          "list": list,
+        })),
+      })
+      .to_string()
+    }
+
+    // The authkey is redacted, so the responses are served in order of the probes.
+    let url = ParsedGachaUrl::from_str(&dirty_url("A").value)
+      .unwrap()
+      .to_request_url(None, None, Some(1));
+    let transport = ReplayGachaUrlTransport::new(
+      [
+        response(0, "OK", Some(100_000_001)),    // A
+        response(0, "OK", Some(100_000_001)),    // B: Same owner as A
+        response(0, "OK", None),                 // C: Empty
+        response(-101, "authkey timeout", None), // D: Expired
+        response(-1, "system error", None),      // E: Error
+        response(0, "OK", Some(100_000_002)),    // F
+      ]
+      .into_iter()
+      .map(|response| GachaUrlExchange {
+        url: redact_authkeys(url.as_str()).into_owned(),
+        response,
+      }),
+    );
+
+    let mut dirty_urls = ["A", "B", "A", "C", "D", "E", "F"]
+      .into_iter()
+      .map(dirty_url)
+      .collect::<Vec<_>>();
+
+    // Unparsable url is never probed
+    dirty_urls.insert(
+      1,
+      DirtyGachaUrl {
+        creation_time: None,
+        value: "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog".to_owned(),
+        webcaches_version: None,
+      },
+    );
+
+    // The same authkey A is probed only once, otherwise the responses are shifted.
+    let discovered = GachaUrl::discover(&transport, dirty_urls).await;
+
+    assert_eq!(
+      discovered
+        .iter()
+        .map(|gacha_url| (gacha_url.url.authkey.as_str(), gacha_url.owner_uid))
+        .collect::<Vec<_>>(),
+      vec![("A", 100_000_001), ("F", 100_000_002)]
+    );
+  }
+
+  #[tokio::test]
+  async fn test_consistency_check_replay() {
+    fn dirty_url(authkey: &str) -> DirtyGachaUrl {
+      DirtyGachaUrl {
+        creation_time: None,
+        value: format!(
+          "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey_ver=1&sign_type=2&authkey={authkey}&lang=zh-cn&region=cn_gf01&game_biz=hk4e_cn&gacha_type=301"
+        ),
+        webcaches_version: None,
+      }
+    }
+
+    fn response(uid: u32) -> String {
+      serde_json::json!({
+        "retcode": 0,
+        "message": "OK",
+        "data": {
+          "region": "cn_gf01",
+          "list": [{
+            "id": "1000000000000000001",
+            "uid": uid.to_string(),
+            "gacha_type": "301",
+            "rank_type": "3",
+            "count": "1",
+            "time": "2024-01-01 00:00:00",
+            "lang": "zh-cn",
+            "name": "Name",
+            "item_type": "Weapon",
+          }],
+        },
+      })
+      .to_string()
+    }
+
+    // The authkey is redacted, so the same request is served in order.
+    let url = ParsedGachaUrl::from_str(&dirty_url("A").value)
+      .unwrap()
+      .to_request_url(None, None, Some(1));
+    let transport = ReplayGachaUrlTransport::new([
+      GachaUrlExchange {
+        url: redact_authkeys(url.as_str()).into_owned(),
+        response: response(100_000_002),
+      },
+      GachaUrlExchange {
+        url: redact_authkeys(url.as_str()).into_owned(),
+        response: response(100_000_001),
+      },
+    ]);
+
+    let gacha_url = GachaUrl::consistency_check(
+      &transport,
+      vec![dirty_url("A"), dirty_url("B")],
+      100_000_001,
+      false,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(gacha_url.owner_uid, 100_000_001);
+    assert_eq!(gacha_url.url.authkey, "B");
+  }
+
+  #[tokio::test]
+  async fn test_from_dirty_replay() {
+    const DIRTY_URL: &str = "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey_ver=1&sign_type=2&authkey=SECRET&lang=zh-cn&region=cn_gf01&game_biz=hk4e_cn&gacha_type=301";
+
+    let url = ParsedGachaUrl::from_str(DIRTY_URL)
+      .unwrap()
+      .to_request_url(None, None, Some(1));
+    let transport = ReplayGachaUrlTransport::new([GachaUrlExchange {
+      url: redact_authkeys(url.as_str()).into_owned(),
+      response: serde_json::json!({
+        "retcode": 0,
+        "message": "OK",
+        "data": {
+          "region": "cn_gf01",
+          "list": [{
+            "id": "1000000000000000001",
+            "uid": "100000002",
+            "gacha_type": "301",
+            "rank_type": "3",
+            "count": "1",
+            "time": "2024-01-01 00:00:00",
+            "lang": "zh-cn",
+            "name": "Name",
+            "item_type": "Weapon",
+          }],
+        },
+      })
+      .to_string(),
+    }]);
+
+    let gacha_url = GachaUrl::from_dirty(&transport, DIRTY_URL.into(), 100_000_002)
+      .await
+      .unwrap();
+    assert_eq!(gacha_url.owner_uid, 100_000_002);
+    assert_eq!(gacha_url.creation_time, None);
+
+    // Owned by the other uid
+    let error = GachaUrl::from_dirty(&transport, DIRTY_URL.into(), 100_000_001)
+      .await
+      .unwrap_err();
+    assert!(matches!(
+      error.as_ref(),
+      GachaUrlErrorKind::InconsistentUid { expected: 100_000_001, actuals }
+        if actuals.len() == 1 && actuals.contains(&100_000_002)
+    ));
+
+    // Unparsable url is never requested
+    let error = GachaUrl::from_dirty(&transport, "https://example.com".into(), 100_000_002)
+      .await
+      .unwrap_err();
+    assert!(matches!(
+      error.as_ref(),
+      GachaUrlErrorKind::IllegalUrl { .. }
+    ));
+  }
 }
@@ -0,0 +1,232 @@
+#[cfg(test)]
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+#[cfg(test)]
+use std::io::BufReader;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use url::Url;
+
+use super::{GachaUrlError, GachaUrlErrorKind};
+use crate::utilities::http_client::http_client;
+use crate::utilities::redact::redact_authkeys;
+
+/// Transport of the gacha url requests, returns the response body.
+#[async_trait]
+pub trait GachaUrlTransport: Send + Sync {
+  async fn get(&self, url: &Url, timeout: Duration) -> Result<String, GachaUrlError>;
+}
+
+/// Request the live endpoints with the global http client.
+pub struct ReqwestGachaUrlTransport;
+
+#[async_trait]
+impl GachaUrlTransport for ReqwestGachaUrlTransport {
+  async fn get(&self, url: &Url, timeout: Duration) -> Result<String, GachaUrlError> {
+    let response = http_client()
+      .get(url.clone())
+      .timeout(timeout)
+      .send()
+      .await
+      .map_err(|cause| GachaUrlErrorKind::Reqwest {
+        cause: cause.without_url(), // Contains the authkey
+      })?
+      .text()
+      .await
+      .map_err(|cause| GachaUrlErrorKind::Reqwest {
+        cause: cause.without_url(),
+      })?;
+
+    Ok(response)
+  }
+}
+
+/// Request and response pair of the gacha url, the authkey is redacted.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GachaUrlExchange {
+  pub url: String,
+  pub response: String,
+}
+
+impl GachaUrlExchange {
+  fn key(url: &Url) -> String {
+    redact_authkeys(url.as_str()).into_owned()
+  }
+}
+
+/// Record the responses of the inner transport, so that they can be replayed later.
+pub struct RecordingGachaUrlTransport<T = ReqwestGachaUrlTransport> {
+  inner: T,
+  exchanges: Mutex<Vec<GachaUrlExchange>>,
+}
+
+impl<T: GachaUrlTransport> RecordingGachaUrlTransport<T> {
+  pub fn new(inner: T) -> Self {
+    Self {
+      inner,
+      exchanges: Mutex::new(Vec::new()),
+    }
+  }
+
+  pub fn exchanges(&self) -> Vec<GachaUrlExchange> {
+    self.exchanges.lock().unwrap().clone()
+  }
+
+  #[tracing::instrument(skip(self))]
+  pub fn save(&self, output: impl AsRef<Path> + std::fmt::Debug) -> io::Result<()> {
+    let exchanges = self.exchanges();
+    info!("Saving {} recorded gacha url exchanges", exchanges.len());
+
+    let writer = BufWriter::new(File::create(output)?);
+    serde_json::to_writer_pretty(writer, &exchanges)?;
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl<T: GachaUrlTransport> GachaUrlTransport for RecordingGachaUrlTransport<T> {
+  async fn get(&self, url: &Url, timeout: Duration) -> Result<String, GachaUrlError> {
+    let response = self.inner.get(url, timeout).await?;
+
+    self.exchanges.lock().unwrap().push(GachaUrlExchange {
+      url: GachaUrlExchange::key(url),
+      response: response.clone(),
+    });
+
+    Ok(response)
+  }
+}
+
+/// Serve the recorded responses back in order of the same request,
+/// and repeat the last one when they are used up. Regression tests only.
+///
+/// Panics if the request is not recorded.
+#[cfg(test)]
+pub struct ReplayGachaUrlTransport(Mutex<HashMap<String, VecDeque<String>>>);
+
+#[cfg(test)]
+impl ReplayGachaUrlTransport {
+  pub fn new(exchanges: impl IntoIterator<Item = GachaUrlExchange>) -> Self {
+    let mut responses = HashMap::<_, VecDeque<_>>::new();
+    for exchange in exchanges {
+      responses
+        .entry(exchange.url)
+        .or_default()
+        .push_back(exchange.response);
+    }
+
+    Self(Mutex::new(responses))
+  }
+
+  pub fn from_file(input: impl AsRef<Path>) -> io::Result<Self> {
+    let reader = BufReader::new(File::open(input)?);
+    let exchanges: Vec<GachaUrlExchange> = serde_json::from_reader(reader)?;
+    Ok(Self::new(exchanges))
+  }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl GachaUrlTransport for ReplayGachaUrlTransport {
+  async fn get(&self, url: &Url, _timeout: Duration) -> Result<String, GachaUrlError> {
+    let key = GachaUrlExchange::key(url);
+    let mut responses = self.0.lock().unwrap();
+
+    let response = match responses.get_mut(&key) {
+      Some(queue) if queue.len() > 1 => queue.pop_front(),
+      Some(queue) => queue.front().cloned(),
+      None => None,
+    };
+
+    Ok(response.unwrap_or_else(|| panic!("No recorded response of the gacha url: {key}")))
+  }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  struct EchoGachaUrlTransport;
+
+  #[async_trait]
+  impl GachaUrlTransport for EchoGachaUrlTransport {
+    async fn get(&self, url: &Url, _timeout: Duration) -> Result<String, GachaUrlError> {
+      Ok(format!(
+        "{{\"end_id\":\"{}\"}}",
+        url.query_pairs().find(|(k, _)| k == "end_id").unwrap().1
+      ))
+    }
+  }
+
+  #[tokio::test]
+  async fn test_record_and_replay() {
+    let timeout = Duration::from_secs(1);
+    let url = |end_id: &str| {
+      Url::parse(&format!(
+        "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey=SECRET&end_id={end_id}"
+      ))
+      .unwrap()
+    };
+
+    let recording = RecordingGachaUrlTransport::new(EchoGachaUrlTransport);
+    recording.get(&url("0"), timeout).await.unwrap();
+    recording.get(&url("1"), timeout).await.unwrap();
+
+    let exchanges = recording.exchanges();
+    assert_eq!(exchanges.len(), 2);
+    assert!(
+      exchanges
+        .iter()
+        .all(|exchange| !exchange.url.contains("SECRET"))
+    );
+
+    // The authkey of the replay session can be different
+    let replay = ReplayGachaUrlTransport::new(exchanges);
+    assert_eq!(
+      replay.get(&url("1"), timeout).await.unwrap(),
+      r#"{"end_id":"1"}"#
+    );
+    assert_eq!(
+      replay.get(&url("0"), timeout).await.unwrap(),
+      r#"{"end_id":"0"}"#
+    );
+    assert_eq!(
+      replay.get(&url("0"), timeout).await.unwrap(),
+      r#"{"end_id":"0"}"#
+    );
+
+    // Saved and replayed from the file
+    let temp_dir = tempfile::tempdir().unwrap();
+    let output = temp_dir.path().join("exchanges.json");
+    recording.save(&output).unwrap();
+
+    let replay = ReplayGachaUrlTransport::from_file(&output).unwrap();
+    assert_eq!(
+      replay.get(&url("1"), timeout).await.unwrap(),
+      r#"{"end_id":"1"}"#
+    );
+
+    temp_dir.close().unwrap();
+  }
+
+  #[tokio::test]
+  #[should_panic(expected = "No recorded response of the gacha url")]
+  async fn test_replay_not_recorded() {
+    let url = Url::parse(
+      "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey=SECRET",
+    )
+    .unwrap();
+
+    let _ = ReplayGachaUrlTransport::new([])
+      .get(&url, Duration::from_secs(1))
+      .await;
+  }
+}
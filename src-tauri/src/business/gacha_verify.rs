@@ -0,0 +1,330 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::database::{Database, GachaRecordQuestioner, GachaRecordQuestionerAdditions, SqlxError};
+use crate::models::{Business, GachaRecord, GachaRecordsAuditSource};
+use crate::utilities::progress_sink::ProgressSink;
+
+use super::{GachaRecordsFetcherError, GachaUrlTransport, gacha_type_of_request};
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "PascalCase")] // Enum name
+pub enum GachaRecordsVerifierFragment {
+  Sleeping,
+  Ready(u32),
+  Pagination(usize),
+  Completed(u32),
+  Finished,
+}
+
+/// Differences between the local and the server records of the gacha type.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaTypeReconciliation {
+  pub gacha_type: u32,
+  // Oldest id on the server, the older local records are beyond its retention and not verified.
+  // None if the server has no records of the gacha type.
+  pub oldest_id: Option<String>,
+  // On the server, but missing locally
+  pub missing: Vec<GachaRecord>,
+  // Present locally, but gone from the server
+  pub vanished: Vec<GachaRecord>,
+}
+
+impl GachaTypeReconciliation {
+  pub fn is_consistent(&self) -> bool {
+    self.missing.is_empty() && self.vanished.is_empty()
+  }
+}
+
+/// Report of the gacha records verification, can be applied as it is.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaRecordsReconciliation {
+  pub business: Business,
+  pub uid: u32,
+  pub gacha_types: Vec<GachaTypeReconciliation>,
+}
+
+impl GachaRecordsReconciliation {
+  pub fn is_consistent(&self) -> bool {
+    self
+      .gacha_types
+      .iter()
+      .all(GachaTypeReconciliation::is_consistent)
+  }
+}
+
+// Records of the server page by page. The pending ones only have their ids,
+// they are present on the server but quarantined locally.
+#[derive(Default)]
+struct ServerGachaRecords {
+  records: Vec<GachaRecord>,
+  pending_ids: Vec<(u32, String)>,
+}
+
+// Compare the local records with the whole history of the server,
+// instead of stopping at the last end id like the incremental sync.
+fn reconcile_gacha_type(
+  gacha_type: u32,
+  server: &ServerGachaRecords,
+  local: Vec<GachaRecord>,
+) -> GachaTypeReconciliation {
+  let server_ids = server
+    .records
+    .iter()
+    .filter(|record| record.gacha_type == gacha_type)
+    .map(|record| &record.id)
+    .chain(
+      server
+        .pending_ids
+        .iter()
+        .filter(|(pending_gacha_type, _)| *pending_gacha_type == gacha_type)
+        .map(|(_, id)| id),
+    )
+    .collect::<HashSet<_>>();
+
+  let Some(oldest_id) = server_ids.iter().min().map(|id| (*id).clone()) else {
+    // Nothing can be verified, the server may have expired all of them
+    return GachaTypeReconciliation {
+      gacha_type,
+      ..Default::default()
+    };
+  };
+
+  let local_ids = local
+    .iter()
+    .map(|record| record.id.clone())
+    .collect::<HashSet<_>>();
+
+  let missing = server
+    .records
+    .iter()
+    .filter(|record| record.gacha_type == gacha_type && !local_ids.contains(&record.id))
+    .cloned()
+    .collect();
+
+  let vanished = local
+    .into_iter()
+    .filter(|record| record.id >= oldest_id && !server_ids.contains(&record.id))
+    .collect();
+
+  GachaTypeReconciliation {
+    gacha_type,
+    oldest_id: Some(oldest_id),
+    missing,
+    vanished,
+  }
+}
+
+// Page across the whole history of the server for the requested gacha type.
+async fn pull_server_gacha_records(
+  transport: &dyn GachaUrlTransport,
+  gacha_url: &str,
+  request_gacha_type: u32,
+  progress_sink: &dyn ProgressSink<GachaRecordsVerifierFragment>,
+) -> Result<ServerGachaRecords, GachaRecordsFetcherError> {
+  // Internal Abbreviations
+  type Fragment = GachaRecordsVerifierFragment;
+
+  let request_gacha_type = request_gacha_type.to_string();
+  let mut server = ServerGachaRecords::default();
+  let mut end_id = String::from("0");
+  let mut pagination: usize = 0;
+
+  loop {
+    if super::gacha_url::is_gacha_url_rate_limited(gacha_url, Some(&request_gacha_type)) {
+      info!("Rate limit reached. Wait a moment...");
+      progress_sink.report(Fragment::Sleeping);
+    }
+
+    pagination += 1;
+    progress_sink.report(Fragment::Pagination(pagination));
+
+    let Some(page) = super::gacha_url::fetch_gacha_records(
+      transport,
+      gacha_url,
+      Some(&request_gacha_type),
+      Some(&end_id),
+      None,
+    )
+    .await?
+    else {
+      break;
+    };
+
+    end_id = page.end_id;
+    server.records.extend(page.records);
+    server.pending_ids.extend(
+      page
+        .pending
+        .into_iter()
+        .map(|record| (record.gacha_type, record.id)),
+    );
+  }
+
+  Ok(server)
+}
+
+/// Verify the local records of each gacha type against the whole history of the server,
+/// e.g. the gaps left by an interrupted sync or a partial import.
+/// The gacha types of the local records are verified if `gacha_types` is None.
+#[tracing::instrument(skip(database, transport, gacha_url, progress_sink))]
+pub async fn verify_gacha_records(
+  database: &Database,
+  business: Business,
+  uid: u32,
+  transport: &dyn GachaUrlTransport,
+  gacha_url: &str,
+  gacha_types: Option<Vec<u32>>,
+  progress_sink: &dyn ProgressSink<GachaRecordsVerifierFragment>,
+) -> Result<GachaRecordsReconciliation, GachaRecordsFetcherError> {
+  info!("Verifying the gacha records...");
+
+  let gacha_types = match gacha_types {
+    Some(gacha_types) => gacha_types,
+    None => {
+      let mut gacha_types =
+        GachaRecordQuestioner::find_gacha_records_by_business_and_uid(database, business, uid)
+          .await?
+          .into_iter()
+          .map(|record| record.gacha_type)
+          .collect::<Vec<_>>();
+
+      gacha_types.sort_unstable();
+      gacha_types.dedup();
+      gacha_types
+    }
+  };
+
+  // Some gacha types are requested together, page them only once
+  let mut servers = HashMap::<u32, ServerGachaRecords>::new();
+  let mut reconciliations = Vec::with_capacity(gacha_types.len());
+
+  for gacha_type in gacha_types {
+    progress_sink.report(GachaRecordsVerifierFragment::Ready(gacha_type));
+
+    let request_gacha_type = gacha_type_of_request(business, gacha_type);
+    if let Entry::Vacant(entry) = servers.entry(request_gacha_type) {
+      entry.insert(
+        pull_server_gacha_records(transport, gacha_url, request_gacha_type, progress_sink).await?,
+      );
+    }
+
+    let local = GachaRecordQuestioner::find_gacha_records_by_business_and_uid_with_gacha_type(
+      database, business, uid, gacha_type,
+    )
+    .await?;
+
+    let reconciliation = reconcile_gacha_type(gacha_type, &servers[&request_gacha_type], local);
+    info!(
+      message = "Gacha type verified",
+      gacha_type,
+      missing = reconciliation.missing.len(),
+      vanished = reconciliation.vanished.len(),
+    );
+
+    reconciliations.push(reconciliation);
+    progress_sink.report(GachaRecordsVerifierFragment::Completed(gacha_type));
+  }
+
+  progress_sink.report(GachaRecordsVerifierFragment::Finished);
+
+  Ok(GachaRecordsReconciliation {
+    business,
+    uid,
+    gacha_types: reconciliations,
+  })
+}
+
+/// Apply the reconciliation, the missing records are created and the vanished are deleted.
+/// Returns the number of created minus deleted.
+#[tracing::instrument(skip_all, fields(business = ?reconciliation.business, uid = reconciliation.uid))]
+pub async fn apply_gacha_records_reconciliation(
+  database: &Database,
+  reconciliation: GachaRecordsReconciliation,
+) -> Result<i64, SqlxError> {
+  let GachaRecordsReconciliation { business, uid, .. } = reconciliation;
+
+  let mut created = Vec::new();
+  let mut deleted = Vec::new();
+  for gacha_type in reconciliation.gacha_types {
+    created.extend(gacha_type.missing);
+    deleted.extend(gacha_type.vanished);
+  }
+
+  // Only the records of the reconciled account
+  created.retain(|record| record.business == business && record.uid == uid);
+  deleted.retain(|record| record.business == business && record.uid == uid);
+
+  if created.is_empty() && deleted.is_empty() {
+    return Ok(0);
+  }
+
+  GachaRecordQuestioner::reconcile_gacha_records(
+    database,
+    created,
+    deleted,
+    &GachaRecordsAuditSource::Verify,
+  )
+  .await
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_reconcile_gacha_type() {
+    // 400 is requested together with 301
+    let server = ServerGachaRecords {
+      records: vec![
+        GachaRecord::fixture("1005", 301),
+        GachaRecord::fixture("1004", 400),
+        GachaRecord::fixture("1003", 301),
+        GachaRecord::fixture("1002", 301),
+      ],
+      pending_ids: vec![(301, "1001".into())],
+    };
+
+    let reconciliation = reconcile_gacha_type(
+      301,
+      &server,
+      vec![
+        // Beyond the retention of the server
+        GachaRecord::fixture("0999", 301),
+        GachaRecord::fixture("1001", 301),
+        GachaRecord::fixture("1002", 301),
+        // Gone from the server
+        GachaRecord::fixture("1004", 301),
+        GachaRecord::fixture("1005", 301),
+      ],
+    );
+
+    assert_eq!(reconciliation.oldest_id.as_deref(), Some("1001"));
+    assert_eq!(
+      reconciliation.missing,
+      vec![GachaRecord::fixture("1003", 301)]
+    );
+    assert_eq!(
+      reconciliation.vanished,
+      vec![GachaRecord::fixture("1004", 301)]
+    );
+    assert!(!reconciliation.is_consistent());
+
+    let reconciliation =
+      reconcile_gacha_type(400, &server, vec![GachaRecord::fixture("1004", 400)]);
+    assert!(reconciliation.is_consistent());
+
+    // Nothing on the server, nothing to verify
+    let reconciliation =
+      reconcile_gacha_type(302, &server, vec![GachaRecord::fixture("0999", 302)]);
+    assert_eq!(reconciliation.oldest_id, None);
+    assert!(reconciliation.is_consistent());
+  }
+}
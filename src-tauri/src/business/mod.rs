@@ -1,32 +1,55 @@
-use std::collections::{HashMap, hash_map};
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::{env, fmt, fs};
 
-use serde::Deserialize;
-use tauri::{Emitter, WebviewWindow};
+use serde::{Deserialize, Serialize};
+use tauri::WebviewWindow;
+use time::OffsetDateTime;
 use time::format_description::FormatItem;
 use time::macros::format_description;
-use tokio::sync::mpsc;
+use tracing::{info, warn};
 
+use crate::consts;
 use crate::database::{
-  DatabaseState, GachaRecordQuestioner, GachaRecordQuestionerAdditions, GachaRecordSaveOnConflict,
+  AccountQuestioner, Database, DatabaseState, GachaRecordQuestioner,
+  GachaRecordQuestionerAdditions, GachaRecordSaveOnConflict, GachaRecordsCheckpointQuestioner,
+  KvMut, PendingGachaRecordQuestioner, PendingGachaRecordQuestionerAdditions, SqlxError,
+  StoredGachaUrlQuestioner, StoredGachaUrlQuestionerAdditions,
 };
 use crate::error::{Error, ErrorDetails};
-use crate::models::{Business, BusinessRegion, GachaRecord};
+use crate::models::{
+  Account, AutoSyncSchedule, Business, BusinessRegion, EndpointOverrides, GachaRecordsAuditSource,
+};
+use crate::utilities::progress_sink::{ProgressSink, progress_sink_from_event_channel};
+use crate::utilities::rate_limiter::RateLimiterSettings;
 
 mod data_folder_locator;
 mod disk_cache;
+mod endpoint_overrides;
+mod gacha_auto_sync;
 mod gacha_convert;
+mod gacha_diff;
 mod gacha_fetcher;
 mod gacha_metadata;
 mod gacha_prettied;
+mod gacha_sync;
 mod gacha_url;
+mod gacha_url_transport;
+mod gacha_verify;
 
 pub use data_folder_locator::*;
+pub use endpoint_overrides::*;
+pub use gacha_auto_sync::*;
 pub use gacha_convert::*;
+pub use gacha_diff::*;
 pub use gacha_fetcher::*;
 pub use gacha_metadata::*;
 pub use gacha_prettied::*;
+pub use gacha_sync::*;
 pub use gacha_url::*;
+pub use gacha_url_transport::*;
+pub use gacha_verify::*;
 
 pub const GACHA_TIME_FORMAT: &[FormatItem<'_>] =
   format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
@@ -45,29 +68,280 @@ pub async fn business_locate_data_folder(
   factory.locate_data_folder(business, region).await
 }
 
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_locate_data_folders(
+  business: Business,
+  region: BusinessRegion,
+  factory: DataFolderLocatorFactory,
+) -> Result<Vec<DataFolderCandidate>, DataFolderError> {
+  factory.locate_data_folders(business, region).await
+}
+
 #[tauri::command]
 #[tracing::instrument(skip_all)]
 pub async fn business_from_webcaches_gacha_url(
+  database: DatabaseState<'_>,
   data_folder: PathBuf,
   expected_uid: u32,
 ) -> Result<GachaUrl, GachaUrlError> {
-  GachaUrl::from_webcaches(&data_folder, expected_uid).await
+  let session = GachaUrlSession::from_env();
+  let gacha_url =
+    GachaUrl::from_webcaches(session.transport.as_ref(), &data_folder, expected_uid).await;
+  session.finish("FromWebcaches");
+
+  let gacha_url = gacha_url?;
+  store_gacha_url(&database, &gacha_url).await;
+  Ok(gacha_url)
 }
 
 #[tauri::command]
 #[tracing::instrument(skip_all)]
 pub async fn business_from_dirty_gacha_url(
+  database: DatabaseState<'_>,
   dirty_url: String,
   expected_uid: u32,
 ) -> Result<GachaUrl, GachaUrlError> {
-  GachaUrl::from_dirty(dirty_url, expected_uid).await
+  let session = GachaUrlSession::from_env();
+  let gacha_url = GachaUrl::from_dirty(session.transport.as_ref(), dirty_url, expected_uid).await;
+  session.finish("FromDirty");
+
+  let gacha_url = gacha_url?;
+  store_gacha_url(&database, &gacha_url).await;
+  Ok(gacha_url)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredAccount {
+  pub business: Business,
+  pub region: BusinessRegion,
+  pub uid: u32,
+  // The existing or created account, None if it is missing.
+  pub account: Option<Account>,
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_discover_accounts(
+  database: DatabaseState<'_>,
+  data_folder: PathBuf,
+  create_missing: Option<bool>,
+) -> Result<Vec<DiscoveredAccount>, Box<dyn ErrorDetails + Send + 'static>> {
+  let create_missing = create_missing.unwrap_or(false);
+  let session = GachaUrlSession::from_env();
+  let gacha_urls =
+    GachaUrl::discover_from_webcaches(session.transport.as_ref(), &data_folder).await;
+  session.finish("Discover");
+
+  let gacha_urls = gacha_urls.map_err(Error::boxed)?;
+
+  let mut discovered = Vec::with_capacity(gacha_urls.len());
+  for gacha_url in gacha_urls {
+    let (business, region) = gacha_url.url.biz;
+    let uid = gacha_url.owner_uid;
+
+    let mut account = AccountQuestioner::find_account_by_business_and_uid(&database, business, uid)
+      .await
+      .map_err(Error::boxed)?;
+
+    if account.is_none() && create_missing {
+      info!(message = "Create the discovered account", %business, uid);
+      account = Some(
+        AccountQuestioner::create_account(
+          &database,
+          business,
+          uid,
+          data_folder.to_string_lossy().into_owned(),
+          None,
+        )
+        .await
+        .map_err(Error::boxed)?,
+      );
+    }
+
+    // It has been validated, reuse it when fetching
+    store_gacha_url(&database, &gacha_url).await;
+
+    discovered.push(DiscoveredAccount {
+      business,
+      region,
+      uid,
+      account,
+    });
+  }
+
+  Ok(discovered)
+}
+
+// Transport of the gacha url requests of a command or the auto sync.
+// The exchanges are recorded into the folder of the environment variable,
+// e.g. for the regression tests. Otherwise, request the live endpoints.
+struct GachaUrlSession {
+  transport: Arc<dyn GachaUrlTransport>,
+  recording: Option<(PathBuf, Arc<RecordingGachaUrlTransport>)>,
+}
+
+impl GachaUrlSession {
+  fn from_env() -> Self {
+    match env::var_os(consts::ENV_GACHA_URL_RECORDING) {
+      Some(folder) => {
+        let recording = Arc::new(RecordingGachaUrlTransport::new(ReqwestGachaUrlTransport));
+        Self {
+          transport: recording.clone(),
+          recording: Some((PathBuf::from(folder), recording)),
+        }
+      }
+      None => Self {
+        transport: Arc::new(ReqwestGachaUrlTransport),
+        recording: None,
+      },
+    }
+  }
+
+  fn finish(self, name: impl fmt::Display) {
+    if let Some((folder, recording)) = self.recording {
+      let timestamp = OffsetDateTime::now_utc().unix_timestamp();
+      let output = folder.join(format!("{name}_{timestamp}.json"));
+      if let Err(error) = recording.save(&output) {
+        warn!("Error saving recorded gacha url exchanges: {error}");
+      }
+    }
+  }
+}
+
+// Storing is only a shortcut for the next fetch, so its errors are not returned.
+async fn store_gacha_url(database: &Database, gacha_url: &GachaUrl) {
+  let creation_time = gacha_url
+    .creation_time
+    .unwrap_or_else(|| OffsetDateTime::now_utc().to_offset(*consts::LOCAL_OFFSET));
+
+  if let Err(error) = StoredGachaUrlQuestioner::upsert_stored_gacha_url(
+    database,
+    gacha_url.url.biz.0,
+    gacha_url.owner_uid,
+    gacha_url.url.to_url(None, None, None).to_string(),
+    creation_time,
+  )
+  .await
+  {
+    warn!("Error storing gacha url: {error}");
+  }
+}
+
+// Reuse the stored gacha url of the account until it has probably expired,
+// otherwise read the latest one from the webcaches of the account data folder.
+#[tracing::instrument(skip(database, transport))]
+async fn resolve_gacha_url(
+  database: &Database,
+  transport: &dyn GachaUrlTransport,
+  business: Business,
+  uid: u32,
+) -> Result<String, GachaRecordsFetcherError> {
+  let now = OffsetDateTime::now_utc().to_offset(*consts::LOCAL_OFFSET);
+
+  match StoredGachaUrlQuestioner::find_stored_gacha_url_by_business_and_uid(database, business, uid)
+    .await
+  {
+    // E.g. stored on the endpoint override by the earlier versions
+    Ok(Some(stored)) if ParsedGachaUrl::from_str(&stored.value).is_err() => {
+      warn!("Stored gacha url is illegal, delete it");
+      if let Err(error) =
+        StoredGachaUrlQuestioner::delete_stored_gacha_url(database, business, uid, &stored.value)
+          .await
+      {
+        warn!("Error deleting stored gacha url: {error}");
+      }
+    }
+    Ok(Some(stored)) if !stored.is_expired(now) => {
+      info!("Reuse the stored gacha url");
+      return Ok(stored.value);
+    }
+    Ok(Some(stored)) => {
+      info!(
+        message = "Stored gacha url has expired",
+        creation_time = ?stored.creation_time
+      );
+    }
+    Ok(None) => {}
+    Err(error) => warn!("Error finding stored gacha url: {error}"),
+  }
+
+  let account = AccountQuestioner::find_account_by_business_and_uid(database, business, uid)
+    .await?
+    .ok_or_else(|| GachaUrlError::from(GachaUrlErrorKind::NotFound))?;
+
+  let gacha_url = GachaUrl::from_webcaches(transport, &account.data_folder, uid).await?;
+
+  store_gacha_url(database, &gacha_url).await;
+  Ok(gacha_url.url.to_url(None, None, None).to_string())
+}
+
+// Touch the stored gacha url once it has fetched, or delete it if it has expired.
+// It is no longer valid, the next fetch will read it from the webcaches again.
+async fn settle_stored_gacha_url(
+  database: &Database,
+  business: Business,
+  uid: u32,
+  gacha_url: &str,
+  fetched: &Result<GachaRecordsFetched, GachaRecordsFetcherError>,
+) {
+  match fetched {
+    Ok(_) => {
+      let now = OffsetDateTime::now_utc().to_offset(*consts::LOCAL_OFFSET);
+      if let Err(error) =
+        StoredGachaUrlQuestioner::touch_stored_gacha_url(database, business, uid, gacha_url, now)
+          .await
+      {
+        warn!("Error touching stored gacha url: {error}");
+      }
+    }
+    Err(GachaRecordsFetcherError::GachaUrl(error))
+      if matches!(error.as_ref(), GachaUrlErrorKind::AuthkeyTimeout) =>
+    {
+      if let Err(error) =
+        StoredGachaUrlQuestioner::delete_stored_gacha_url(database, business, uid, gacha_url).await
+      {
+        warn!("Error deleting stored gacha url: {error}");
+      }
+    }
+    Err(_) => {}
+  }
+}
+
+// The creation time of the stored gacha url, if it is the same one.
+async fn stored_gacha_url_creation_time(
+  database: &Database,
+  business: Business,
+  uid: u32,
+  gacha_url: &str,
+) -> Result<Option<OffsetDateTime>, SqlxError> {
+  Ok(
+    StoredGachaUrlQuestioner::find_stored_gacha_url_by_business_and_uid(database, business, uid)
+      .await?
+      .filter(|stored| stored.value == gacha_url)
+      .map(|stored| stored.creation_time),
+  )
 }
 
 #[derive(Copy, Clone, Debug, Deserialize)]
 pub enum GachaRecordSaveToDatabase {
   No,
   Yes,
+  // Replace the newer records page by page, and resume the interrupted gacha types.
   FullUpdate,
+  // Commit each page as it arrives, and resume the interrupted gacha types.
+  Stream,
+  // Preview the changes of the full update, nothing is written.
+  DryRun,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum GachaRecordsFetcherOutput {
+  // Positive numbers are added, negative numbers are deleted
+  Changes(i64),
+  DryRun(GachaRecordsDiff),
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -79,152 +353,334 @@ pub async fn business_create_gacha_records_fetcher(
   business: Business,
   region: BusinessRegion,
   uid: u32,
-  gacha_url: String,
-  mut gacha_type_and_last_end_id_mappings: Vec<(u32, Option<String>)>,
+  gacha_url: Option<String>,
+  gacha_type_and_last_end_id_mappings: Vec<(u32, Option<String>)>,
   event_channel: Option<String>,
   save_to_database: Option<GachaRecordSaveToDatabase>,
   save_on_conflict: Option<GachaRecordSaveOnConflict>,
-) -> Result<i64, Box<dyn ErrorDetails + Send + 'static>> {
+  fetcher_id: Option<String>,
+  resume: Option<bool>,
+  fetch_unknown_gacha_types: Option<bool>,
+) -> Result<GachaRecordsFetcherOutput, Box<dyn ErrorDetails + Send + 'static>> {
   let save_to_database = save_to_database.unwrap_or(GachaRecordSaveToDatabase::No);
   let save_on_conflict = save_on_conflict.unwrap_or(GachaRecordSaveOnConflict::Nothing);
 
-  // The last_end_id value is discarded on full update
-  if matches!(save_to_database, GachaRecordSaveToDatabase::FullUpdate) {
-    for (_, last_end_id) in gacha_type_and_last_end_id_mappings.iter_mut() {
-      last_end_id.take();
+  let session = GachaUrlSession::from_env();
+  let gacha_url = match gacha_url {
+    Some(gacha_url) => gacha_url,
+    None => resolve_gacha_url(&database, session.transport.as_ref(), business, uid)
+      .await
+      .map_err(GachaRecordsFetcherError::boxed)?,
+  };
+
+  // The last end ids are discarded, same as the full update
+  let gacha_type_and_last_end_id_mappings = match save_to_database {
+    GachaRecordSaveToDatabase::DryRun => gacha_type_and_last_end_id_mappings
+      .into_iter()
+      .map(|(gacha_type, _)| (gacha_type, None))
+      .collect(),
+    _ => gacha_type_and_last_end_id_mappings,
+  };
+
+  let gacha_types = gacha_type_and_last_end_id_mappings
+    .iter()
+    .map(|(gacha_type, _)| *gacha_type)
+    .collect::<Vec<_>>();
+
+  let streaming = match save_to_database {
+    GachaRecordSaveToDatabase::No
+    | GachaRecordSaveToDatabase::Yes
+    | GachaRecordSaveToDatabase::DryRun => None,
+    GachaRecordSaveToDatabase::FullUpdate | GachaRecordSaveToDatabase::Stream => {
+      // Start over, instead of resuming the interrupted sync
+      if !resume.unwrap_or(true) {
+        GachaRecordsCheckpointQuestioner::delete_gacha_records_checkpoints_by_business_and_uid(
+          &database, business, uid,
+        )
+        .await
+        .map_err(Error::boxed)?;
+      }
+
+      let gacha_url_creation_time =
+        stored_gacha_url_creation_time(&database, business, uid, &gacha_url)
+          .await
+          .map_err(Error::boxed)?;
+
+      Some(GachaRecordsStreaming::new(
+        database.inner().clone(),
+        save_on_conflict,
+        matches!(save_to_database, GachaRecordSaveToDatabase::FullUpdate),
+        gacha_url_creation_time,
+      ))
     }
-  }
+  };
 
-  let records = create_gacha_records_fetcher(
+  let progress_sink = progress_sink_from_event_channel(window, event_channel);
+  let fetched = create_gacha_records_fetcher(
     business,
     region,
     uid,
-    gacha_url,
+    session.transport.clone(),
+    gacha_url.clone(),
     gacha_type_and_last_end_id_mappings,
-    window,
-    event_channel,
+    fetch_unknown_gacha_types.unwrap_or(false),
+    progress_sink.as_ref(),
+    fetcher_id,
+    streaming,
   )
-  .await?
-  .unwrap_or(Vec::new());
+  .await;
 
-  if records.is_empty() {
-    return Ok(0);
-  }
+  session.finish(format_args!("{business:?}_{uid}"));
+
+  settle_stored_gacha_url(&database, business, uid, &gacha_url, &fetched).await;
+
+  let GachaRecordsFetched {
+    records,
+    pending,
+    changes,
+  } = fetched.map_err(GachaRecordsFetcherError::boxed)?;
+
+  let changes = match save_to_database {
+    GachaRecordSaveToDatabase::No => {
+      // Same as the records, they are not saved. The count is reported by the fragments.
+      if !pending.is_empty() {
+        warn!("{} pieces of pending data are not saved", pending.len());
+      }
 
-  match save_to_database {
-    GachaRecordSaveToDatabase::No => Ok(0),
+      0
+    }
     GachaRecordSaveToDatabase::Yes => {
-      let changes =
-        GachaRecordQuestioner::create_gacha_records(&database, records, save_on_conflict, None)
+      if !pending.is_empty() {
+        PendingGachaRecordQuestioner::create_pending_gacha_records(&database, pending)
           .await
-          .map_err(Error::boxed)? as i64;
+          .map_err(Error::boxed)?;
+      }
 
-      Ok(changes)
+      if records.is_empty() {
+        return Ok(GachaRecordsFetcherOutput::Changes(0));
+      }
+
+      GachaRecordQuestioner::create_gacha_records(
+        &database,
+        records,
+        save_on_conflict,
+        &GachaRecordsAuditSource::Fetch,
+        None,
+      )
+      .await
+      .map_err(Error::boxed)? as i64
     }
-    GachaRecordSaveToDatabase::FullUpdate => {
-      let groups: HashMap<u32, Vec<GachaRecord>> =
-        records.into_iter().fold(HashMap::new(), |mut acc, record| {
-          match acc.entry(record.gacha_type) {
-            hash_map::Entry::Occupied(mut o) => {
-              o.get_mut().push(record);
-            }
-            hash_map::Entry::Vacant(o) => {
-              o.insert(vec![record]);
-            }
-          }
-          acc
-        });
-
-      let mut deleted: i64 = 0;
-      let mut created: i64 = 0;
-
-      for (gacha_type, records) in groups {
-        if records.is_empty() {
-          continue;
-        }
+    // Records have been committed while fetching
+    GachaRecordSaveToDatabase::FullUpdate | GachaRecordSaveToDatabase::Stream => changes,
+    GachaRecordSaveToDatabase::DryRun => {
+      let local =
+        GachaRecordQuestioner::find_gacha_records_by_business_and_uid(&database, business, uid)
+          .await
+          .map_err(Error::boxed)?;
 
-        let oldest_end_id = records.last().map(|record| record.id.as_str()).unwrap();
+      let diff = diff_gacha_records(business, uid, &gacha_types, &records, &pending, &local);
+      return Ok(GachaRecordsFetcherOutput::DryRun(diff));
+    }
+  };
 
-        deleted += GachaRecordQuestioner::delete_gacha_records_by_newer_than_end_id(
-          &database,
-          business,
-          uid,
-          gacha_type,
-          oldest_end_id,
-        )
-        .await
-        .map_err(Error::boxed)? as i64;
+  Ok(GachaRecordsFetcherOutput::Changes(changes))
+}
 
-        created +=
-          GachaRecordQuestioner::create_gacha_records(&database, records, save_on_conflict, None)
-            .await
-            .map_err(Error::boxed)? as i64;
-      }
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_apply_gacha_records_diff(
+  database: DatabaseState<'_>,
+  diff: GachaRecordsDiff,
+) -> Result<i64, Box<dyn ErrorDetails + Send + 'static>> {
+  apply_gacha_records_diff(&database, diff)
+    .await
+    .map_err(Error::boxed)
+}
 
-      let changes = created - deleted;
+// Only the completed gacha types are kept, unless discarded.
+// The streaming fetcher has committed the pages, so it cannot be discarded.
+// See: `cancel_gacha_records_fetcher`
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_cancel_gacha_records_fetcher(
+  fetcher_id: String,
+  discard: Option<bool>,
+) -> Result<bool, Box<dyn ErrorDetails + Send + 'static>> {
+  cancel_gacha_records_fetcher(&fetcher_id, discard.unwrap_or(false)).map_err(Error::boxed)
+}
 
-      Ok(changes)
-    }
-  }
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_sync_all_accounts(
+  window: WebviewWindow,
+  database: DatabaseState<'_>,
+  event_channel: Option<String>,
+) -> Result<Vec<GachaRecordsSyncResult>, Box<dyn ErrorDetails + Send + 'static>> {
+  let progress_sink = progress_sink_from_event_channel(window, event_channel);
+
+  let session = GachaUrlSession::from_env();
+  let results = sync_all_accounts(
+    database.inner(),
+    session.transport.clone(),
+    progress_sink.as_ref(),
+  )
+  .await;
+
+  session.finish("Sync");
+  results.map_err(Error::boxed)
 }
 
 #[tauri::command]
 #[tracing::instrument(skip_all)]
-pub async fn business_import_gacha_records(
+pub async fn business_auto_sync_schedule() -> AutoSyncSchedule {
+  auto_sync_schedule()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip(database))]
+pub async fn business_update_auto_sync_schedule(
+  database: DatabaseState<'_>,
+  schedule: AutoSyncSchedule,
+) -> Result<AutoSyncSchedule, Box<dyn ErrorDetails + Send + 'static>> {
+  let schedule = schedule.sanitized();
+
+  KvMut::from(database.as_ref(), consts::KV_AUTO_SYNC_SCHEDULE)
+    .try_write_json(&schedule)
+    .await
+    .expect("Error serializing auto sync schedule")
+    .map_err(Error::boxed)?;
+
+  Ok(set_auto_sync_schedule(schedule))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_auto_sync_state(
+  database: DatabaseState<'_>,
+) -> Result<Option<AutoSyncState>, Box<dyn ErrorDetails + Send + 'static>> {
+  auto_sync_state(database.as_ref())
+    .await
+    .map_err(Error::boxed)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_verify_gacha_records(
   window: WebviewWindow,
   database: DatabaseState<'_>,
+  business: Business,
+  uid: u32,
+  gacha_url: Option<String>,
+  gacha_types: Option<Vec<u32>>,
+  event_channel: Option<String>,
+) -> Result<GachaRecordsReconciliation, Box<dyn ErrorDetails + Send + 'static>> {
+  let session = GachaUrlSession::from_env();
+  let gacha_url = match gacha_url {
+    Some(gacha_url) => gacha_url,
+    None => resolve_gacha_url(&database, session.transport.as_ref(), business, uid)
+      .await
+      .map_err(GachaRecordsFetcherError::boxed)?,
+  };
+
+  let progress_sink = progress_sink_from_event_channel(window, event_channel);
+  let reconciliation = verify_gacha_records(
+    &database,
+    business,
+    uid,
+    session.transport.as_ref(),
+    &gacha_url,
+    gacha_types,
+    progress_sink.as_ref(),
+  )
+  .await;
+
+  session.finish(format_args!("Verify_{business:?}_{uid}"));
+  let reconciliation = reconciliation.map_err(GachaRecordsFetcherError::boxed)?;
+
+  if reconciliation.is_consistent() {
+    info!("Gacha records are consistent with the server");
+  }
+
+  Ok(reconciliation)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_apply_gacha_records_reconciliation(
+  database: DatabaseState<'_>,
+  reconciliation: GachaRecordsReconciliation,
+) -> Result<i64, Box<dyn ErrorDetails + Send + 'static>> {
+  apply_gacha_records_reconciliation(&database, reconciliation)
+    .await
+    .map_err(Error::boxed)
+}
+
+#[tracing::instrument(skip(database, progress_sink))]
+pub async fn import_gacha_records(
+  database: &Database,
   input: PathBuf,
   importer: GachaRecordsImporter,
-  save_on_conflict: Option<GachaRecordSaveOnConflict>,
-  progress_channel: Option<String>,
+  save_on_conflict: GachaRecordSaveOnConflict,
+  progress_sink: &dyn ProgressSink<f32>,
 ) -> Result<u64, Box<dyn ErrorDetails + Send + 'static>> {
-  let records = importer.import(GachaMetadata::current(), input)?;
-
-  // Progress reporting
-  let (progress_reporter, progress_task) = if let Some(event_channel) = progress_channel {
-    let (reporter, mut receiver) = mpsc::channel(1);
-    let task = tokio::spawn(async move {
-      while let Some(progress) = receiver.recv().await {
-        window.emit(&event_channel, &progress).unwrap(); // FIXME: emit SAFETY?
-      }
-    });
-
-    (Some(reporter), Some(task))
-  } else {
-    (None, None)
+  let records = importer.import(GachaMetadata::current(), &input)?;
+
+  // Identify the imported file in the audit log
+  let file_hash = match fs::read(&input) {
+    Ok(bytes) => Some(gacha_metadata::sha1sum(bytes)),
+    Err(error) => {
+      warn!("Error reading the imported file for the hash: {error}");
+      None
+    }
   };
 
   let changes = GachaRecordQuestioner::create_gacha_records(
-    database.as_ref(),
+    database,
     records,
-    save_on_conflict.unwrap_or(GachaRecordSaveOnConflict::Nothing),
-    progress_reporter,
+    save_on_conflict,
+    &GachaRecordsAuditSource::Import { file_hash },
+    Some(progress_sink),
   )
   .await
   .map_err(Error::boxed)?;
 
-  // Wait for the progress task to finish
-  if let Some(progress_task) = progress_task {
-    progress_task.await.unwrap(); // FIXME: SAFETY?
-  }
-
   Ok(changes)
 }
 
 #[tauri::command]
 #[tracing::instrument(skip_all)]
-pub async fn business_export_gacha_records(
+pub async fn business_import_gacha_records(
   window: WebviewWindow,
   database: DatabaseState<'_>,
+  input: PathBuf,
+  importer: GachaRecordsImporter,
+  save_on_conflict: Option<GachaRecordSaveOnConflict>,
+  progress_channel: Option<String>,
+) -> Result<u64, Box<dyn ErrorDetails + Send + 'static>> {
+  let progress_sink = progress_sink_from_event_channel(window, progress_channel);
+
+  import_gacha_records(
+    database.as_ref(),
+    input,
+    importer,
+    save_on_conflict.unwrap_or(GachaRecordSaveOnConflict::Nothing),
+    progress_sink.as_ref(),
+  )
+  .await
+}
+
+// Reports the progress of loading the records of accounts, and then 1.0 when it is written.
+#[tracing::instrument(skip(database, progress_sink))]
+pub async fn export_gacha_records(
+  database: &Database,
   output: PathBuf,
   exporter: GachaRecordsExporter,
+  progress_sink: &dyn ProgressSink<f32>,
 ) -> Result<PathBuf, Box<dyn ErrorDetails + Send + 'static>> {
-  // TODO: Progress reporting
-
   let records = match &exporter {
     GachaRecordsExporter::LegacyUigf(writer) => {
       GachaRecordQuestioner::find_gacha_records_by_business_and_uid(
-        database.as_ref(),
+        database,
         Business::GenshinImpact,
         writer.account_uid,
       )
@@ -233,10 +689,11 @@ pub async fn business_export_gacha_records(
     }
     GachaRecordsExporter::Uigf(writer) => {
       let mut records = Vec::new();
+      let total = writer.accounts.len();
 
-      for account_uid in writer.accounts.keys() {
+      for (completes, account_uid) in writer.accounts.keys().enumerate() {
         let account_records = GachaRecordQuestioner::find_gacha_records_by_businesses_or_uid(
-          database.as_ref(),
+          database,
           writer.businesses.as_ref(),
           *account_uid,
         )
@@ -244,13 +701,17 @@ pub async fn business_export_gacha_records(
         .map_err(Error::boxed)?;
 
         records.extend(account_records);
+
+        // The rest is for writing
+        let progress = (completes + 1) as f32 / total as f32 * 0.9;
+        progress_sink.report((progress * 100.).round() / 100.);
       }
 
       records
     }
     GachaRecordsExporter::Srgf(writer) => {
       GachaRecordQuestioner::find_gacha_records_by_business_and_uid(
-        database.as_ref(),
+        database,
         Business::HonkaiStarRail,
         writer.account_uid,
       )
@@ -259,7 +720,24 @@ pub async fn business_export_gacha_records(
     }
   };
 
-  exporter.export(GachaMetadata::current(), records, output)
+  let output = exporter.export(GachaMetadata::current(), records, output)?;
+  progress_sink.report(1.0);
+
+  Ok(output)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_export_gacha_records(
+  window: WebviewWindow,
+  database: DatabaseState<'_>,
+  output: PathBuf,
+  exporter: GachaRecordsExporter,
+  progress_channel: Option<String>,
+) -> Result<PathBuf, Box<dyn ErrorDetails + Send + 'static>> {
+  let progress_sink = progress_sink_from_event_channel(window, progress_channel);
+
+  export_gacha_records(database.as_ref(), output, exporter, progress_sink.as_ref()).await
 }
 
 #[tauri::command]
@@ -293,10 +771,47 @@ pub async fn business_gacha_metadata_is_updating() -> bool {
   GachaMetadata::is_updating()
 }
 
+/// Resolve the item ids of the pending records with the current metadata,
+/// and move the resolved ones into the gacha records.
+#[tracing::instrument(skip(database))]
+pub async fn resolve_pending_gacha_records(database: &Database) -> Result<u64, SqlxError> {
+  let metadata = GachaMetadata::current();
+
+  PendingGachaRecordQuestioner::resolve_pending_gacha_records(database, &|record| {
+    metadata
+      .locale(record.business, &record.lang)
+      .and_then(|map| map.entry_from_name_first(&record.name))
+      .map(|entry| entry.id)
+  })
+  .await
+}
+
+/// Update the gacha metadata, then resolve the pending records in the background.
+pub async fn update_gacha_metadata(
+  database: Arc<Database>,
+) -> Result<GachaMetadataUpdatedKind, Box<dyn std::error::Error + Send + Sync + 'static>> {
+  let kind = GachaMetadata::update().await?;
+
+  if matches!(kind, GachaMetadataUpdatedKind::Success(_)) {
+    tokio::spawn(async move {
+      match resolve_pending_gacha_records(&database).await {
+        Ok(resolved) => info!("Resolved {resolved} pending gacha records"),
+        Err(error) => warn!("Error resolving pending gacha records: {error}"),
+      }
+    });
+  }
+
+  Ok(kind)
+}
+
 #[tauri::command]
 #[tracing::instrument(skip_all)]
-pub async fn business_gacha_metadata_update() -> Result<GachaMetadataUpdatedKind, String> {
-  GachaMetadata::update().await.map_err(|e| e.to_string())
+pub async fn business_gacha_metadata_update(
+  database: DatabaseState<'_>,
+) -> Result<GachaMetadataUpdatedKind, String> {
+  update_gacha_metadata(database.inner().clone())
+    .await
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -312,4 +827,143 @@ pub async fn business_gacha_metadata_item_name_from_id(
     .map(|entry| entry.name.to_owned())
 }
 
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_gacha_url_rate_limiter_settings() -> RateLimiterSettings {
+  gacha_url_rate_limiter_settings()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip(database))]
+pub async fn business_update_gacha_url_rate_limiter_settings(
+  database: DatabaseState<'_>,
+  settings: RateLimiterSettings,
+) -> Result<RateLimiterSettings, Box<dyn ErrorDetails + Send + 'static>> {
+  let settings = settings.sanitized();
+
+  KvMut::from(database.as_ref(), consts::KV_GACHA_URL_RATE_LIMITER)
+    .try_write_json(&settings)
+    .await
+    .expect("Error serializing gacha url rate limiter settings")
+    .map_err(Error::boxed)?;
+
+  Ok(set_gacha_url_rate_limiter_settings(settings))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all)]
+pub async fn business_endpoint_overrides() -> EndpointOverrides {
+  endpoint_overrides()
+}
+
+#[tauri::command]
+#[tracing::instrument(skip(database))]
+pub async fn business_update_endpoint_overrides(
+  database: DatabaseState<'_>,
+  overrides: EndpointOverrides,
+) -> Result<EndpointOverrides, Box<dyn ErrorDetails + Send + 'static>> {
+  let overrides = overrides.validated().map_err(Error::boxed)?;
+
+  KvMut::from(database.as_ref(), consts::KV_ENDPOINT_OVERRIDES)
+    .try_write_json(&overrides)
+    .await
+    .expect("Error serializing endpoint overrides")
+    .map_err(Error::boxed)?;
+
+  set_endpoint_overrides(overrides).map_err(Error::boxed)
+}
+
 // endregion
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::{BizInternals, GachaUrlEndpointOverride};
+  use crate::utilities::redact::redact_authkeys;
+
+  #[tokio::test]
+  async fn test_stored_gacha_url_endpoint_override() {
+    const GACHA_URL: &str = "https://public-operation-nap-sg.hoyoverse.com/common/gacha_record/api/getGachaLog?authkey_ver=1&sign_type=2&authkey=SECRET&lang=en&region=prod_gf_jp&game_biz=nap_global&real_gacha_type=1";
+    const BASE_URL: &str = "http://localhost:8080/getGachaLog";
+
+    // Only the business and region that the other tests do not request
+    set_endpoint_overrides(EndpointOverrides {
+      gacha_urls: vec![GachaUrlEndpointOverride {
+        business: Business::ZenlessZoneZero,
+        region: Some(BusinessRegion::Global),
+        base_url: BASE_URL.into(),
+      }],
+      ..Default::default()
+    })
+    .unwrap();
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let database = Database::new_with(temp_dir.path().join("test.db")).await;
+
+    let gacha_url = GachaUrl {
+      url: ParsedGachaUrl::from_str(GACHA_URL).unwrap(),
+      owner_uid: 1_300_000_001,
+      creation_time: None,
+    };
+
+    store_gacha_url(&database, &gacha_url).await;
+
+    // Stored on the official endpoint, without any request
+    let stored = resolve_gacha_url(
+      &database,
+      &ReplayGachaUrlTransport::new([]),
+      Business::ZenlessZoneZero,
+      1_300_000_001,
+    )
+    .await
+    .unwrap();
+
+    let official = BizInternals::mapped(Business::ZenlessZoneZero, BusinessRegion::Global);
+    assert!(stored.starts_with(official.base_gacha_url));
+
+    // But requested on the override
+    let request_url =
+      ParsedGachaUrl::from_str(&stored)
+        .unwrap()
+        .to_request_url(Some("1"), None, None);
+    assert!(request_url.as_str().starts_with(BASE_URL));
+
+    let transport = ReplayGachaUrlTransport::new([GachaUrlExchange {
+      url: redact_authkeys(request_url.as_str()).into_owned(),
+      response: serde_json::json!({
+        "retcode": 0,
+        "message": "OK",
+        "data": {
+          "region": "prod_gf_jp",
+          "list": [{
+            "id": "1000000000000000001",
+            "uid": "1300000001",
+            "gacha_type": "1",
+            "gacha_id": "0",
+            "rank_type": "2",
+            "count": "1",
+            "time": "2024-01-01 00:00:00",
+            "lang": "en",
+            "name": "Name",
+            "item_type": "W-Engines",
+            "item_id": "12001",
+          }],
+        },
+      })
+      .to_string(),
+    }]);
+
+    let page = fetch_gacha_records(&transport, &stored, Some("1"), None, None)
+      .await
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(page.records.len(), 1);
+    assert_eq!(page.records[0].gacha_type, 1);
+
+    set_endpoint_overrides(EndpointOverrides::default()).unwrap();
+    database.close().await;
+  }
+}
@@ -4,7 +4,6 @@ use std::sync::LazyLock;
 
 use cfg_if::cfg_if;
 use os_info::{Info as OsInfo, get as get_os_info};
-use reqwest::Client as Reqwest;
 use time::UtcOffset;
 use time::format_description::FormatItem;
 use time::macros::format_description;
@@ -75,11 +74,24 @@ pub const TAURI_MAIN_WINDOW_DECORATIONS: bool = false;
 // Environment variables
 
 pub const ENV_DEVTOOLS: &str = "HG_DEVTOOLS";
+// Folder to save the recorded gacha url exchanges of each fetcher
+pub const ENV_GACHA_URL_RECORDING: &str = "HG_GACHA_URL_RECORDING";
+#[cfg(target_os = "linux")]
+pub const ENV_WINE_PREFIX: &str = "HG_WINE_PREFIX";
 
 // Known database kvs
 
 pub const KV_THEME_DATA: &str = "HG_THEME_DATA";
 pub const KV_WINDOW_STATE: &str = "HG_WINDOW_STATE";
+pub const KV_GACHA_URL_RATE_LIMITER: &str = "HG_GACHA_URL_RATE_LIMITER";
+pub const KV_ENDPOINT_OVERRIDES: &str = "HG_ENDPOINT_OVERRIDES";
+pub const KV_HTTP_CLIENT_SETTINGS: &str = "HG_HTTP_CLIENT_SETTINGS";
+pub const KV_AUTO_SYNC_SCHEDULE: &str = "HG_AUTO_SYNC_SCHEDULE";
+pub const KV_AUTO_SYNC_STATE: &str = "HG_AUTO_SYNC_STATE";
+
+// Events emitted by the backend
+
+pub const EVENT_AUTO_SYNC: &str = "HG_AUTO_SYNC";
 
 // Lazy
 
@@ -88,13 +100,6 @@ pub static OS_INFO: LazyLock<OsInfo> = LazyLock::new(get_os_info);
 pub static LOCAL_OFFSET: LazyLock<UtcOffset> =
   LazyLock::new(|| UtcOffset::current_local_offset().unwrap());
 
-pub static REQWEST: LazyLock<Reqwest> = LazyLock::new(|| {
-  Reqwest::builder()
-    .user_agent(format!("{APP_NAME}/{VERSION}"))
-    .build()
-    .unwrap()
-});
-
 // region: Locale
 
 pub struct Locale {
@@ -171,6 +176,9 @@ pub struct Platform {
   pub appdata_local: PathBuf,
   pub appdata_locallow_mihoyo: PathBuf,
   pub appdata_locallow_cognosphere: PathBuf,
+  /// Wine prefix that the games run in. (Wine, Proton, anime-game-launcher)
+  #[cfg(target_os = "linux")]
+  pub wine_prefix: PathBuf,
 }
 
 impl Platform {
@@ -210,6 +218,111 @@ impl Platform {
         appdata_local: appdata_local,
       }
     }
+  } else if #[cfg(target_os = "linux")] {
+    pub const WINE_PROTON_USER: &'static str = "steamuser";
+
+    fn new() -> Self {
+      let user_home = env::var("HOME")
+        .map(PathBuf::from)
+        .expect("Failed to get user home directory");
+
+      // XDG Base Directory
+      //   https://specifications.freedesktop.org/basedir-spec/latest/
+      let xdg_dir = |key: &str, default: &str| {
+        env::var_os(key)
+          .map(PathBuf::from)
+          .filter(|path| path.is_absolute())
+          .unwrap_or_else(|| user_home.join(default))
+      };
+
+      // The user directories are defined in `user-dirs.dirs`, and normally not exported
+      //   https://www.freedesktop.org/wiki/Software/xdg-user-dirs/
+      let desktop = env::var_os("XDG_DESKTOP_DIR")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| {
+          let user_dirs = xdg_dir("XDG_CONFIG_HOME", ".config").join("user-dirs.dirs");
+          let user_dirs = std::fs::read_to_string(user_dirs).ok()?;
+          Self::xdg_user_dir(&user_home, &user_dirs, "XDG_DESKTOP_DIR")
+        })
+        .unwrap_or_else(|| user_home.join("Desktop"));
+
+      let appdata_local = xdg_dir("XDG_DATA_HOME", ".local/share");
+      let wine_prefix = Self::resolve_wine_prefix(&user_home, |key| env::var_os(key));
+
+      let appdata_locallow = Self::wine_appdata_locallow(&wine_prefix);
+      Self {
+        user_home,
+        desktop,
+        appdata_local,
+        appdata_locallow_mihoyo: appdata_locallow.join(Self::FOLDER_MIHOYO),
+        appdata_locallow_cognosphere: appdata_locallow.join(Self::FOLDER_COGNOSPHERE),
+        wine_prefix,
+      }
+    }
+
+    // Value of the key in `user-dirs.dirs`, e.g. `XDG_DESKTOP_DIR="$HOME/Desktop"`.
+    // Only `$HOME` relative or absolute paths are allowed.
+    fn xdg_user_dir(user_home: &std::path::Path, user_dirs: &str, key: &str) -> Option<PathBuf> {
+      user_dirs
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| {
+          let value = line.strip_prefix(key)?.trim_start().strip_prefix('=')?.trim();
+          let value = value.strip_prefix('"')?.strip_suffix('"')?;
+          let path = match value.strip_prefix("$HOME") {
+            Some(relative) => user_home.join(relative.trim_start_matches('/')),
+            None => PathBuf::from(value),
+          };
+
+          path.is_absolute().then_some(path)
+        })
+    }
+
+    // Wine prefix priority: HG_WINE_PREFIX > WINEPREFIX > ~/.wine
+    fn resolve_wine_prefix(
+      user_home: &std::path::Path,
+      env_var: impl Fn(&str) -> Option<std::ffi::OsString>,
+    ) -> PathBuf {
+      [ENV_WINE_PREFIX, "WINEPREFIX"]
+        .into_iter()
+        .filter_map(env_var)
+        .map(PathBuf::from)
+        .find(|path| path.is_absolute())
+        .unwrap_or_else(|| user_home.join(".wine"))
+    }
+
+    /// `drive_c/users/<user>/AppData/LocalLow` inside the Wine prefix.
+    ///
+    /// Wine uses the name of the current user, and Proton always uses `steamuser`.
+    /// Otherwise, the first user folder other than `Public` is used.
+    pub fn wine_appdata_locallow(wine_prefix: &std::path::Path) -> PathBuf {
+      Self::wine_appdata_locallow_of(wine_prefix, env::var("USER").ok().as_deref())
+    }
+
+    fn wine_appdata_locallow_of(
+      wine_prefix: &std::path::Path,
+      current_user: Option<&str>,
+    ) -> PathBuf {
+      let users = wine_prefix.join("drive_c").join("users");
+
+      let user_folder = current_user
+        .into_iter()
+        .chain([Self::WINE_PROTON_USER])
+        .map(|user| users.join(user))
+        .find(|folder| folder.is_dir())
+        .or_else(|| {
+          std::fs::read_dir(&users)
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .find(|path| path.is_dir() && !path.ends_with("Public"))
+        })
+        .unwrap_or_else(|| users.join(current_user.unwrap_or(Self::WINE_PROTON_USER)));
+
+      user_folder.join("AppData").join("LocalLow")
+    }
   } else {
     fn new() -> Self {
       unimplemented!()
@@ -220,3 +333,114 @@ impl Platform {
 pub static PLATFORM: LazyLock<Platform> = LazyLock::new(Platform::new);
 
 // endregion
+
+// Tests
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+  use std::collections::HashMap;
+  use std::ffi::OsString;
+  use std::fs;
+  use std::path::Path;
+
+  use super::*;
+
+  #[test]
+  fn test_xdg_user_dir() {
+    let user_home = Path::new("/home/user");
+    let user_dirs = r#"
+# This file is written by xdg-user-dirs-update
+XDG_DOWNLOAD_DIR="$HOME/Downloads"
+XDG_DESKTOP_DIR="$HOME/Schreibtisch"
+XDG_MUSIC_DIR="/mnt/music"
+XDG_VIDEOS_DIR="Videos"
+"#;
+
+    assert_eq!(
+      Platform::xdg_user_dir(user_home, user_dirs, "XDG_DESKTOP_DIR"),
+      Some(PathBuf::from("/home/user/Schreibtisch"))
+    );
+    assert_eq!(
+      Platform::xdg_user_dir(user_home, user_dirs, "XDG_MUSIC_DIR"),
+      Some(PathBuf::from("/mnt/music"))
+    );
+
+    // Relative paths are not allowed
+    assert_eq!(
+      Platform::xdg_user_dir(user_home, user_dirs, "XDG_VIDEOS_DIR"),
+      None
+    );
+    assert_eq!(
+      Platform::xdg_user_dir(user_home, user_dirs, "XDG_PICTURES_DIR"),
+      None
+    );
+  }
+
+  #[test]
+  fn test_resolve_wine_prefix() {
+    let user_home = Path::new("/home/user");
+    let resolve = |vars: &[(&str, &str)]| {
+      let vars = vars
+        .iter()
+        .map(|(key, val)| (key.to_string(), OsString::from(val)))
+        .collect::<HashMap<_, _>>();
+
+      Platform::resolve_wine_prefix(user_home, |key| vars.get(key).cloned())
+    };
+
+    assert_eq!(
+      resolve(&[(ENV_WINE_PREFIX, "/opt/hg"), ("WINEPREFIX", "/opt/wine")]),
+      PathBuf::from("/opt/hg")
+    );
+    assert_eq!(
+      resolve(&[("WINEPREFIX", "/opt/wine")]),
+      PathBuf::from("/opt/wine")
+    );
+
+    // Relative paths are ignored
+    assert_eq!(
+      resolve(&[(ENV_WINE_PREFIX, "hg"), ("WINEPREFIX", "/opt/wine")]),
+      PathBuf::from("/opt/wine")
+    );
+    assert_eq!(resolve(&[]), PathBuf::from("/home/user/.wine"));
+  }
+
+  #[test]
+  fn test_wine_appdata_locallow() {
+    let wine_prefix = tempfile::tempdir().unwrap();
+    let users = wine_prefix.path().join("drive_c").join("users");
+    let locallow = |user: &str| users.join(user).join("AppData").join("LocalLow");
+
+    // Nothing exists, the current user is assumed
+    assert_eq!(
+      Platform::wine_appdata_locallow_of(wine_prefix.path(), Some("alice")),
+      locallow("alice")
+    );
+    assert_eq!(
+      Platform::wine_appdata_locallow_of(wine_prefix.path(), None),
+      locallow(Platform::WINE_PROTON_USER)
+    );
+
+    // The first user folder other than `Public`
+    fs::create_dir_all(users.join("Public")).unwrap();
+    fs::create_dir_all(users.join("bob")).unwrap();
+    assert_eq!(
+      Platform::wine_appdata_locallow_of(wine_prefix.path(), Some("alice")),
+      locallow("bob")
+    );
+
+    // Proton is preferred over other users
+    fs::create_dir_all(users.join(Platform::WINE_PROTON_USER)).unwrap();
+    assert_eq!(
+      Platform::wine_appdata_locallow_of(wine_prefix.path(), Some("alice")),
+      locallow(Platform::WINE_PROTON_USER)
+    );
+
+    // The current user is preferred over all
+    fs::create_dir_all(users.join("alice")).unwrap();
+    assert_eq!(
+      Platform::wine_appdata_locallow_of(wine_prefix.path(), Some("alice")),
+      locallow("alice")
+    );
+  }
+}
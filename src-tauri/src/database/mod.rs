@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -12,19 +12,22 @@ use sqlx::encode::IsNull;
 use sqlx::error::BoxDynError;
 use sqlx::query::{Query, QueryAs};
 use sqlx::sqlite::{
-  Sqlite, SqliteArguments, SqliteConnectOptions, SqlitePool, SqliteQueryResult, SqliteRow,
-  SqliteTypeInfo, SqliteValueRef,
+  Sqlite, SqliteArguments, SqliteConnectOptions, SqliteConnection, SqlitePool, SqliteQueryResult,
+  SqliteRow, SqliteTypeInfo, SqliteValueRef,
 };
 use sqlx::{Decode, Encode, Executor, FromRow, Row, Type};
 use tauri::State as TauriState;
 use time::OffsetDateTime;
-use tokio::sync::mpsc;
 use tracing::{debug, info};
 
 use crate::consts;
 use crate::database::legacy_migration::{LegacyMigrationError, MigrationMetrics};
 use crate::error::{Error, ErrorDetails};
-use crate::models::{Account, AccountProperties, Business, GachaRecord, Kv};
+use crate::models::{
+  Account, AccountProperties, Business, GachaRecord, GachaRecordsAudit, GachaRecordsAuditOperation,
+  GachaRecordsAuditSource, GachaRecordsCheckpoint, Kv, PendingGachaRecord, StoredGachaUrl,
+};
+use crate::utilities::progress_sink::ProgressSink;
 
 mod kvs;
 mod legacy_migration;
@@ -251,7 +254,104 @@ RELEASE start_migration_v2;
 COMMIT TRANSACTION;
 ";
 
-const SQLS: &[&str] = &[SQL_V1, SQL_V2];
+// Changes:
+// Table: `HG_GACHA_URLS`
+//      : Validated gacha urls of the accounts, reused until they have expired.
+
+const SQL_V3: &str = r"
+BEGIN TRANSACTION;
+
+CREATE TABLE IF NOT EXISTS `HG_GACHA_URLS` (
+  `business`      INTEGER NOT NULL,
+  `uid`           INTEGER NOT NULL,
+  `value`         TEXT    NOT NULL,
+  `creation_time` TEXT    NOT NULL,
+  `last_used_at`  TEXT,
+  PRIMARY KEY (`business`, `uid`)
+);
+
+PRAGMA USER_VERSION = 3;
+COMMIT TRANSACTION;
+";
+
+// Changes:
+// Table: `HG_GACHA_RECORDS_CHECKPOINTS`
+//      : Checkpoints of the interrupted gacha records sync, resumed after restarts.
+
+const SQL_V4: &str = r"
+BEGIN TRANSACTION;
+
+CREATE TABLE IF NOT EXISTS `HG_GACHA_RECORDS_CHECKPOINTS` (
+  `business`                INTEGER  NOT NULL,
+  `uid`                     INTEGER  NOT NULL,
+  `gacha_type`              INTEGER  NOT NULL,
+  `end_id`                  TEXT     NOT NULL,
+  `last_end_id`             TEXT,
+  `full_update`             INTEGER  NOT NULL,
+  `gacha_url_creation_time` TEXT,
+  `updated_at`              DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`business`, `uid`, `gacha_type`)
+);
+
+PRAGMA USER_VERSION = 4;
+COMMIT TRANSACTION;
+";
+
+// Changes:
+// Table: `HG_GACHA_RECORDS_PENDING`
+//      : Quarantined gacha records whose item id is missing from the metadata,
+//      : moved into `HG_GACHA_RECORDS` once they are resolved.
+
+const SQL_V5: &str = r"
+BEGIN TRANSACTION;
+
+CREATE TABLE IF NOT EXISTS `HG_GACHA_RECORDS_PENDING` (
+  `business`   INTEGER  NOT NULL,
+  `uid`        INTEGER  NOT NULL,
+  `id`         TEXT     NOT NULL,
+  `gacha_type` INTEGER  NOT NULL,
+  `gacha_id`   INTEGER,
+  `rank_type`  INTEGER  NOT NULL,
+  `count`      INTEGER  NOT NULL,
+  `time`       TEXT     NOT NULL,
+  `lang`       TEXT     NOT NULL,
+  `name`       TEXT     NOT NULL,
+  `item_type`  TEXT     NOT NULL,
+  `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`business`, `uid`, `id`, `gacha_type`)
+);
+
+PRAGMA USER_VERSION = 5;
+COMMIT TRANSACTION;
+";
+
+// Changes:
+// Table: `HG_GACHA_RECORDS_AUDIT`
+//      : Audit log of the changes to `HG_GACHA_RECORDS`, kept after the records are deleted.
+
+const SQL_V6: &str = r"
+BEGIN TRANSACTION;
+
+CREATE TABLE IF NOT EXISTS `HG_GACHA_RECORDS_AUDIT` (
+  `seq`        INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+  `business`   INTEGER  NOT NULL,
+  `uid`        INTEGER  NOT NULL,
+  `id`         TEXT     NOT NULL,
+  `gacha_type` INTEGER  NOT NULL,
+  `operation`  TEXT     NOT NULL,
+  `source`     TEXT     NOT NULL,
+  `before`     TEXT,
+  `after`      TEXT,
+  `created_at` DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS `HG_GACHA_RECORDS_AUDIT.business_uid_idx`    ON `HG_GACHA_RECORDS_AUDIT` (`business`, `uid`);
+CREATE INDEX IF NOT EXISTS `HG_GACHA_RECORDS_AUDIT.business_uid_id_idx` ON `HG_GACHA_RECORDS_AUDIT` (`business`, `uid`, `id`);
+
+PRAGMA USER_VERSION = 6;
+COMMIT TRANSACTION;
+";
+
+const SQLS: &[&str] = &[SQL_V1, SQL_V2, SQL_V3, SQL_V4, SQL_V5, SQL_V6];
 
 // endregion
 
@@ -412,6 +512,10 @@ impl<'r> FromRow<'r, SqliteRow> for Kv {
 declare_questioner_with_handlers! {
   Account,
 
+  "SELECT * FROM `HG_ACCOUNTS` ORDER BY `business` ASC, `uid` ASC;"
+    = find_accounts {
+      }: fetch_all -> Vec<Account>,
+
   "SELECT * FROM `HG_ACCOUNTS` WHERE `business` = ?;"
     = find_accounts_by_business {
         business: Business,
@@ -632,51 +736,49 @@ pub trait GachaRecordQuestionerAdditions {
       .bind(record.item_id)
   }
 
-  #[tracing::instrument(skip(database, records, progress_reporter), fields(records = records.len()))]
+  #[tracing::instrument(skip(database, records, progress_sink), fields(records = records.len()))]
   async fn create_gacha_records(
     database: &Database,
     records: Vec<GachaRecord>,
     save_on_conflict: GachaRecordSaveOnConflict,
-    progress_reporter: Option<mpsc::Sender<f32>>,
+    source: &GachaRecordsAuditSource,
+    progress_sink: Option<&dyn ProgressSink<f32>>,
   ) -> Result<u64, SqlxError> {
     info!("Executing create gacha records database operation...");
     let total = records.len();
     let start = Instant::now();
 
     let mut txn = database.as_ref().begin().await?;
+    let mut trail = GachaRecordsAuditTrail::new(source);
     let mut changes = 0;
     let mut completes = 0;
     let mut last_progress_reported = Instant::now();
 
     for record in records {
       completes += 1;
-      changes +=
-        <GachaRecordQuestioner as GachaRecordQuestionerAdditions>::sql_create_gacha_record(
-          record,
-          save_on_conflict,
-        )
-        .execute(&mut *txn)
-        .await?
-        .rows_affected();
+      changes += trail.create(&mut txn, record, save_on_conflict).await?;
 
       // Progress reporting: 200ms interval
       // Avoiding excessive recording leading to frequent reporting
-      if let Some(reporter) = &progress_reporter {
+      if let Some(sink) = progress_sink {
         if last_progress_reported.elapsed().as_millis() > 200 {
           last_progress_reported = Instant::now();
 
           let progress = completes as f32 / total as f32;
           let progress = (progress * 100.).round() / 100.;
           if progress > 0. {
-            let _ = reporter.try_send(progress);
+            sink.report(progress);
           }
         }
       }
     }
+    trail.write(&mut txn).await?;
     txn.commit().await?;
 
     // Avoiding incomplete progress due to reporting intervals
-    let _ = progress_reporter.map(|reporter| reporter.try_send(1.0));
+    if let Some(sink) = progress_sink {
+      sink.report(1.0);
+    }
 
     info!(
       message = "Creation of gacha records completed",
@@ -692,15 +794,31 @@ pub trait GachaRecordQuestionerAdditions {
     database: &Database,
     business: Business,
     uid: u32,
+    source: &GachaRecordsAuditSource,
   ) -> Result<u64, SqlxError> {
     info!("Executing delete gacha records database operation...");
     let start = Instant::now();
-    let changes = sqlx::query("DELETE FROM `HG_GACHA_RECORDS` WHERE `business` = ? AND `uid` = ?;")
+    let mut txn = database.as_ref().begin().await?;
+    let deleted: Vec<GachaRecord> = sqlx::query_as(
+      "DELETE FROM `HG_GACHA_RECORDS` WHERE `business` = ? AND `uid` = ? RETURNING *;",
+    )
+    .bind(business)
+    .bind(uid)
+    .fetch_all(&mut *txn)
+    .await?;
+
+    let changes = deleted.len() as u64;
+    let mut trail = GachaRecordsAuditTrail::new(source);
+    trail.delete(deleted);
+
+    // Otherwise, they would come back once they are resolved
+    sqlx::query("DELETE FROM `HG_GACHA_RECORDS_PENDING` WHERE `business` = ? AND `uid` = ?;")
       .bind(business)
       .bind(uid)
-      .execute(database.as_ref())
-      .await?
-      .rows_affected();
+      .execute(&mut *txn)
+      .await?;
+    trail.write(&mut txn).await?;
+    txn.commit().await?;
 
     info!(
       message = "Deletion of gacha records completed",
@@ -718,17 +836,24 @@ pub trait GachaRecordQuestionerAdditions {
     uid: u32,
     gacha_type: u32,
     end_id: &str,
+    source: &GachaRecordsAuditSource,
   ) -> Result<u64, SqlxError> {
     info!("Executing delete gacha records by newer than end_id database operation...");
     let start = Instant::now();
-    let changes = sqlx::query("DELETE FROM `HG_GACHA_RECORDS` WHERE `business` = ? AND `uid` = ? AND `gacha_type` = ? AND `id` >= ?;")
+    let mut txn = database.as_ref().begin().await?;
+    let deleted: Vec<GachaRecord> = sqlx::query_as("DELETE FROM `HG_GACHA_RECORDS` WHERE `business` = ? AND `uid` = ? AND `gacha_type` = ? AND `id` >= ? RETURNING *;")
       .bind(business)
       .bind(uid)
       .bind(gacha_type)
       .bind(end_id)
-      .execute(database.as_ref())
-      .await?
-      .rows_affected();
+      .fetch_all(&mut *txn)
+      .await?;
+
+    let changes = deleted.len() as u64;
+    let mut trail = GachaRecordsAuditTrail::new(source);
+    trail.delete(deleted);
+    trail.write(&mut txn).await?;
+    txn.commit().await?;
 
     info!(
       message = "Deletion of gacha records by newer than end_id completed",
@@ -739,6 +864,113 @@ pub trait GachaRecordQuestionerAdditions {
     Ok(changes)
   }
 
+  // Replace the records of the page in a transaction.
+  // The existing records of the requested gacha types whose id is in [end_id, older_than)
+  // are deleted first, even if the page has only the pending records.
+  // The end id is the oldest of the page, including the pending records.
+  #[allow(clippy::too_many_arguments)]
+  #[tracing::instrument(skip(database, records), fields(records = records.len()))]
+  async fn replace_gacha_records(
+    database: &Database,
+    business: Business,
+    uid: u32,
+    gacha_types: &[u32],
+    records: Vec<GachaRecord>,
+    save_on_conflict: GachaRecordSaveOnConflict,
+    source: &GachaRecordsAuditSource,
+    end_id: &str,
+    older_than: Option<&str>,
+  ) -> Result<i64, SqlxError> {
+    info!("Executing replace gacha records database operation...");
+    let start = Instant::now();
+
+    let mut txn = database.as_ref().begin().await?;
+    let mut trail = GachaRecordsAuditTrail::new(source);
+    let mut deleted = 0;
+    let mut created = 0;
+
+    for &gacha_type in gacha_types {
+      let records: Vec<GachaRecord> = sqlx::query_as("DELETE FROM `HG_GACHA_RECORDS` WHERE `business` = ? AND `uid` = ? AND `gacha_type` = ? AND `id` >= ? AND (? IS NULL OR `id` < ?) RETURNING *;")
+        .bind(business)
+        .bind(uid)
+        .bind(gacha_type)
+        .bind(end_id)
+        .bind(older_than)
+        .bind(older_than)
+        .fetch_all(&mut *txn)
+        .await?;
+
+      deleted += records.len() as u64;
+      trail.delete(records);
+    }
+
+    for record in records {
+      created += trail.create(&mut txn, record, save_on_conflict).await?;
+    }
+    trail.write(&mut txn).await?;
+    txn.commit().await?;
+
+    info!(
+      message = "Replacement of gacha records completed",
+      deleted = ?deleted,
+      created = ?created,
+      elapsed = ?start.elapsed(),
+    );
+
+    Ok(created as i64 - deleted as i64)
+  }
+
+  // Delete and create the records in a transaction, e.g. to apply the reconciliation.
+  // Returns the number of created minus deleted.
+  #[tracing::instrument(
+    skip(database, created, deleted),
+    fields(created = created.len(), deleted = deleted.len())
+  )]
+  async fn reconcile_gacha_records(
+    database: &Database,
+    created: Vec<GachaRecord>,
+    deleted: Vec<GachaRecord>,
+    source: &GachaRecordsAuditSource,
+  ) -> Result<i64, SqlxError> {
+    info!("Executing reconcile gacha records database operation...");
+    let start = Instant::now();
+
+    let mut txn = database.as_ref().begin().await?;
+    let mut trail = GachaRecordsAuditTrail::new(source);
+    let mut deletions = 0;
+    let mut creations = 0;
+
+    for record in deleted {
+      let records: Vec<GachaRecord> = sqlx::query_as("DELETE FROM `HG_GACHA_RECORDS` WHERE `business` = ? AND `uid` = ? AND `id` = ? AND `gacha_type` = ? RETURNING *;")
+        .bind(record.business)
+        .bind(record.uid)
+        .bind(record.id)
+        .bind(record.gacha_type)
+        .fetch_all(&mut *txn)
+        .await?;
+
+      deletions += records.len() as u64;
+      trail.delete(records);
+    }
+
+    for record in created {
+      creations += trail
+        .create(&mut txn, record, GachaRecordSaveOnConflict::Nothing)
+        .await?;
+    }
+    trail.write(&mut txn).await?;
+    txn.commit().await?;
+
+    info!(
+      message = "Reconciliation of gacha records completed",
+      deleted = ?deletions,
+      created = ?creations,
+      elapsed = ?start.elapsed(),
+    );
+
+    Ok(creations as i64 - deletions as i64)
+  }
+
   #[tracing::instrument(skip(database))]
   async fn find_gacha_records_by_businesses_and_uid(
     database: &Database,
@@ -795,7 +1027,14 @@ pub mod gacha_record_questioner_additions {
     records: Vec<GachaRecord>,
     on_conflict: GachaRecordSaveOnConflict,
   ) -> Result<u64, SqlxError> {
-    GachaRecordQuestioner::create_gacha_records(database.as_ref(), records, on_conflict, None).await
+    GachaRecordQuestioner::create_gacha_records(
+      database.as_ref(),
+      records,
+      on_conflict,
+      &GachaRecordsAuditSource::Manual,
+      None,
+    )
+    .await
   }
 
   #[tauri::command]
@@ -808,6 +1047,7 @@ pub mod gacha_record_questioner_additions {
       database.as_ref(),
       business,
       uid,
+      &GachaRecordsAuditSource::Manual,
     )
     .await
   }
@@ -841,6 +1081,594 @@ pub mod gacha_record_questioner_additions {
   }
 }
 
+// endregion
+
+// region: GachaRecordsAudit Questioner
+
+declare_questioner_with_handlers! {
+  GachaRecordsAudit,
+
+  "SELECT * FROM `HG_GACHA_RECORDS_AUDIT` WHERE `business` = ? AND `uid` = ? ORDER BY `seq` DESC LIMIT ?;"
+    = find_gacha_records_audits_by_business_and_uid_with_limit {
+        business: Business,
+        uid: u32,
+        limit: u32,
+      }: fetch_all -> Vec<GachaRecordsAudit>,
+
+  "SELECT * FROM `HG_GACHA_RECORDS_AUDIT` WHERE `business` = ? AND `uid` = ? AND `id` = ? ORDER BY `seq` DESC;"
+    = find_gacha_records_audits_by_business_and_uid_and_id {
+        business: Business,
+        uid: u32,
+        id: String,
+      }: fetch_all -> Vec<GachaRecordsAudit>,
+}
+
+impl<'r> FromRow<'r, SqliteRow> for GachaRecordsAudit {
+  fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+    let record = |column: &str| -> Result<Option<GachaRecord>, sqlx::Error> {
+      row
+        .try_get::<Option<String>, _>(column)?
+        .map(|value| serde_json::from_str(&value))
+        .transpose()
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+    };
+
+    Ok(Self {
+      seq: row.try_get("seq")?,
+      business: row.try_get("business")?,
+      uid: row.try_get("uid")?,
+      id: row.try_get("id")?,
+      gacha_type: row.try_get("gacha_type")?,
+      operation: row.try_get("operation")?,
+      source: row.try_get("source")?,
+      before: record("before")?,
+      after: record("after")?,
+      created_at: row.try_get("created_at")?,
+    })
+  }
+}
+
+impl Type<Sqlite> for GachaRecordsAuditOperation {
+  fn type_info() -> SqliteTypeInfo {
+    String::type_info()
+  }
+
+  fn compatible(ty: &SqliteTypeInfo) -> bool {
+    String::compatible(ty)
+  }
+}
+
+impl<'r> Encode<'r, Sqlite> for GachaRecordsAuditOperation {
+  fn encode_by_ref(
+    &self,
+    buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'r>,
+  ) -> Result<IsNull, BoxDynError> {
+    format!("{self:?}").encode_by_ref(buf)
+  }
+}
+
+impl Decode<'_, Sqlite> for GachaRecordsAuditOperation {
+  fn decode(value: SqliteValueRef) -> Result<Self, BoxDynError> {
+    match String::decode(value)?.as_str() {
+      "Create" => Ok(Self::Create),
+      "Update" => Ok(Self::Update),
+      "Delete" => Ok(Self::Delete),
+      other => Err(format!("Unknown gacha records audit operation: {other}").into()),
+    }
+  }
+}
+
+impl Type<Sqlite> for GachaRecordsAuditSource {
+  fn type_info() -> SqliteTypeInfo {
+    String::type_info()
+  }
+
+  fn compatible(ty: &SqliteTypeInfo) -> bool {
+    String::compatible(ty)
+  }
+}
+
+impl<'r> Encode<'r, Sqlite> for GachaRecordsAuditSource {
+  fn encode_by_ref(
+    &self,
+    buf: &mut <Sqlite as sqlx::Database>::ArgumentBuffer<'r>,
+  ) -> Result<IsNull, BoxDynError> {
+    serde_json::to_string(self)
+      .map_err(|e| format!("Failed when serializing gacha records audit source: {e}"))?
+      .encode_by_ref(buf)
+  }
+}
+
+impl Decode<'_, Sqlite> for GachaRecordsAuditSource {
+  fn decode(value: SqliteValueRef) -> Result<Self, BoxDynError> {
+    serde_json::from_str(&String::decode(value)?)
+      .map_err(|e| format!("Failed when deserializing gacha records audit source: {e}").into())
+  }
+}
+
+type GachaRecordKey = (Business, u32, String, u32);
+
+fn gacha_record_key(record: &GachaRecord) -> GachaRecordKey {
+  (
+    record.business,
+    record.uid,
+    record.id.clone(),
+    record.gacha_type,
+  )
+}
+
+// Changes of the gacha records in the transaction, written into the audit log with it.
+struct GachaRecordsAuditTrail<'a> {
+  source: &'a GachaRecordsAuditSource,
+  created: Vec<GachaRecord>,
+  updated: Vec<(GachaRecord, GachaRecord)>,
+  deleted: Vec<GachaRecord>,
+}
+
+impl<'a> GachaRecordsAuditTrail<'a> {
+  fn new(source: &'a GachaRecordsAuditSource) -> Self {
+    Self {
+      source,
+      created: Vec::new(),
+      updated: Vec::new(),
+      deleted: Vec::new(),
+    }
+  }
+
+  // Create the record, the existing one is read first when it can be updated.
+  async fn create(
+    &mut self,
+    conn: &mut SqliteConnection,
+    record: GachaRecord,
+    save_on_conflict: GachaRecordSaveOnConflict,
+  ) -> Result<u64, sqlx::Error> {
+    let before: Option<GachaRecord> = match save_on_conflict {
+      GachaRecordSaveOnConflict::Nothing => None,
+      GachaRecordSaveOnConflict::Update => {
+        sqlx::query_as("SELECT * FROM `HG_GACHA_RECORDS` WHERE `business` = ? AND `uid` = ? AND `id` = ? AND `gacha_type` = ?;")
+          .bind(record.business)
+          .bind(record.uid)
+          .bind(record.id.clone())
+          .bind(record.gacha_type)
+          .fetch_optional(&mut *conn)
+          .await?
+      }
+    };
+
+    let changes =
+      <GachaRecordQuestioner as GachaRecordQuestionerAdditions>::sql_create_gacha_record(
+        record.clone(),
+        save_on_conflict,
+      )
+      .execute(&mut *conn)
+      .await?
+      .rows_affected();
+
+    if changes > 0 {
+      match before {
+        None => self.created.push(record),
+        Some(before) if before != record => self.updated.push((before, record)),
+        Some(_) => {}
+      }
+    }
+
+    Ok(changes)
+  }
+
+  fn delete(&mut self, records: Vec<GachaRecord>) {
+    self.deleted.extend(records);
+  }
+
+  async fn write(self, conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    // The records deleted and created again are updated, e.g. replaced by the full update
+    let mut deleted = self
+      .deleted
+      .into_iter()
+      .map(|record| (gacha_record_key(&record), record))
+      .collect::<HashMap<_, _>>();
+
+    let mut created = Vec::with_capacity(self.created.len());
+    let mut updated = self.updated;
+    for record in self.created {
+      match deleted.remove(&gacha_record_key(&record)) {
+        None => created.push(record),
+        Some(before) if before != record => updated.push((before, record)),
+        Some(_) => {}
+      }
+    }
+
+    let mut deleted = deleted.into_values().collect::<Vec<_>>();
+    deleted.sort();
+
+    // (operation, key of the record, before, after)
+    let entries = created
+      .iter()
+      .map(|after| (GachaRecordsAuditOperation::Create, after, None, Some(after)))
+      .chain(updated.iter().map(|(before, after)| {
+        (
+          GachaRecordsAuditOperation::Update,
+          after,
+          Some(before),
+          Some(after),
+        )
+      }))
+      .chain(deleted.iter().map(|before| {
+        (
+          GachaRecordsAuditOperation::Delete,
+          before,
+          Some(before),
+          None,
+        )
+      }));
+
+    let to_json = |record: Option<&GachaRecord>| {
+      record
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| sqlx::Error::Encode(Box::new(e)))
+    };
+
+    let created_at = OffsetDateTime::now_utc();
+    for (operation, record, before, after) in entries {
+      sqlx::query(
+        "INSERT INTO `HG_GACHA_RECORDS_AUDIT` (
+          `business`, `uid`, `id`, `gacha_type`, `operation`,
+          `source`, `before`, `after`, `created_at`
+        ) VALUES (
+          ?, ?, ?, ?, ?,
+          ?, ?, ?, ?
+        );",
+      )
+      .bind(record.business)
+      .bind(record.uid)
+      .bind(record.id.clone())
+      .bind(record.gacha_type)
+      .bind(operation)
+      .bind(self.source.clone())
+      .bind(to_json(before)?)
+      .bind(to_json(after)?)
+      .bind(created_at)
+      .execute(&mut *conn)
+      .await?;
+    }
+
+    Ok(())
+  }
+}
+
+// endregion
+
+// region: GachaRecordsCheckpoint Questioner
+
+declare_questioner_with_handlers! {
+  GachaRecordsCheckpoint,
+
+  "SELECT * FROM `HG_GACHA_RECORDS_CHECKPOINTS` WHERE `business` = ? AND `uid` = ?;"
+    = find_gacha_records_checkpoints_by_business_and_uid {
+        business: Business,
+        uid: u32,
+      }: fetch_all -> Vec<GachaRecordsCheckpoint>,
+
+  "SELECT * FROM `HG_GACHA_RECORDS_CHECKPOINTS` WHERE `business` = ? AND `uid` = ? AND `gacha_type` = ?;"
+    = find_gacha_records_checkpoint {
+        business: Business,
+        uid: u32,
+        gacha_type: u32,
+      }: fetch_optional -> Option<GachaRecordsCheckpoint>,
+
+  "DELETE FROM `HG_GACHA_RECORDS_CHECKPOINTS` WHERE `business` = ? AND `uid` = ? AND `gacha_type` = ? RETURNING *;"
+    = delete_gacha_records_checkpoint {
+        business: Business,
+        uid: u32,
+        gacha_type: u32,
+      }: fetch_optional -> Option<GachaRecordsCheckpoint>,
+
+  "DELETE FROM `HG_GACHA_RECORDS_CHECKPOINTS` WHERE `business` = ? AND `uid` = ? RETURNING *;"
+    = delete_gacha_records_checkpoints_by_business_and_uid {
+        business: Business,
+        uid: u32,
+      }: fetch_all -> Vec<GachaRecordsCheckpoint>,
+}
+
+impl<'r> FromRow<'r, SqliteRow> for GachaRecordsCheckpoint {
+  fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+    Ok(Self {
+      business: row.try_get("business")?,
+      uid: row.try_get("uid")?,
+      gacha_type: row.try_get("gacha_type")?,
+      end_id: row.try_get("end_id")?,
+      last_end_id: row.try_get("last_end_id")?,
+      full_update: row.try_get("full_update")?,
+      gacha_url_creation_time: row.try_get("gacha_url_creation_time")?,
+      updated_at: row.try_get("updated_at")?,
+    })
+  }
+}
+
+#[async_trait]
+pub trait GachaRecordsCheckpointQuestionerAdditions {
+  #[allow(clippy::too_many_arguments)]
+  #[tracing::instrument(skip(database))]
+  async fn upsert_gacha_records_checkpoint(
+    database: &Database,
+    business: Business,
+    uid: u32,
+    gacha_type: u32,
+    end_id: String,
+    last_end_id: Option<String>,
+    full_update: bool,
+    gacha_url_creation_time: Option<OffsetDateTime>,
+  ) -> Result<GachaRecordsCheckpoint, SqlxError> {
+    info!("Executing upsert gacha records checkpoint database operation...");
+    let checkpoint = sqlx::query_as(
+      "INSERT INTO `HG_GACHA_RECORDS_CHECKPOINTS` (`business`, `uid`, `gacha_type`, `end_id`, `last_end_id`, `full_update`, `gacha_url_creation_time`) VALUES (?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (`business`, `uid`, `gacha_type`) DO UPDATE SET
+          `end_id`                  = excluded.`end_id`,
+          `last_end_id`             = excluded.`last_end_id`,
+          `full_update`             = excluded.`full_update`,
+          `gacha_url_creation_time` = excluded.`gacha_url_creation_time`,
+          `updated_at`              = CURRENT_TIMESTAMP
+        RETURNING *;",
+    )
+    .bind(business)
+    .bind(uid)
+    .bind(gacha_type)
+    .bind(end_id)
+    .bind(last_end_id)
+    .bind(full_update)
+    .bind(gacha_url_creation_time)
+    .fetch_one(database.as_ref())
+    .await?;
+
+    Ok(checkpoint)
+  }
+}
+
+impl GachaRecordsCheckpointQuestionerAdditions for GachaRecordsCheckpointQuestioner {}
+
+// endregion
+
+// region: PendingGachaRecord Questioner
+
+declare_questioner_with_handlers! {
+  PendingGachaRecord,
+
+  "SELECT * FROM `HG_GACHA_RECORDS_PENDING` WHERE `business` = ? AND `uid` = ? ORDER BY `id` ASC;"
+    = find_pending_gacha_records_by_business_and_uid {
+        business: Business,
+        uid: u32,
+      }: fetch_all -> Vec<PendingGachaRecord>,
+}
+
+impl<'r> FromRow<'r, SqliteRow> for PendingGachaRecord {
+  fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+    Ok(Self {
+      business: row.try_get("business")?,
+      uid: row.try_get("uid")?,
+      id: row.try_get("id")?,
+      gacha_type: row.try_get("gacha_type")?,
+      gacha_id: row.try_get("gacha_id")?,
+      rank_type: row.try_get("rank_type")?,
+      count: row.try_get("count")?,
+      lang: row.try_get("lang")?,
+      time: row.try_get("time")?,
+      name: row.try_get("name")?,
+      item_type: row.try_get("item_type")?,
+    })
+  }
+}
+
+#[async_trait]
+pub trait PendingGachaRecordQuestionerAdditions {
+  #[tracing::instrument(skip(database, records), fields(records = records.len()))]
+  async fn create_pending_gacha_records(
+    database: &Database,
+    records: Vec<PendingGachaRecord>,
+  ) -> Result<u64, SqlxError> {
+    info!("Executing create pending gacha records database operation...");
+    let mut txn = database.as_ref().begin().await?;
+    let mut changes = 0;
+
+    for record in records {
+      changes += sqlx::query(
+        "INSERT INTO `HG_GACHA_RECORDS_PENDING` (
+          `business`, `uid`, `id`, `gacha_type`, `gacha_id`, `rank_type`,
+          `count`, `time`, `lang`, `name`, `item_type`
+        ) VALUES (
+          ?, ?, ?, ?, ?, ?,
+          ?, ?, ?, ?, ?
+        ) ON CONFLICT (`business`, `uid`, `id`, `gacha_type`) DO NOTHING;",
+      )
+      .bind(record.business)
+      .bind(record.uid)
+      .bind(record.id)
+      .bind(record.gacha_type)
+      .bind(record.gacha_id)
+      .bind(record.rank_type)
+      .bind(record.count)
+      .bind(record.time)
+      .bind(record.lang)
+      .bind(record.name)
+      .bind(record.item_type)
+      .execute(&mut *txn)
+      .await?
+      .rows_affected();
+    }
+    txn.commit().await?;
+
+    Ok(changes)
+  }
+
+  // Move the pending records whose item id is resolved into the gacha records.
+  // Returns the number of the resolved records.
+  #[tracing::instrument(skip_all)]
+  async fn resolve_pending_gacha_records(
+    database: &Database,
+    resolver: &(dyn for<'r> Fn(&'r PendingGachaRecord) -> Option<u32> + Sync),
+  ) -> Result<u64, SqlxError> {
+    info!("Executing resolve pending gacha records database operation...");
+    let start = Instant::now();
+
+    let pending: Vec<PendingGachaRecord> =
+      sqlx::query_as("SELECT * FROM `HG_GACHA_RECORDS_PENDING` ORDER BY `id` ASC;")
+        .fetch_all(database.as_ref())
+        .await?;
+
+    if pending.is_empty() {
+      return Ok(0);
+    }
+
+    let mut txn = database.as_ref().begin().await?;
+    let source = GachaRecordsAuditSource::PendingResolved;
+    let mut trail = GachaRecordsAuditTrail::new(&source);
+    let mut resolved = 0;
+
+    for record in pending {
+      let Some(item_id) = resolver(&record) else {
+        continue;
+      };
+
+      sqlx::query("DELETE FROM `HG_GACHA_RECORDS_PENDING` WHERE `business` = ? AND `uid` = ? AND `id` = ? AND `gacha_type` = ?;")
+        .bind(record.business)
+        .bind(record.uid)
+        .bind(record.id.clone())
+        .bind(record.gacha_type)
+        .execute(&mut *txn)
+        .await?;
+
+      // The record may already exist, e.g. imported after it is pending
+      resolved += trail
+        .create(
+          &mut txn,
+          record.resolve(item_id),
+          GachaRecordSaveOnConflict::Nothing,
+        )
+        .await?;
+    }
+    trail.write(&mut txn).await?;
+    txn.commit().await?;
+
+    info!(
+      message = "Resolution of pending gacha records completed",
+      resolved = ?resolved,
+      elapsed = ?start.elapsed(),
+    );
+
+    Ok(resolved)
+  }
+}
+
+impl PendingGachaRecordQuestionerAdditions for PendingGachaRecordQuestioner {}
+
+// endregion
+
+// region: StoredGachaUrl Questioner
+
+declare_questioner_with_handlers! {
+  StoredGachaUrl,
+
+  "SELECT * FROM `HG_GACHA_URLS` WHERE `business` = ? AND `uid` = ?;"
+    = find_stored_gacha_url_by_business_and_uid {
+        business: Business,
+        uid: u32,
+      }: fetch_optional -> Option<StoredGachaUrl>,
+
+  "DELETE FROM `HG_GACHA_URLS` WHERE `business` = ? AND `uid` = ? RETURNING *;"
+    = delete_stored_gacha_url_by_business_and_uid {
+        business: Business,
+        uid: u32,
+      }: fetch_optional -> Option<StoredGachaUrl>,
+}
+
+impl<'r> FromRow<'r, SqliteRow> for StoredGachaUrl {
+  fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+    Ok(Self {
+      business: row.try_get("business")?,
+      uid: row.try_get("uid")?,
+      value: row.try_get("value")?,
+      creation_time: row.try_get("creation_time")?,
+      last_used_at: row.try_get("last_used_at")?,
+    })
+  }
+}
+
+// The gacha url contains the authkey, so these operations are not declared
+// by the questioner macro, which would record all the arguments.
+#[async_trait]
+pub trait StoredGachaUrlQuestionerAdditions {
+  #[tracing::instrument(skip(database, value))]
+  async fn upsert_stored_gacha_url(
+    database: &Database,
+    business: Business,
+    uid: u32,
+    value: String,
+    creation_time: OffsetDateTime,
+  ) -> Result<StoredGachaUrl, SqlxError> {
+    info!("Executing upsert stored gacha url database operation...");
+    let stored = sqlx::query_as(
+      "INSERT OR REPLACE INTO `HG_GACHA_URLS` (`business`, `uid`, `value`, `creation_time`, `last_used_at`) VALUES (?, ?, ?, ?, NULL) RETURNING *;",
+    )
+    .bind(business)
+    .bind(uid)
+    .bind(value)
+    .bind(creation_time)
+    .fetch_one(database.as_ref())
+    .await?;
+
+    Ok(stored)
+  }
+
+  #[tracing::instrument(skip(database, value))]
+  async fn touch_stored_gacha_url(
+    database: &Database,
+    business: Business,
+    uid: u32,
+    value: &str,
+    last_used_at: OffsetDateTime,
+  ) -> Result<u64, SqlxError> {
+    info!("Executing touch stored gacha url database operation...");
+    let changes = sqlx::query(
+      "UPDATE `HG_GACHA_URLS` SET `last_used_at` = ? WHERE `business` = ? AND `uid` = ? AND `value` = ?;",
+    )
+    .bind(last_used_at)
+    .bind(business)
+    .bind(uid)
+    .bind(value)
+    .execute(database.as_ref())
+    .await?
+    .rows_affected();
+
+    Ok(changes)
+  }
+
+  #[tracing::instrument(skip(database, value))]
+  async fn delete_stored_gacha_url(
+    database: &Database,
+    business: Business,
+    uid: u32,
+    value: &str,
+  ) -> Result<u64, SqlxError> {
+    info!("Executing delete stored gacha url database operation...");
+    let changes = sqlx::query(
+      "DELETE FROM `HG_GACHA_URLS` WHERE `business` = ? AND `uid` = ? AND `value` = ?;",
+    )
+    .bind(business)
+    .bind(uid)
+    .bind(value)
+    .execute(database.as_ref())
+    .await?
+    .rows_affected();
+
+    Ok(changes)
+  }
+}
+
+impl StoredGachaUrlQuestionerAdditions for StoredGachaUrlQuestioner {}
+
+// endregion
+
+// region: Legacy Migration
+
 #[tauri::command]
 #[tracing::instrument(skip_all)]
 pub async fn database_legacy_migration(
@@ -874,4 +1702,338 @@ mod tests {
       )
     );
   }
+
+  #[tokio::test]
+  async fn test_replace_gacha_records_and_checkpoint() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let database = Database::new_with(temp_dir.path().join("test.db")).await;
+    let (business, uid) = (Business::GenshinImpact, 100_000_001);
+
+    GachaRecordQuestioner::create_gacha_records(
+      &database,
+      vec![
+        GachaRecord::fixture("1000000000000000004", 301).with_name("Old"),
+        GachaRecord::fixture("1000000000000000003", 301).with_name("Old"),
+        GachaRecord::fixture("1000000000000000002", 301).with_name("Stale"),
+        GachaRecord::fixture("1000000000000000001", 301).with_name("Old"),
+      ],
+      GachaRecordSaveOnConflict::Nothing,
+      &GachaRecordsAuditSource::Fetch,
+      None,
+    )
+    .await
+    .unwrap();
+
+    // The second page: [..0001, ..0004), the stale record is deleted
+    let changes = GachaRecordQuestioner::replace_gacha_records(
+      &database,
+      business,
+      uid,
+      &[301],
+      vec![
+        GachaRecord::fixture("1000000000000000003", 301).with_name("New"),
+        GachaRecord::fixture("1000000000000000001", 301).with_name("New"),
+      ],
+      GachaRecordSaveOnConflict::Nothing,
+      &GachaRecordsAuditSource::FullUpdate,
+      "1000000000000000001",
+      Some("1000000000000000004"),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(changes, -1);
+
+    let names =
+      GachaRecordQuestioner::find_gacha_records_by_business_and_uid(&database, business, uid)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|record| record.name)
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, ["New", "New", "Old"]);
+
+    let checkpoint = GachaRecordsCheckpointQuestioner::upsert_gacha_records_checkpoint(
+      &database,
+      business,
+      uid,
+      301,
+      "1000000000000000003".into(),
+      None,
+      true,
+      None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(checkpoint.end_id, "1000000000000000003");
+    assert!(checkpoint.full_update);
+
+    let checkpoints =
+      GachaRecordsCheckpointQuestioner::delete_gacha_records_checkpoints_by_business_and_uid(
+        &database, business, uid,
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(checkpoints, [checkpoint]);
+
+    database.close().await;
+  }
+
+  #[tokio::test]
+  async fn test_replace_gacha_records_window() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let database = Database::new_with(temp_dir.path().join("test.db")).await;
+    let (business, uid) = (Business::GenshinImpact, 100_000_001);
+
+    GachaRecordQuestioner::create_gacha_records(
+      &database,
+      vec![
+        GachaRecord::fixture("1000000000000000005", 301).with_name("Old"),
+        GachaRecord::fixture("1000000000000000004", 400).with_name("Stale"),
+        GachaRecord::fixture("1000000000000000003", 301).with_name("Old"),
+        GachaRecord::fixture("1000000000000000002", 400).with_name("Stale"),
+        GachaRecord::fixture("1000000000000000001", 301).with_name("Old"),
+      ],
+      GachaRecordSaveOnConflict::Nothing,
+      &GachaRecordsAuditSource::Fetch,
+      None,
+    )
+    .await
+    .unwrap();
+
+    // The page of 301 has no 400 records, and its oldest ..0002 is a pending record.
+    // The window is [..0002, ..0005) of both 301 and 400.
+    let changes = GachaRecordQuestioner::replace_gacha_records(
+      &database,
+      business,
+      uid,
+      &[301, 400],
+      vec![GachaRecord::fixture("1000000000000000003", 301).with_name("New")],
+      GachaRecordSaveOnConflict::Nothing,
+      &GachaRecordsAuditSource::FullUpdate,
+      "1000000000000000002",
+      Some("1000000000000000005"),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(changes, -2);
+
+    // The page has only the pending records, the window is still replaced.
+    let changes = GachaRecordQuestioner::replace_gacha_records(
+      &database,
+      business,
+      uid,
+      &[301, 400],
+      vec![],
+      GachaRecordSaveOnConflict::Nothing,
+      &GachaRecordsAuditSource::FullUpdate,
+      "1000000000000000001",
+      Some("1000000000000000002"),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(changes, -1);
+
+    let records =
+      GachaRecordQuestioner::find_gacha_records_by_business_and_uid(&database, business, uid)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|record| (record.id, record.name))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+      records,
+      [
+        ("1000000000000000003".to_owned(), "New".to_owned()),
+        ("1000000000000000005".to_owned(), "Old".to_owned()),
+      ]
+    );
+
+    database.close().await;
+  }
+
+  #[tokio::test]
+  async fn test_resolve_pending_gacha_records() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let database = Database::new_with(temp_dir.path().join("test.db")).await;
+    let (business, uid) = (Business::GenshinImpact, 100_000_001);
+
+    let pending = |id: &str, name: &str| {
+      let record = GachaRecord::fixture(id, 301).with_name(name);
+      PendingGachaRecord {
+        business: record.business,
+        uid: record.uid,
+        id: record.id,
+        gacha_type: record.gacha_type,
+        gacha_id: record.gacha_id,
+        rank_type: record.rank_type,
+        count: record.count,
+        lang: record.lang,
+        time: record.time,
+        name: record.name,
+        item_type: record.item_type,
+      }
+    };
+
+    let changes = PendingGachaRecordQuestioner::create_pending_gacha_records(
+      &database,
+      vec![
+        pending("1000000000000000003", "Known"),
+        pending("1000000000000000002", "Known"),
+        pending("1000000000000000001", "Unknown"),
+      ],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(changes, 3);
+
+    // Already imported, so it is not counted as resolved
+    GachaRecordQuestioner::create_gacha_records(
+      &database,
+      vec![GachaRecord::fixture("1000000000000000003", 301).with_name("Known")],
+      GachaRecordSaveOnConflict::Nothing,
+      &GachaRecordsAuditSource::Fetch,
+      None,
+    )
+    .await
+    .unwrap();
+
+    let resolved =
+      PendingGachaRecordQuestioner::resolve_pending_gacha_records(&database, &|record| {
+        (record.name == "Known").then_some(11301)
+      })
+      .await
+      .unwrap();
+
+    assert_eq!(resolved, 1);
+
+    let records =
+      GachaRecordQuestioner::find_gacha_records_by_business_and_uid(&database, business, uid)
+        .await
+        .unwrap();
+
+    assert_eq!(
+      records,
+      [
+        GachaRecord::fixture("1000000000000000002", 301).with_name("Known"),
+        GachaRecord::fixture("1000000000000000003", 301).with_name("Known"),
+      ]
+    );
+
+    let remaining = PendingGachaRecordQuestioner::find_pending_gacha_records_by_business_and_uid(
+      &database, business, uid,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(remaining, [pending("1000000000000000001", "Unknown")]);
+
+    database.close().await;
+  }
+
+  #[tokio::test]
+  async fn test_gacha_records_audit() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let database = Database::new_with(temp_dir.path().join("test.db")).await;
+    let (business, uid) = (Business::GenshinImpact, 100_000_001);
+
+    let import = GachaRecordsAuditSource::Import {
+      file_hash: Some("da39a3ee5e6b4b0d3255bfef95601890afd80709".into()),
+    };
+
+    GachaRecordQuestioner::create_gacha_records(
+      &database,
+      vec![
+        GachaRecord::fixture("1000000000000000002", 301).with_name("Old"),
+        GachaRecord::fixture("1000000000000000001", 301).with_name("Old"),
+      ],
+      GachaRecordSaveOnConflict::Nothing,
+      &import,
+      None,
+    )
+    .await
+    .unwrap();
+
+    // Unchanged records are not audited
+    GachaRecordQuestioner::replace_gacha_records(
+      &database,
+      business,
+      uid,
+      &[301],
+      vec![
+        GachaRecord::fixture("1000000000000000002", 301).with_name("New"),
+        GachaRecord::fixture("1000000000000000001", 301).with_name("Old"),
+      ],
+      GachaRecordSaveOnConflict::Nothing,
+      &GachaRecordsAuditSource::FullUpdate,
+      "1000000000000000001",
+      None,
+    )
+    .await
+    .unwrap();
+
+    GachaRecordQuestioner::delete_gacha_records_by_business_and_uid(
+      &database,
+      business,
+      uid,
+      &GachaRecordsAuditSource::Manual,
+    )
+    .await
+    .unwrap();
+
+    let audits = GachaRecordsAuditQuestioner::find_gacha_records_audits_by_business_and_uid_and_id(
+      &database,
+      business,
+      uid,
+      "1000000000000000002".into(),
+    )
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|audit| (audit.operation, audit.source, audit.before, audit.after))
+    .collect::<Vec<_>>();
+
+    let (old, new) = (
+      GachaRecord::fixture("1000000000000000002", 301).with_name("Old"),
+      GachaRecord::fixture("1000000000000000002", 301).with_name("New"),
+    );
+
+    assert_eq!(
+      audits,
+      [
+        (
+          GachaRecordsAuditOperation::Delete,
+          GachaRecordsAuditSource::Manual,
+          Some(new.clone()),
+          None,
+        ),
+        (
+          GachaRecordsAuditOperation::Update,
+          GachaRecordsAuditSource::FullUpdate,
+          Some(old.clone()),
+          Some(new),
+        ),
+        (GachaRecordsAuditOperation::Create, import, None, Some(old),),
+      ]
+    );
+
+    let audits =
+      GachaRecordsAuditQuestioner::find_gacha_records_audits_by_business_and_uid_with_limit(
+        &database, business, uid, 10,
+      )
+      .await
+      .unwrap();
+
+    // Create 2, Update 1, Delete 2
+    assert_eq!(audits.len(), 5);
+
+    database.close().await;
+  }
 }
@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::env;
 use std::fmt::{self, Display};
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
@@ -163,6 +162,10 @@ impl BizInternals {
       .unwrap_or_else(|| panic!("No biz internal mapping value with key: {business}.{region}"))
   }
 
+  pub fn all() -> impl Iterator<Item = &'static Self> {
+    BIZ_INTERNALS.values().copied()
+  }
+
   /// Game biz codename
   pub fn from_codename(val: impl AsRef<str>) -> Option<&'static Self> {
     BIZ_INTERNALS
@@ -180,9 +183,9 @@ impl BizInternals {
     }
   }
 
-  pub fn base_gacha_url_to_hkrpg_collaboration(&self) -> Option<String> {
+  pub fn base_gacha_url_to_hkrpg_collaboration(&self, base_gacha_url: &str) -> Option<String> {
     if self.business == Business::HonkaiStarRail {
-      Some(self.base_gacha_url.replace("getGachaLog", "getLdGachaLog"))
+      Some(base_gacha_url.replace("getGachaLog", "getLdGachaLog"))
     } else {
       None
     }
@@ -203,10 +206,12 @@ impl BizInternals {
     }
   }
 
+  /// The game executable is always a Windows executable,
+  /// even when the game runs in Wine or Proton.
   #[inline]
   pub fn join_executable_file(&self, folder: impl AsRef<Path>) -> PathBuf {
     let mut executable = folder.as_ref().join(self.executable_name);
-    executable.set_extension(env::consts::EXE_EXTENSION);
+    executable.set_extension("exe");
     executable
   }
 }
@@ -1,6 +1,12 @@
+use std::fmt::{self, Debug};
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 use tauri::Theme;
 
+use super::{Business, BusinessRegion};
+use crate::utilities::redact::REDACTED;
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ThemeData {
@@ -21,3 +27,139 @@ pub struct WindowState {
   pub prev_y: i32,
   pub maximized: bool,
 }
+
+/// Overrides of the api endpoints, e.g. a self-hosted metadata mirror or a local stand-in server.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EndpointOverrides {
+  // Base url of all endpoints, unless they are overridden individually.
+  pub mirror: Option<String>,
+  pub gacha_urls: Vec<GachaUrlEndpointOverride>,
+  pub metadata: Option<String>,
+  pub release: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaUrlEndpointOverride {
+  pub business: Business,
+  // All regions of the business, if absent.
+  pub region: Option<BusinessRegion>,
+  pub base_url: String,
+}
+
+/// Settings of the shared http client.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpClientSettings {
+  pub proxy: HttpProxy,
+  // PEM or DER encoded certificates, trusted in addition to the system ones.
+  pub root_certificates: Vec<PathBuf>,
+  // Seconds
+  pub connect_timeout: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind")]
+pub enum HttpProxy {
+  // Environment variables or the system settings
+  #[default]
+  System,
+  Http(ProxyServer),
+  Https(ProxyServer),
+  Socks5(ProxyServer),
+}
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyServer {
+  pub host: String,
+  pub port: u16,
+  pub username: Option<String>,
+  pub password: Option<String>,
+}
+
+impl Debug for ProxyServer {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("ProxyServer")
+      .field("host", &self.host)
+      .field("port", &self.port)
+      .field("username", &self.username)
+      .field("password", &self.password.as_ref().map(|_| REDACTED))
+      .finish()
+  }
+}
+
+/// Schedule of the background gacha records sync of every account.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AutoSyncSchedule {
+  pub enabled: bool,
+  // Minutes
+  pub interval: u32,
+  // Also sync the account once the webcaches of its data folder are modified
+  pub on_webcaches_modified: bool,
+}
+
+impl AutoSyncSchedule {
+  // Minutes, also between the syncs of the same account
+  pub const MIN_INTERVAL: u32 = 5;
+  pub const MAX_INTERVAL: u32 = 7 * 24 * 60;
+
+  /// Clamp the interval, so that the gacha url apis are not visited too frequently.
+  pub fn sanitized(self) -> Self {
+    Self {
+      interval: self.interval.clamp(Self::MIN_INTERVAL, Self::MAX_INTERVAL),
+      ..self
+    }
+  }
+}
+
+impl Default for AutoSyncSchedule {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      interval: 60,
+      on_webcaches_modified: true,
+    }
+  }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_auto_sync_schedule_sanitized() {
+    for (interval, expected) in [
+      (0, AutoSyncSchedule::MIN_INTERVAL),
+      (
+        AutoSyncSchedule::MIN_INTERVAL,
+        AutoSyncSchedule::MIN_INTERVAL,
+      ),
+      (60, 60),
+      (
+        AutoSyncSchedule::MAX_INTERVAL,
+        AutoSyncSchedule::MAX_INTERVAL,
+      ),
+      (u32::MAX, AutoSyncSchedule::MAX_INTERVAL),
+    ] {
+      let schedule = AutoSyncSchedule {
+        enabled: true,
+        interval,
+        on_webcaches_modified: false,
+      };
+
+      assert_eq!(
+        schedule.sanitized(),
+        AutoSyncSchedule {
+          enabled: true,
+          interval: expected,
+          on_webcaches_modified: false,
+        }
+      );
+    }
+  }
+}
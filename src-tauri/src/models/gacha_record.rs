@@ -91,3 +91,31 @@ impl GachaRecord {
     PrimitiveDateTime::new(self.time.date(), self.time.time())
   }
 }
+
+#[cfg(test)]
+impl GachaRecord {
+  /// Blue weapon record of the test account, shared by the tests.
+  pub fn fixture(id: &str, gacha_type: u32) -> Self {
+    Self {
+      business: Business::GenshinImpact,
+      uid: 100_000_001,
+      id: id.into(),
+      gacha_type,
+      gacha_id: None,
+      rank_type: 3,
+      count: 1,
+      lang: "en-us".into(),
+      time: time::macros::datetime!(2024-01-01 00:00:00 +8),
+      name: "Cool Steel".into(),
+      item_type: "Weapon".into(),
+      item_id: 11301,
+    }
+  }
+
+  pub fn with_name(self, name: &str) -> Self {
+    Self {
+      name: name.into(),
+      ..self
+    }
+  }
+}
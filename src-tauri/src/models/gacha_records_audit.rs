@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+
+use super::{Business, GachaRecord};
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum GachaRecordsAuditOperation {
+  Create,
+  Update,
+  Delete,
+}
+
+/// Why the gacha records have changed.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all_fields = "camelCase")]
+pub enum GachaRecordsAuditSource {
+  // Incremental fetch from the gacha url
+  Fetch,
+  // Fetch from the gacha url, and the newer records are replaced
+  FullUpdate,
+  // Sha1 of the imported file, None if it is unreadable after the import
+  Import { file_hash: Option<String> },
+  // Reconciliation against the server history
+  Verify,
+  // Pending records resolved after the metadata update
+  PendingResolved,
+  // Requested by the user, e.g. the records of the account are deleted
+  Manual,
+}
+
+/// Change of a gacha record, they are kept after the record is deleted.
+///
+/// `before` is None when created, `after` is None when deleted.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaRecordsAudit {
+  pub seq: i64,
+  pub business: Business,
+  pub uid: u32,
+  pub id: String,
+  pub gacha_type: u32,
+  pub operation: GachaRecordsAuditOperation,
+  pub source: GachaRecordsAuditSource,
+  pub before: Option<GachaRecord>,
+  pub after: Option<GachaRecord>,
+  #[serde(with = "rfc3339")]
+  pub created_at: OffsetDateTime,
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+
+use super::Business;
+
+/// Checkpoint of the interrupted gacha records sync.
+///
+/// Records newer than the `end_id` have been committed,
+/// the older ones until the `last_end_id` are still missing.
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GachaRecordsCheckpoint {
+  pub business: Business,
+  pub uid: u32,
+  pub gacha_type: u32,
+  pub end_id: String,
+  pub last_end_id: Option<String>,
+  // The newer records are being replaced, instead of inserted.
+  pub full_update: bool,
+  #[serde(with = "rfc3339::option")]
+  pub gacha_url_creation_time: Option<OffsetDateTime>,
+  #[serde(with = "rfc3339")]
+  pub updated_at: OffsetDateTime,
+}
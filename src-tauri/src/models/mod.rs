@@ -2,10 +2,18 @@ mod account;
 mod business;
 mod core;
 mod gacha_record;
+mod gacha_records_audit;
+mod gacha_records_checkpoint;
 mod kv;
+mod pending_gacha_record;
+mod stored_gacha_url;
 
 pub use account::*;
 pub use business::*;
 pub use core::*;
 pub use gacha_record::*;
+pub use gacha_records_audit::*;
+pub use gacha_records_checkpoint::*;
 pub use kv::*;
+pub use pending_gacha_record::*;
+pub use stored_gacha_url::*;
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use time::serde::rfc3339;
+
+use super::{Business, GachaRecord};
+
+/// Gacha record whose item id is pending, quarantined until it can be resolved.
+///
+/// 'Genshin Impact' only, the item id is mapped from the name of the metadata.
+/// It is missing when the new item drops before the metadata is updated.
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingGachaRecord {
+  pub business: Business,
+  pub uid: u32,
+  pub id: String,
+  pub gacha_type: u32,
+  pub gacha_id: Option<u32>,
+  pub rank_type: u32,
+  pub count: u32,
+  pub lang: String,
+  #[serde(with = "rfc3339")]
+  pub time: OffsetDateTime,
+  pub name: String,
+  pub item_type: String,
+}
+
+impl PendingGachaRecord {
+  pub fn resolve(self, item_id: u32) -> GachaRecord {
+    GachaRecord {
+      business: self.business,
+      uid: self.uid,
+      id: self.id,
+      gacha_type: self.gacha_type,
+      gacha_id: self.gacha_id,
+      rank_type: self.rank_type,
+      count: self.count,
+      lang: self.lang,
+      time: self.time,
+      name: self.name,
+      item_type: self.item_type,
+      item_id,
+    }
+  }
+}
@@ -0,0 +1,80 @@
+use std::fmt::{self, Debug};
+
+use serde::{Deserialize, Serialize};
+use time::serde::rfc3339;
+use time::{Duration, OffsetDateTime};
+
+use super::Business;
+use crate::utilities::redact::RedactedUrl;
+
+/// Validated gacha url of the account, reused by the fetcher until it has expired.
+
+#[derive(Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredGachaUrl {
+  pub business: Business,
+  pub uid: u32,
+  pub value: String,
+  // When it is unknown (e.g. dirty gacha url), then it is the time of storing.
+  #[serde(with = "rfc3339")]
+  pub creation_time: OffsetDateTime,
+  #[serde(with = "rfc3339::option")]
+  pub last_used_at: Option<OffsetDateTime>,
+}
+
+impl StoredGachaUrl {
+  /// The authkey of the gacha url is usually valid for one day.
+  pub const LIFETIME: Duration = Duration::DAY;
+
+  pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+    self.creation_time + Self::LIFETIME < now
+  }
+}
+
+impl Debug for StoredGachaUrl {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("StoredGachaUrl")
+      .field("business", &self.business)
+      .field("uid", &self.uid)
+      .field("value", &RedactedUrl(&self.value))
+      .field("creation_time", &self.creation_time)
+      .field("last_used_at", &self.last_used_at)
+      .finish()
+  }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use time::macros::datetime;
+
+  use super::*;
+
+  fn stored_gacha_url() -> StoredGachaUrl {
+    StoredGachaUrl {
+      business: Business::GenshinImpact,
+      uid: 100_000_001,
+      value: "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey=secret&authkey_ver=1".into(),
+      creation_time: datetime!(2024-01-01 00:00:00 UTC),
+      last_used_at: None,
+    }
+  }
+
+  #[test]
+  fn test_is_expired() {
+    let stored = stored_gacha_url();
+
+    assert!(!stored.is_expired(datetime!(2024-01-01 12:00:00 UTC)));
+    assert!(!stored.is_expired(datetime!(2024-01-02 00:00:00 UTC)));
+    assert!(stored.is_expired(datetime!(2024-01-02 00:00:01 UTC)));
+  }
+
+  #[test]
+  fn test_debug_redacted() {
+    let debug = format!("{:?}", stored_gacha_url());
+
+    assert!(!debug.contains("secret"));
+    assert!(debug.contains("authkey=<redacted>&authkey_ver=1"));
+  }
+}
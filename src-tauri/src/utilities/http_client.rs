@@ -0,0 +1,219 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{LazyLock, RwLock};
+use std::time::Duration;
+
+use reqwest::{Certificate, Client as Reqwest, Proxy};
+use tracing::info;
+use url::Url;
+
+use crate::consts;
+use crate::error::declare_error_kinds;
+use crate::models::{HttpClientSettings, HttpProxy};
+
+declare_error_kinds! {
+  #[derive(Debug, thiserror::Error)]
+  HttpClientError {
+    #[error("Invalid proxy: {reason}")]
+    InvalidProxy {
+      reason: String
+    },
+
+    #[error("Error reading root certificate '{path}': {cause}")]
+    ReadCertificate {
+      path: PathBuf,
+      cause: std::io::Error => serde_json::json!({
+        "kind": cause.kind().to_string(),
+        "message": cause.to_string(),
+      })
+    },
+
+    #[error("Invalid root certificate '{path}': {cause}")]
+    InvalidCertificate {
+      path: PathBuf,
+      cause: reqwest::Error => cause.to_string()
+    },
+
+    #[error("Error building http client: {cause}")]
+    Build {
+      cause: reqwest::Error => cause.to_string()
+    },
+  }
+}
+
+static HTTP_CLIENT_SETTINGS: LazyLock<RwLock<HttpClientSettings>> = LazyLock::new(Default::default);
+
+static HTTP_CLIENT: LazyLock<RwLock<Reqwest>> = LazyLock::new(|| {
+  let client = build_http_client(&HttpClientSettings::default())
+    .expect("Error building the default http client");
+
+  RwLock::new(client)
+});
+
+/// The shared http client, it is rebuilt when the settings change.
+pub fn http_client() -> Reqwest {
+  // Cheap, the client is reference counted.
+  HTTP_CLIENT.read().unwrap().clone()
+}
+
+pub fn http_client_settings() -> HttpClientSettings {
+  HTTP_CLIENT_SETTINGS.read().unwrap().clone()
+}
+
+#[tracing::instrument]
+pub fn set_http_client_settings(settings: HttpClientSettings) -> Result<(), HttpClientError> {
+  let client = build_http_client(&settings)?;
+  info!("Setting the http client");
+
+  *HTTP_CLIENT.write().unwrap() = client;
+  *HTTP_CLIENT_SETTINGS.write().unwrap() = settings;
+  Ok(())
+}
+
+fn proxy_from_settings(proxy: &HttpProxy) -> Result<Option<Proxy>, HttpClientError> {
+  let (scheme, server) = match proxy {
+    HttpProxy::System => return Ok(None),
+    HttpProxy::Http(server) => ("http", server),
+    HttpProxy::Https(server) => ("https", server),
+    // Resolve the host names by the proxy
+    HttpProxy::Socks5(server) => ("socks5h", server),
+  };
+
+  let invalid = |reason: String| HttpClientErrorKind::InvalidProxy { reason };
+
+  let mut url = Url::parse(&format!("{scheme}://{}:{}", server.host, server.port))
+    .map_err(|error| invalid(error.to_string()))?;
+
+  // The credentials of the proxy url are used for the authentication.
+  if let Some(username) = server.username.as_deref().filter(|s| !s.is_empty()) {
+    url
+      .set_username(username)
+      .map_err(|_| invalid("Illegal username".into()))?;
+    url
+      .set_password(server.password.as_deref())
+      .map_err(|_| invalid("Illegal password".into()))?;
+  }
+
+  let proxy = Proxy::all(url).map_err(|error| invalid(error.without_url().to_string()))?;
+  Ok(Some(proxy))
+}
+
+fn read_root_certificates(path: &PathBuf) -> Result<Vec<Certificate>, HttpClientError> {
+  let bytes = fs::read(path).map_err(|cause| HttpClientErrorKind::ReadCertificate {
+    path: path.clone(),
+    cause,
+  })?;
+
+  // PEM may be a bundle of certificates, otherwise DER
+  if bytes.starts_with(b"-----BEGIN") {
+    Certificate::from_pem_bundle(&bytes)
+  } else {
+    Certificate::from_der(&bytes).map(|certificate| vec![certificate])
+  }
+  .map_err(|cause| {
+    HttpClientErrorKind::InvalidCertificate {
+      path: path.clone(),
+      cause,
+    }
+    .into()
+  })
+}
+
+pub fn build_http_client(settings: &HttpClientSettings) -> Result<Reqwest, HttpClientError> {
+  let mut builder =
+    Reqwest::builder().user_agent(format!("{}/{}", consts::APP_NAME, consts::VERSION));
+
+  if let Some(proxy) = proxy_from_settings(&settings.proxy)? {
+    builder = builder.proxy(proxy);
+  }
+
+  for path in &settings.root_certificates {
+    for certificate in read_root_certificates(path)? {
+      builder = builder.add_root_certificate(certificate);
+    }
+  }
+
+  if let Some(connect_timeout) = settings.connect_timeout.filter(|secs| *secs > 0) {
+    builder = builder.connect_timeout(Duration::from_secs(connect_timeout));
+  }
+
+  let client = builder
+    .build()
+    .map_err(|cause| HttpClientErrorKind::Build { cause })?;
+
+  Ok(client)
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::models::ProxyServer;
+
+  #[test]
+  fn test_build_http_client() {
+    for proxy in [
+      HttpProxy::System,
+      HttpProxy::Http(ProxyServer {
+        host: "127.0.0.1".into(),
+        port: 8080,
+        username: Some("user".into()),
+        password: Some("p@ss:word".into()),
+      }),
+      HttpProxy::Socks5(ProxyServer {
+        host: "localhost".into(),
+        port: 1080,
+        username: None,
+        password: None,
+      }),
+    ] {
+      let settings = HttpClientSettings {
+        proxy,
+        root_certificates: vec![],
+        connect_timeout: Some(5),
+      };
+
+      assert!(build_http_client(&settings).is_ok(), "{settings:?}");
+    }
+
+    let error = build_http_client(&HttpClientSettings {
+      proxy: HttpProxy::Https(ProxyServer {
+        host: "illegal host".into(),
+        port: 443,
+        username: None,
+        password: None,
+      }),
+      ..Default::default()
+    })
+    .map_err(|e| e.into_inner());
+
+    assert!(matches!(
+      error,
+      Err(HttpClientErrorKind::InvalidProxy { .. })
+    ));
+
+    let error = build_http_client(&HttpClientSettings {
+      root_certificates: vec![PathBuf::from("not_found.pem")],
+      ..Default::default()
+    })
+    .map_err(|e| e.into_inner());
+
+    assert!(matches!(
+      error,
+      Err(HttpClientErrorKind::ReadCertificate { .. })
+    ));
+  }
+
+  #[test]
+  fn test_proxy_server_debug_redacted() {
+    let proxy = HttpProxy::Http(ProxyServer {
+      host: "127.0.0.1".into(),
+      port: 8080,
+      username: Some("user".into()),
+      password: Some("secret".into()),
+    });
+
+    assert!(!format!("{proxy:?}").contains("secret"));
+  }
+}
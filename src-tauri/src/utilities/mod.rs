@@ -1,2 +1,6 @@
 pub mod file_dialog;
+pub mod http_client;
+pub mod progress_sink;
+pub mod rate_limiter;
+pub mod redact;
 pub mod serde_helper;
@@ -0,0 +1,104 @@
+use serde::Serialize;
+use tauri::{Emitter, Runtime, WebviewWindow, Wry};
+#[cfg(test)]
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// Receiver of the progress of long running operations,
+/// e.g. the gacha records fetcher, importer and exporter.
+pub trait ProgressSink<T>: Send + Sync {
+  fn report(&self, progress: T);
+}
+
+/// Emit the progress to the frontend as the tauri event.
+pub struct TauriEventProgressSink<R: Runtime = Wry> {
+  window: WebviewWindow<R>,
+  event: String,
+}
+
+impl<R: Runtime> TauriEventProgressSink<R> {
+  pub fn new(window: WebviewWindow<R>, event: impl Into<String>) -> Self {
+    Self {
+      window,
+      event: event.into(),
+    }
+  }
+}
+
+impl<R, T> ProgressSink<T> for TauriEventProgressSink<R>
+where
+  R: Runtime,
+  T: Serialize + Clone,
+{
+  fn report(&self, progress: T) {
+    // The progress is only informative, it should not break the operation.
+    if let Err(error) = self.window.emit(&self.event, progress) {
+      warn!("Error emitting progress to event {}: {error}", self.event);
+    }
+  }
+}
+
+/// Send the progress to the channel, so that the tests can assert it.
+#[cfg(test)]
+pub struct ChannelProgressSink<T>(mpsc::UnboundedSender<T>);
+
+#[cfg(test)]
+impl<T> ChannelProgressSink<T> {
+  pub fn new() -> (Self, mpsc::UnboundedReceiver<T>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    (Self(sender), receiver)
+  }
+}
+
+#[cfg(test)]
+impl<T: Send> ProgressSink<T> for ChannelProgressSink<T> {
+  fn report(&self, progress: T) {
+    // The receiver may have been dropped, if it is no longer interested.
+    let _ = self.0.send(progress);
+  }
+}
+
+/// Discard all progress.
+pub struct NoopProgressSink;
+
+impl<T> ProgressSink<T> for NoopProgressSink {
+  fn report(&self, _progress: T) {}
+}
+
+/// Tauri event progress sink of the window, or no-op if the event channel is absent.
+pub fn progress_sink_from_event_channel<T>(
+  window: WebviewWindow,
+  event_channel: Option<String>,
+) -> Box<dyn ProgressSink<T>>
+where
+  T: Serialize + Clone,
+{
+  match event_channel.filter(|event| !event.is_empty()) {
+    Some(event) => Box::new(TauriEventProgressSink::new(window, event)),
+    None => Box::new(NoopProgressSink),
+  }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_channel_progress_sink() {
+    let (sink, mut receiver) = ChannelProgressSink::new();
+    let sink: &dyn ProgressSink<f32> = &sink;
+
+    sink.report(0.5);
+    sink.report(1.);
+
+    assert_eq!(receiver.try_recv(), Ok(0.5));
+    assert_eq!(receiver.try_recv(), Ok(1.));
+    assert!(receiver.try_recv().is_err());
+
+    // Dropped receiver does not break the reporting
+    drop(receiver);
+    sink.report(1.);
+  }
+}
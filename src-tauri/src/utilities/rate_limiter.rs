@@ -0,0 +1,212 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RateLimiterSettings {
+  /// Maximum number of continuous requests without waiting.
+  pub capacity: u32,
+  /// Requests per second at full speed.
+  pub rate: f64,
+  /// Requests per second at the slowest, when it is throttled continuously.
+  pub min_rate: f64,
+  /// Number of continuous successes to speed back up.
+  pub recover_after: u32,
+}
+
+impl Default for RateLimiterSettings {
+  fn default() -> Self {
+    Self {
+      capacity: 5,
+      rate: 2.,
+      min_rate: 0.2,
+      recover_after: 10,
+    }
+  }
+}
+
+impl RateLimiterSettings {
+  /// Clamp the settings, so that the rate limiter always makes progress.
+  pub fn sanitized(self) -> Self {
+    let rate = if self.rate.is_finite() && self.rate > 0. {
+      self.rate
+    } else {
+      Self::default().rate
+    };
+
+    let min_rate = if self.min_rate.is_finite() && self.min_rate > 0. {
+      self.min_rate.min(rate)
+    } else {
+      Self::default().min_rate.min(rate)
+    };
+
+    Self {
+      capacity: self.capacity.max(1),
+      rate,
+      min_rate,
+      recover_after: self.recover_after.max(1),
+    }
+  }
+}
+
+struct RateLimiterState {
+  settings: RateLimiterSettings,
+  tokens: f64,
+  rate: f64,
+  successes: u32,
+  last_refilled: Instant,
+}
+
+impl RateLimiterState {
+  fn refill(&mut self) {
+    let now = Instant::now();
+    let elapsed = now.duration_since(self.last_refilled).as_secs_f64();
+    self.tokens = (self.tokens + elapsed * self.rate).min(self.settings.capacity as f64);
+    self.last_refilled = now;
+  }
+}
+
+/// Token bucket rate limiter, that slows down when it is throttled
+/// and speeds back up after a run of successes.
+pub struct RateLimiter(Mutex<RateLimiterState>);
+
+impl RateLimiter {
+  pub fn new(settings: RateLimiterSettings) -> Self {
+    Self(Mutex::new(RateLimiterState {
+      tokens: settings.capacity as f64,
+      rate: settings.rate,
+      successes: 0,
+      last_refilled: Instant::now(),
+      settings,
+    }))
+  }
+
+  pub fn reconfigure(&self, settings: RateLimiterSettings) {
+    let mut state = self.0.lock().unwrap();
+    state.refill();
+    state.tokens = state.tokens.min(settings.capacity as f64);
+    state.rate = settings.rate;
+    state.successes = 0;
+    state.settings = settings;
+  }
+
+  /// Whether a request can be made without waiting.
+  pub fn is_available(&self) -> bool {
+    let mut state = self.0.lock().unwrap();
+    state.refill();
+    state.tokens >= 1.
+  }
+
+  /// Wait until a token is available, and take it.
+  pub async fn acquire(&self) {
+    loop {
+      let wait = {
+        let mut state = self.0.lock().unwrap();
+        state.refill();
+
+        if state.tokens >= 1. {
+          state.tokens -= 1.;
+          return;
+        }
+
+        Duration::from_secs_f64((1. - state.tokens) / state.rate)
+      };
+
+      debug!("Rate limited, wait a moment: {wait:?}");
+      tokio::time::sleep(wait).await;
+    }
+  }
+
+  pub fn succeeded(&self) {
+    let mut state = self.0.lock().unwrap();
+    state.successes += 1;
+
+    if state.successes >= state.settings.recover_after && state.rate < state.settings.rate {
+      state.refill();
+      state.rate = (state.rate * 2.).min(state.settings.rate);
+      state.successes = 0;
+      info!(
+        "Rate limiter speeds back up: {} requests per second",
+        state.rate
+      );
+    }
+  }
+
+  pub fn throttled(&self) {
+    let mut state = self.0.lock().unwrap();
+    state.refill();
+    state.tokens = 0.;
+    state.rate = (state.rate / 2.).max(state.settings.min_rate);
+    state.successes = 0;
+    info!(
+      "Rate limiter slows down: {} requests per second",
+      state.rate
+    );
+  }
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn rate(limiter: &RateLimiter) -> f64 {
+    limiter.0.lock().unwrap().rate
+  }
+
+  #[test]
+  fn test_rate_limiter_settings_sanitized() {
+    let settings = RateLimiterSettings {
+      capacity: 0,
+      rate: 0.,
+      min_rate: 10.,
+      recover_after: 0,
+    }
+    .sanitized();
+
+    assert_eq!(
+      settings,
+      RateLimiterSettings {
+        capacity: 1,
+        rate: 2.,
+        min_rate: 2.,
+        recover_after: 1,
+      }
+    );
+  }
+
+  #[tokio::test]
+  async fn test_rate_limiter() {
+    let limiter = RateLimiter::new(RateLimiterSettings {
+      capacity: 2,
+      rate: 100.,
+      min_rate: 10.,
+      recover_after: 2,
+    });
+
+    limiter.acquire().await;
+    limiter.acquire().await;
+    assert!(!limiter.is_available());
+
+    // Wait for a token at full speed
+    let start = Instant::now();
+    limiter.acquire().await;
+    assert!(start.elapsed() < Duration::from_millis(100));
+
+    limiter.throttled();
+    assert_eq!(rate(&limiter), 50.);
+    limiter.throttled();
+    limiter.throttled();
+    limiter.throttled();
+    assert_eq!(rate(&limiter), 10.);
+
+    limiter.succeeded();
+    assert_eq!(rate(&limiter), 10.);
+    limiter.succeeded();
+    assert_eq!(rate(&limiter), 20.);
+  }
+}
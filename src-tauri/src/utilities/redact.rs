@@ -0,0 +1,85 @@
+use std::borrow::Cow;
+use std::fmt::{self, Debug, Display};
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+/// Placeholder of the redacted credentials.
+pub const REDACTED: &str = "<redacted>";
+
+// authkey=xxx, or the url encoded authkey%3Dxxx that ends with %26 (&).
+// But not authkey_ver=1
+static REGEX_AUTHKEY: LazyLock<Regex> = LazyLock::new(|| {
+  Regex::new(
+    r#"(?i)(?P<key>authkey=)[^&\s#"']+|(?P<encoded_key>authkey%3D)(?:[^&\s#"'%]|%[013-9a-f][0-9a-f]|%2[0-57-9a-f])+"#,
+  )
+  .unwrap()
+});
+
+/// Redact the values of all `authkey` in the text.
+pub fn redact_authkeys(text: &str) -> Cow<'_, str> {
+  REGEX_AUTHKEY.replace_all(text, |captures: &Captures<'_>| {
+    let key = captures
+      .name("key")
+      .or_else(|| captures.name("encoded_key"))
+      .map_or("", |key| key.as_str());
+
+    format!("{key}{REDACTED}")
+  })
+}
+
+/// Gacha url with the `authkey` redacted in `Debug` and `Display`.
+/// Use it for every gacha url in the `tracing` fields.
+pub struct RedactedUrl<'a>(pub &'a str);
+
+impl Display for RedactedUrl<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&redact_authkeys(self.0))
+  }
+}
+
+impl Debug for RedactedUrl<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    Debug::fmt(&*redact_authkeys(self.0), f)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_redact_authkeys() {
+    assert_eq!(
+      redact_authkeys(
+        "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey_ver=1&authkey=abc%2Fdef%3D%3D&lang=zh-cn"
+      ),
+      "https://public-operation-hk4e.mihoyo.com/gacha_info/api/getGachaLog?authkey_ver=1&authkey=<redacted>&lang=zh-cn"
+    );
+
+    assert_eq!(
+      redact_authkeys(
+        "url=https%3A%2F%2Fexample.com%3FAuthKey%3Dabc%26lang%3Dzh-cn \"authkey=abc\""
+      ),
+      "url=https%3A%2F%2Fexample.com%3FAuthKey%3D<redacted>%26lang%3Dzh-cn \"authkey=<redacted>\""
+    );
+
+    assert_eq!(
+      redact_authkeys("No credentials, authkey_ver=1"),
+      "No credentials, authkey_ver=1"
+    );
+  }
+
+  #[test]
+  fn test_redacted_url() {
+    let url = RedactedUrl("https://hoyoverse.com/?authkey=abc#/log");
+    assert_eq!(
+      url.to_string(),
+      "https://hoyoverse.com/?authkey=<redacted>#/log"
+    );
+    assert_eq!(
+      format!("{url:?}"),
+      "\"https://hoyoverse.com/?authkey=<redacted>#/log\""
+    );
+  }
+}
@@ -1,18 +1,26 @@
 import { DetailedError, isDetailedError } from '@/api/error'
 import { Account } from '@/interfaces/Account'
+import { AutoSyncSchedule } from '@/interfaces/AutoSyncSchedule'
 import { Business, BusinessRegion, GenshinImpact } from '@/interfaces/Business'
+import { EndpointOverrides } from '@/interfaces/EndpointOverrides'
 import { GachaRecord, GachaTypeAndLastEndIdMappings, PrettizedGachaRecords, PrettyCategory } from '@/interfaces/GachaRecord'
+import { PendingGachaRecord } from '@/interfaces/PendingGachaRecord'
 import { FindGachaRecordsByBusinessAndUidArgs, SqlxDatabaseError, SqlxError } from './database'
 import { declareCommand } from '.'
 
 // See:
 //   src-tauri/src/business/mod.rs
 //   src-tauri/src/business/data_folder_locator.rs
+//   src-tauri/src/business/endpoint_overrides.rs
+//   src-tauri/src/business/gacha_auto_sync.rs
 //   src-tauri/src/business/gacha_convert.rs
+//   src-tauri/src/business/gacha_diff.rs
 //   src-tauri/src/business/gacha_fetcher.rs
 //   src-tauri/src/business/gacha_metadata.rs
 //   src-tauri/src/business/gacha_prettied.rs
+//   src-tauri/src/business/gacha_sync.rs
 //   src-tauri/src/business/gacha_url.rs
+//   src-tauri/src/business/gacha_verify.rs
 
 // See: https://doc.rust-lang.org/std/io/struct.Error.html
 export interface NativeIOError {
@@ -29,6 +37,7 @@ export enum DataFolderErrorKind {
   UnityLogFileNotFound = 'UnityLogFileNotFound',
   OpenUnityLogFile = 'OpenUnityLogFile',
   Vacant = 'Vacant',
+  Unsupported = 'Unsupported',
 }
 
 export type DataFolderError = DetailedError<typeof NamedDataFolderError,
@@ -36,6 +45,7 @@ export type DataFolderError = DetailedError<typeof NamedDataFolderError,
   | { kind: DataFolderErrorKind.UnityLogFileNotFound, path: string }
   | { kind: DataFolderErrorKind.OpenUnityLogFile, path: string, cause: NativeIOError }
   | { kind: DataFolderErrorKind.Vacant }
+  | { kind: DataFolderErrorKind.Unsupported }
 >
 
 export function isDataFolderError (error: unknown): error is DataFolderError {
@@ -53,6 +63,7 @@ export type LocateDataFolderFactory =
   | { UnityLog: null }
   | { Manual: { title: string } }
   | { Registry: null } // HACK: only Windows
+  | { WinePrefix: null } // HACK: only Linux
 
 export type LocateDataFolderArgs<T extends Business> = NonNullable<{
   business: T
@@ -63,6 +74,45 @@ export type LocateDataFolderArgs<T extends Business> = NonNullable<{
 export type LocateDataFolder = <T extends Business>(args: LocateDataFolderArgs<T>) => Promise<DataFolder<T>>
 export const locateDataFolder: LocateDataFolder = declareCommand('business_locate_data_folder')
 
+export type DataFolderLocatorKind = 'UnityLog' | 'Manual' | 'WinePrefix' // HACK: WinePrefix only Linux
+
+export interface DataFolderCandidate<T extends Business> extends DataFolder<T> {
+  executable: boolean
+  lastModified: string | null
+  webcachesVersion: string | null
+  locator: DataFolderLocatorKind
+}
+
+export type LocateDataFolders = <T extends Business>(args: LocateDataFolderArgs<T>) => Promise<DataFolderCandidate<T>[]>
+export const locateDataFolders: LocateDataFolders = declareCommand('business_locate_data_folders')
+
+// #endregion
+
+// #region: Endpoint Overrides
+
+const NamedEndpointOverridesError = 'EndpointOverridesError' as const
+
+export enum EndpointOverridesErrorKind {
+  InvalidBaseUrl = 'InvalidBaseUrl',
+}
+
+export type EndpointOverridesError = DetailedError<typeof NamedEndpointOverridesError,
+  | { kind: EndpointOverridesErrorKind.InvalidBaseUrl, endpoint: string, url: string, reason: string }
+>
+
+export function isEndpointOverridesError (error: unknown): error is EndpointOverridesError {
+  return isDetailedError(error) &&
+    error.name === NamedEndpointOverridesError
+}
+
+export const endpointOverrides = declareCommand<undefined, EndpointOverrides>('business_endpoint_overrides')
+
+export type UpdateEndpointOverridesArgs = NonNullable<{
+  overrides: EndpointOverrides
+}>
+
+export const updateEndpointOverrides = declareCommand<UpdateEndpointOverridesArgs, EndpointOverrides>('business_update_endpoint_overrides')
+
 // #endregion
 
 // #region: Gacha Url
@@ -79,10 +129,10 @@ export enum GachaUrlErrorKind {
   IllegalGameBiz = 'IllegalGameBiz',
   InvalidParams = 'InvalidParams',
   Reqwest = 'Reqwest',
+  DeserializeResponse = 'DeserializeResponse',
   AuthkeyTimeout = 'AuthkeyTimeout',
   VisitTooFrequently = 'VisitTooFrequently',
   UnexpectedResponse = 'UnexpectedResponse',
-  MissingMetadataEntry = 'MissingMetadataEntry',
   InconsistentUid = 'InconsistentUid'
 }
 
@@ -96,10 +146,10 @@ export type GachaUrlError = DetailedError<typeof NamedGachaUrlError,
   | { kind: GachaUrlErrorKind.IllegalGameBiz, url: string, value: string }
   | { kind: GachaUrlErrorKind.InvalidParams, params: string[] }
   | { kind: GachaUrlErrorKind.Reqwest, cause: string }
+  | { kind: GachaUrlErrorKind.DeserializeResponse, cause: string }
   | { kind: GachaUrlErrorKind.AuthkeyTimeout }
   | { kind: GachaUrlErrorKind.VisitTooFrequently }
   | { kind: GachaUrlErrorKind.UnexpectedResponse, retcode: number, message: string }
-  | { kind: GachaUrlErrorKind.MissingMetadataEntry, business: Business, locale: string, name: string }
   | { kind: GachaUrlErrorKind.InconsistentUid, expected: Account['uid'], actuals: Array<Account['uid']> }
 >
 
@@ -130,6 +180,36 @@ export type FromDirtyGachaUrlArgs = NonNullable<{
 
 export const fromDirtyGachaUrl = declareCommand<FromDirtyGachaUrlArgs, GachaUrl<Business>>('business_from_dirty_gacha_url')
 
+export interface DiscoveredAccount<T extends Business> {
+  business: T
+  region: BusinessRegion
+  uid: Account['uid']
+  account: Account<T> | null
+}
+
+export type DiscoverAccountsArgs = NonNullable<{
+  dataFolder: string
+  createMissing?: boolean
+}>
+
+export const discoverAccounts = declareCommand<DiscoverAccountsArgs, DiscoveredAccount<Business>[]>('business_discover_accounts')
+
+// See: src-tauri/src/utilities/rate_limiter.rs
+export interface RateLimiterSettings {
+  capacity: number
+  rate: number
+  minRate: number
+  recoverAfter: number
+}
+
+export const gachaUrlRateLimiterSettings = declareCommand<undefined, RateLimiterSettings>('business_gacha_url_rate_limiter_settings')
+
+export type UpdateGachaUrlRateLimiterSettingsArgs = NonNullable<{
+  settings: RateLimiterSettings
+}>
+
+export const updateGachaUrlRateLimiterSettings = declareCommand<UpdateGachaUrlRateLimiterSettingsArgs, RateLimiterSettings>('business_update_gacha_url_rate_limiter_settings')
+
 // #endregion
 
 // #region: LegacyUigf
@@ -562,6 +642,7 @@ export type ExportGachaRecordsArgs = NonNullable<{
       exportTime: string | Date
       pretty?: boolean | null
     } }
+  progressChannel?: string
 }>
 
 export const exportGachaRecords = declareCommand<ExportGachaRecordsArgs, string>('business_export_gacha_records')
@@ -574,16 +655,91 @@ export type CreateGachaRecordsFetcherArgs<T extends Business> = NonNullable<{
   business: T
   region: BusinessRegion
   uid: Account['uid']
-  gachaUrl: string
+  // Reuse the stored or read from the webcaches if not provided
+  gachaUrl?: string
   gachaTypeAndLastEndIdMappings: GachaTypeAndLastEndIdMappings<T>
   eventChannel?: string
-  saveToDatabase?: 'No' | 'Yes' | 'FullUpdate'
+  // 'DryRun' is only for previewGachaRecordsFullUpdate
+  saveToDatabase?: 'No' | 'Yes' | 'FullUpdate' | 'Stream'
   saveOnConflict?: 'Nothing' | 'Update'
+  // Used to cancel the fetcher
+  fetcherId?: string
+  // Resume the interrupted sync checkpoints when 'FullUpdate' or 'Stream', default is true
+  resume?: boolean
+  // Fetch the unknown gacha types under the 'Unknown' category, otherwise skip them with the warning
+  fetchUnknownGachaTypes?: boolean
 }>
 
 export type CreateGachaRecordsFetcher = <T extends Business>(args: CreateGachaRecordsFetcherArgs<T>) => Promise<number>
 export const createGachaRecordsFetcher: CreateGachaRecordsFetcher = declareCommand('business_create_gacha_records_fetcher')
 
+export interface GachaRecordFieldChange {
+  field: string
+  local: unknown
+  remote: unknown
+}
+
+export interface GachaRecordChanged<T extends Business> {
+  local: GachaRecord<T>
+  remote: GachaRecord<T>
+  changes: GachaRecordFieldChange[]
+}
+
+export interface GachaTypeDiff<T extends Business> {
+  gachaType: GachaRecord<T>['gachaType']
+  // The older local records are not replaced
+  oldestId: string | null
+  deleted: GachaRecord<T>[]
+  created: GachaRecord<T>[]
+  changed: GachaRecordChanged<T>[]
+  // Without the metadata, quarantined on apply
+  pending: PendingGachaRecord<T>[]
+}
+
+export interface GachaRecordsDiff<T extends Business> {
+  business: T
+  uid: Account['uid']
+  gachaTypes: GachaTypeDiff<T>[]
+}
+
+// Same fetcher with 'DryRun', the changes of the full update are previewed without writing
+export type PreviewGachaRecordsFullUpdate = <T extends Business>(args: Omit<CreateGachaRecordsFetcherArgs<T>, 'saveToDatabase' | 'saveOnConflict' | 'resume'> & {
+  saveToDatabase: 'DryRun'
+}) => Promise<GachaRecordsDiff<T>>
+export const previewGachaRecordsFullUpdate: PreviewGachaRecordsFullUpdate = declareCommand('business_create_gacha_records_fetcher')
+
+export type ApplyGachaRecordsDiffArgs<T extends Business> = NonNullable<{
+  diff: GachaRecordsDiff<T>
+}>
+
+export type ApplyGachaRecordsDiff = <T extends Business>(args: ApplyGachaRecordsDiffArgs<T>) => Promise<number>
+export const applyGachaRecordsDiff: ApplyGachaRecordsDiff = declareCommand('business_apply_gacha_records_diff')
+
+const NamedGachaRecordsFetcherCancelError = 'GachaRecordsFetcherCancelError' as const
+
+export enum GachaRecordsFetcherCancelErrorKind {
+  DiscardStreaming = 'DiscardStreaming',
+}
+
+export type GachaRecordsFetcherCancelError = DetailedError<typeof NamedGachaRecordsFetcherCancelError,
+  | { kind: GachaRecordsFetcherCancelErrorKind.DiscardStreaming }
+>
+
+export function isGachaRecordsFetcherCancelError (error: unknown): error is GachaRecordsFetcherCancelError {
+  return isDetailedError(error) &&
+    error.name === NamedGachaRecordsFetcherCancelError
+}
+
+export type CancelGachaRecordsFetcherArgs = NonNullable<{
+  fetcherId: string
+  // Discard the records already collected, otherwise keep the completed gacha types.
+  // The pages of the gacha type in progress are always dropped.
+  // Rejected with 'DiscardStreaming' when 'Stream', the pages are committed.
+  discard?: boolean
+}>
+
+export const cancelGachaRecordsFetcher = declareCommand<CancelGachaRecordsFetcherArgs, boolean>('business_cancel_gacha_records_fetcher')
+
 export enum GachaRecordsFetcherFragmentKind {
   Sleeping = 'Sleeping',
   Ready = 'Ready',
@@ -591,7 +747,10 @@ export enum GachaRecordsFetcherFragmentKind {
   DataRef = 'DataRef',
   Data = 'Data',
   Completed = 'Completed',
-  Finished = 'Finished'
+  UnknownGachaType = 'UnknownGachaType',
+  Pending = 'Pending',
+  Finished = 'Finished',
+  Cancelled = 'Cancelled',
 }
 
 export type GachaRecordsFetcherFragment<T extends Business> =
@@ -601,7 +760,133 @@ export type GachaRecordsFetcherFragment<T extends Business> =
   | { [GachaRecordsFetcherFragmentKind.DataRef]: number }
   | { [GachaRecordsFetcherFragmentKind.Data]: GachaRecord<T>[] }
   | { [GachaRecordsFetcherFragmentKind.Completed]: PrettyCategory }
+  | { [GachaRecordsFetcherFragmentKind.UnknownGachaType]: number }
+  | { [GachaRecordsFetcherFragmentKind.Pending]: number }
   | GachaRecordsFetcherFragmentKind.Finished
+  // Only the completed gacha types are kept, unless discarded
+  | GachaRecordsFetcherFragmentKind.Cancelled
+
+// #endregion
+
+// #region: Gacha Records Sync
+
+export type SyncAllAccountsArgs = NonNullable<{
+  eventChannel?: string
+}>
+
+export type GachaRecordsSyncOutcome =
+  | { kind: 'Synced', changes: number }
+  // No valid gacha url of the account, e.g. it has expired
+  | { kind: 'Expired' }
+  // Not an error, e.g. no new gacha records, or the game is not installed here
+  | { kind: 'Skipped', reason: DetailedError<string, object | null> }
+  | { kind: 'Failed', error: DetailedError<string, object | null> }
+
+export interface GachaRecordsSyncResult<T extends Business> {
+  business: T
+  uid: Account['uid']
+  outcome: GachaRecordsSyncOutcome
+}
+
+export const syncAllAccounts = declareCommand<SyncAllAccountsArgs, GachaRecordsSyncResult<Business>[]>('business_sync_all_accounts')
+
+export enum GachaRecordsSyncFragmentKind {
+  Account = 'Account',
+  Fetcher = 'Fetcher',
+  Completed = 'Completed',
+  Finished = 'Finished',
+}
+
+export type GachaRecordsSyncFragment<T extends Business> =
+  | { [GachaRecordsSyncFragmentKind.Account]: { business: T, uid: Account['uid'] } }
+  | { [GachaRecordsSyncFragmentKind.Fetcher]: { business: T, uid: Account['uid'], fragment: GachaRecordsFetcherFragment<T> } }
+  | { [GachaRecordsSyncFragmentKind.Completed]: GachaRecordsSyncResult<T> }
+  | GachaRecordsSyncFragmentKind.Finished
+
+// #endregion
+
+// #region: Gacha Records Auto Sync
+
+export const autoSyncSchedule = declareCommand<undefined, AutoSyncSchedule>('business_auto_sync_schedule')
+
+export type UpdateAutoSyncScheduleArgs = NonNullable<{
+  schedule: AutoSyncSchedule
+}>
+
+export const updateAutoSyncSchedule = declareCommand<UpdateAutoSyncScheduleArgs, AutoSyncSchedule>('business_update_auto_sync_schedule')
+
+export type AutoSyncTrigger = 'Interval' | 'WebcachesModified'
+
+export interface AutoSyncState {
+  lastRun: string | null
+  // The webcaches modified runs do not postpone the interval
+  lastIntervalRun: string | null
+  lastTrigger: AutoSyncTrigger | null
+  lastResults: GachaRecordsSyncResult<Business>[]
+  webcachesModified: Record<string, number>
+  lastSynced: Record<string, number>
+}
+
+export const autoSyncState = declareCommand<undefined, AutoSyncState | null>('business_auto_sync_state')
+
+// Emitted after each auto sync, the payload is the state.
+// See: src-tauri/src/consts.rs
+export const AutoSyncEvent = 'HG_AUTO_SYNC' as const
+
+// #endregion
+
+// #region: Gacha Records Verifier
+
+export type VerifyGachaRecordsArgs<T extends Business> = NonNullable<{
+  business: T
+  uid: Account['uid']
+  // Reuse the stored or read from the webcaches if not provided
+  gachaUrl?: string
+  // Verify the gacha types of the local records if not provided
+  gachaTypes?: Array<GachaRecord<T>['gachaType']>
+  eventChannel?: string
+}>
+
+export interface GachaTypeReconciliation<T extends Business> {
+  gachaType: GachaRecord<T>['gachaType']
+  // The older local records are beyond the retention of the server and not verified
+  oldestId: string | null
+  // On the server, but missing locally
+  missing: GachaRecord<T>[]
+  // Present locally, but gone from the server
+  vanished: GachaRecord<T>[]
+}
+
+export interface GachaRecordsReconciliation<T extends Business> {
+  business: T
+  uid: Account['uid']
+  gachaTypes: GachaTypeReconciliation<T>[]
+}
+
+export type VerifyGachaRecords = <T extends Business>(args: VerifyGachaRecordsArgs<T>) => Promise<GachaRecordsReconciliation<T>>
+export const verifyGachaRecords: VerifyGachaRecords = declareCommand('business_verify_gacha_records')
+
+export type ApplyGachaRecordsReconciliationArgs<T extends Business> = NonNullable<{
+  reconciliation: GachaRecordsReconciliation<T>
+}>
+
+export type ApplyGachaRecordsReconciliation = <T extends Business>(args: ApplyGachaRecordsReconciliationArgs<T>) => Promise<number>
+export const applyGachaRecordsReconciliation: ApplyGachaRecordsReconciliation = declareCommand('business_apply_gacha_records_reconciliation')
+
+export enum GachaRecordsVerifierFragmentKind {
+  Sleeping = 'Sleeping',
+  Ready = 'Ready',
+  Pagination = 'Pagination',
+  Completed = 'Completed',
+  Finished = 'Finished',
+}
+
+export type GachaRecordsVerifierFragment<T extends Business> =
+  | GachaRecordsVerifierFragmentKind.Sleeping
+  | { [GachaRecordsVerifierFragmentKind.Ready]: GachaRecord<T>['gachaType'] }
+  | { [GachaRecordsVerifierFragmentKind.Pagination]: number }
+  | { [GachaRecordsVerifierFragmentKind.Completed]: GachaRecord<T>['gachaType'] }
+  | GachaRecordsVerifierFragmentKind.Finished
 
 // #endregion
 
@@ -658,9 +943,24 @@ export const gachaMetadataItemNameFromId: GachaMetadataItemNameFromId = declareC
 
 const BusinessCommands = {
   locateDataFolder,
+  locateDataFolders,
+  endpointOverrides,
+  updateEndpointOverrides,
   fromWebCachesGachaUrl,
   fromDirtyGachaUrl,
+  discoverAccounts,
+  gachaUrlRateLimiterSettings,
+  updateGachaUrlRateLimiterSettings,
   createGachaRecordsFetcher,
+  previewGachaRecordsFullUpdate,
+  applyGachaRecordsDiff,
+  cancelGachaRecordsFetcher,
+  syncAllAccounts,
+  autoSyncSchedule,
+  updateAutoSyncSchedule,
+  autoSyncState,
+  verifyGachaRecords,
+  applyGachaRecordsReconciliation,
   importGachaRecords,
   exportGachaRecords,
   findAndPrettyGachaRecords,
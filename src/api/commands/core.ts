@@ -1,3 +1,4 @@
+import { HttpClientSettings } from '@/interfaces/HttpClientSettings'
 import { OsInfo } from '@/interfaces/Os'
 import { ColorScheme } from '@/interfaces/Theme'
 import { declareCommand } from '.'
@@ -37,6 +38,23 @@ export const pickFile = declareCommand<PickFileArgs, string | null>('core_pick_f
 export type PickFolderArgs = Omit<PickFileArgs, 'filters'>
 export const pickFolder = declareCommand<PickFolderArgs, string | null>('core_pick_folder')
 
+export const httpClientSettings = declareCommand<undefined, HttpClientSettings>('core_http_client_settings')
+
+export type UpdateHttpClientSettingsArgs = { settings: HttpClientSettings }
+export const updateHttpClientSettings = declareCommand<UpdateHttpClientSettingsArgs>('core_update_http_client_settings')
+
+// See: src-tauri/src/business/endpoint_overrides.rs
+export interface EndpointConnectivity {
+  endpoint: string
+  url: string
+  reachable: boolean
+  status: number | null
+  elapsed: number // milliseconds
+  error: string | null
+}
+
+export const testConnectivity = declareCommand<undefined, EndpointConnectivity[]>('core_test_connectivity')
+
 export const updaterIsUpdating = declareCommand<undefined, boolean>('core_updater_is_updating')
 export const updaterUpdate = declareCommand<{ progressChannel: string }, 'Updating' | 'UpToDate' | { Success: string }>('core_updater_update')
 
@@ -56,6 +74,9 @@ const CoreCommands = {
   changeTheme,
   pickFile,
   pickFolder,
+  httpClientSettings,
+  updateHttpClientSettings,
+  testConnectivity,
   updaterIsUpdating,
   updaterUpdate,
 } as const
@@ -2,7 +2,11 @@ import { DetailedError, isDetailedError } from '@/api/error'
 import { Account } from '@/interfaces/Account'
 import { Business } from '@/interfaces/Business'
 import { GachaRecord } from '@/interfaces/GachaRecord'
+import { GachaRecordsAudit } from '@/interfaces/GachaRecordsAudit'
+import { GachaRecordsCheckpoint } from '@/interfaces/GachaRecordsCheckpoint'
 import { Kv } from '@/interfaces/Kv'
+import { PendingGachaRecord } from '@/interfaces/PendingGachaRecord'
+import { StoredGachaUrl } from '@/interfaces/StoredGachaUrl'
 import { declareCommand } from '.'
 
 // See: src-tauri/src/database/mod.rs
@@ -60,6 +64,8 @@ export const deleteKv = declareCommand<DeleteKvArgs, Kv | null>('database_delete
 
 // #region: Account
 
+export const findAccounts = declareCommand<undefined, Account[]>('database_find_accounts')
+
 export type FindAccountsByBusinessArgs = Pick<Account, 'business'>
 export const findAccountsByBusiness = declareCommand<FindAccountsByBusinessArgs, Account[]>('database_find_accounts_by_business')
 
@@ -121,6 +127,53 @@ export const findGachaRecordsByBusinessesOrUid = declareCommand<FindGachaRecords
 
 // #endregion
 
+// #region: GachaRecordsAudit
+
+// Newest first
+export type FindGachaRecordsAuditsByBusinessAndUidWithLimitArgs<T extends Business> = Pick<GachaRecordsAudit<T>, 'business' | 'uid'> & { limit: number }
+export type FindGachaRecordsAuditsByBusinessAndUidWithLimit = <T extends Business>(args: FindGachaRecordsAuditsByBusinessAndUidWithLimitArgs<T>) => Promise<GachaRecordsAudit<T>[]>
+export const findGachaRecordsAuditsByBusinessAndUidWithLimit: FindGachaRecordsAuditsByBusinessAndUidWithLimit = declareCommand('database_find_gacha_records_audits_by_business_and_uid_with_limit')
+
+// Newest first, e.g. when and why the record has changed or disappeared
+export type FindGachaRecordsAuditsByBusinessAndUidAndIdArgs<T extends Business> = Pick<GachaRecordsAudit<T>, 'business' | 'uid' | 'id'>
+export type FindGachaRecordsAuditsByBusinessAndUidAndId = <T extends Business>(args: FindGachaRecordsAuditsByBusinessAndUidAndIdArgs<T>) => Promise<GachaRecordsAudit<T>[]>
+export const findGachaRecordsAuditsByBusinessAndUidAndId: FindGachaRecordsAuditsByBusinessAndUidAndId = declareCommand('database_find_gacha_records_audits_by_business_and_uid_and_id')
+
+// #endregion
+
+// #region: GachaRecordsCheckpoint
+
+export type FindGachaRecordsCheckpointsByBusinessAndUidArgs = Pick<GachaRecordsCheckpoint, 'business' | 'uid'>
+export const findGachaRecordsCheckpointsByBusinessAndUid = declareCommand<FindGachaRecordsCheckpointsByBusinessAndUidArgs, GachaRecordsCheckpoint[]>('database_find_gacha_records_checkpoints_by_business_and_uid')
+
+export type FindGachaRecordsCheckpointArgs = Pick<GachaRecordsCheckpoint, 'business' | 'uid' | 'gachaType'>
+export const findGachaRecordsCheckpoint = declareCommand<FindGachaRecordsCheckpointArgs, GachaRecordsCheckpoint | null>('database_find_gacha_records_checkpoint')
+
+export type DeleteGachaRecordsCheckpointArgs = Pick<GachaRecordsCheckpoint, 'business' | 'uid' | 'gachaType'>
+export const deleteGachaRecordsCheckpoint = declareCommand<DeleteGachaRecordsCheckpointArgs, GachaRecordsCheckpoint | null>('database_delete_gacha_records_checkpoint')
+
+export type DeleteGachaRecordsCheckpointsByBusinessAndUidArgs = Pick<GachaRecordsCheckpoint, 'business' | 'uid'>
+export const deleteGachaRecordsCheckpointsByBusinessAndUid = declareCommand<DeleteGachaRecordsCheckpointsByBusinessAndUidArgs, GachaRecordsCheckpoint[]>('database_delete_gacha_records_checkpoints_by_business_and_uid')
+
+// #endregion
+
+// #region: PendingGachaRecord
+
+export type FindPendingGachaRecordsByBusinessAndUidArgs = Pick<PendingGachaRecord, 'business' | 'uid'>
+export const findPendingGachaRecordsByBusinessAndUid = declareCommand<FindPendingGachaRecordsByBusinessAndUidArgs, PendingGachaRecord[]>('database_find_pending_gacha_records_by_business_and_uid')
+
+// #endregion
+
+// #region: StoredGachaUrl
+
+export type FindStoredGachaUrlByBusinessAndUidArgs = Pick<StoredGachaUrl, 'business' | 'uid'>
+export const findStoredGachaUrlByBusinessAndUid = declareCommand<FindStoredGachaUrlByBusinessAndUidArgs, StoredGachaUrl | null>('database_find_stored_gacha_url_by_business_and_uid')
+
+export type DeleteStoredGachaUrlByBusinessAndUidArgs = Pick<StoredGachaUrl, 'business' | 'uid'>
+export const deleteStoredGachaUrlByBusinessAndUid = declareCommand<DeleteStoredGachaUrlByBusinessAndUidArgs, StoredGachaUrl | null>('database_delete_stored_gacha_url_by_business_and_uid')
+
+// #endregion
+
 // #region: Legacy Migration
 
 const NamedLegacyMigrationError = 'LegacyMigrationError' as const
@@ -178,6 +231,7 @@ const DatabaseCommands = {
   updateKv,
   upsertKv,
   deleteKv,
+  findAccounts,
   findAccountsByBusiness,
   findAccountByBusinessAndUid,
   createAccount,
@@ -192,6 +246,15 @@ const DatabaseCommands = {
   deleteGachaRecordsByBusinessAndUid,
   findGachaRecordsByBusinessesAndUid,
   findGachaRecordsByBusinessesOrUid,
+  findGachaRecordsAuditsByBusinessAndUidWithLimit,
+  findGachaRecordsAuditsByBusinessAndUidAndId,
+  findGachaRecordsCheckpointsByBusinessAndUid,
+  findGachaRecordsCheckpoint,
+  deleteGachaRecordsCheckpoint,
+  deleteGachaRecordsCheckpointsByBusinessAndUid,
+  findPendingGachaRecordsByBusinessAndUid,
+  findStoredGachaUrlByBusinessAndUid,
+  deleteStoredGachaUrlByBusinessAndUid,
   legacyMigration,
 } as const
 
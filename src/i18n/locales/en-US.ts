@@ -34,6 +34,7 @@ export default {
             Weapon: 'Weapon Event Wish',
             Weapon_Title: 'Epitome Invocation',
             Chronicled: 'Chronicled Wish',
+            Unknown: 'Unknown',
             Aggregated: 'Aggregated',
           },
         },
@@ -70,6 +71,7 @@ export default {
             Weapon: 'Light Cone Event Warp',
             CollaborationCharacter: 'Character Collaboration Warp',
             CollaborationWeapon: 'Light Cone Collaboration Warp',
+            Unknown: 'Unknown',
             Aggregated: 'Aggregated',
           },
         },
@@ -105,6 +107,7 @@ export default {
             Weapon: 'W-Engine Channel',
             Bangboo: 'Bangboo Channel',
             Bangboo_Title: 'An Outstanding Partner',
+            Unknown: 'Unknown',
             Aggregated: 'Aggregated',
             Aggregated_NoBangboo: '(Excluding Bangboo Channel)',
           },
@@ -128,6 +131,19 @@ export default {
         UnityLogFileNotFound: 'Unity log file not found: {{path}}',
         OpenUnityLogFile: 'Error opening Unity log file: {{cause.message}}: {{path}}',
         Vacant: 'Game data folder is vacant.',
+        Unsupported: 'Game data folder locator is unsupported.',
+      },
+      HttpClientError: {
+        InvalidProxy: 'Invalid proxy: {{reason}}',
+        ReadCertificate: 'Error reading root certificate: {{cause.message}}: {{path}}',
+        InvalidCertificate: 'Invalid root certificate: {{cause}}: {{path}}',
+        Build: 'Error building http client: {{cause}}',
+      },
+      EndpointOverridesError: {
+        InvalidBaseUrl: 'Invalid base url of the endpoint '{{endpoint}}': {{url}} ({{reason}})',
+      },
+      GachaRecordsFetcherCancelError: {
+        DiscardStreaming: 'Records of the streaming fetcher are committed, and cannot be discarded.',
       },
       GachaUrlError: {
         WebCachesNotFound: 'Webcaches path does not exist: {{path}}',
@@ -139,10 +155,10 @@ export default {
         IllegalGameBiz: 'Illegal gacha url GameBiz param: {{value}}',
         InvalidParams: 'Invalid gacha url parameters: {{params}}',
         Reqwest: 'Error when requesting gacha url: {{cause}}',
+        DeserializeResponse: 'Error when parsing the gacha url response: {{cause}}',
         AuthkeyTimeout: 'The gacha url has expired. Please reopen the history interface in the game!',
         VisitTooFrequently: 'The gacha url was visit too frequently, please try again later!',
         UnexpectedResponse: 'The gacha url returned an unexpected response: {{message}} (retcode: {{retcode}})',
-        MissingMetadataEntry: 'Missing metadata entry: {{business}}, locale: {{locale}}, name: {{name}}',
         InconsistentUid: 'Owner uid of the gacha url does not match: {{actuals}} (expected: {{expected}})',
       },
       LegacyUigfGachaRecordsWriteError: {
@@ -351,7 +367,10 @@ export default {
                   Pagination: 'Fetching records for page {{value}}...',
                   Data: 'Fetched {{value}} new records.',
                   Completed: 'Completed fetching records: $t(Business.{{keyofBusinesses}}.Gacha.Category.{{value}})',
+                  UnknownGachaType: 'Unknown gacha type: {{value}}',
+                  Pending: '{{value}} records have unknown items, waiting for the metadata update.',
                   Finished: 'All done.',
+                  Cancelled: 'Cancelled.',
                 },
               },
             },
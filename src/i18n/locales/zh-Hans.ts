@@ -34,6 +34,7 @@ export default {
             Weapon: '武器活动祈愿',
             Weapon_Title: '神铸赋形',
             Chronicled: '集录祈愿',
+            Unknown: '未知',
             Aggregated: '总计',
           },
         },
@@ -65,6 +66,7 @@ export default {
             Weapon: '光锥活动跃迁',
             CollaborationCharacter: '角色联动跃迁',
             CollaborationWeapon: '光锥联动跃迁',
+            Unknown: '未知',
             Aggregated: '总计',
           },
         },
@@ -95,6 +97,7 @@ export default {
             Weapon: '音擎频段',
             Bangboo: '邦布频段',
             Bangboo_Title: '卓越搭档',
+            Unknown: '未知',
             Aggregated: '总计',
             Aggregated_NoBangboo: '(不含邦布频段)',
           },
@@ -118,6 +121,19 @@ export default {
         UnityLogFileNotFound: 'Unity 日志文件未存在：{{path}}',
         OpenUnityLogFile: '打开 Unity 日志文件时错误：{{cause.message}：{{path}}',
         Vacant: '空缺的游戏数据目录。',
+        Unsupported: '不支持的游戏数据目录定位方式。',
+      },
+      HttpClientError: {
+        InvalidProxy: '无效的代理：{{reason}}',
+        ReadCertificate: '读取根证书时错误：{{cause.message}}：{{path}}',
+        InvalidCertificate: '无效的根证书：{{cause}}：{{path}}',
+        Build: '构建 HTTP 客户端时错误：{{cause}}',
+      },
+      EndpointOverridesError: {
+        InvalidBaseUrl: '端点 '{{endpoint}}' 的基础链接无效：{{url}}（{{reason}}）',
+      },
+      GachaRecordsFetcherCancelError: {
+        DiscardStreaming: '流式获取的记录已写入数据库，无法丢弃。',
       },
       GachaUrlError: {
         WebCachesNotFound: '网页缓存路径未存在：{{path}}',
@@ -129,10 +145,10 @@ export default {
         IllegalGameBiz: '非法的抽卡链接 GameBiz 参数：{{value}}',
         InvalidParams: '无效的抽卡链接参数：{{params}}',
         Reqwest: '请求抽卡链接时错误：{{cause}}',
+        DeserializeResponse: '解析抽卡链接响应时错误：{{cause}}',
         AuthkeyTimeout: '抽卡链接已经过期失效。请重新在游戏内打开抽卡历史记录界面！',
         VisitTooFrequently: '抽卡链接访问过于频繁。请稍后重试！',
         UnexpectedResponse: '抽卡链接返回了意外响应：{{message}} (返回码：{{retcode}})',
-        MissingMetadataEntry: '缺失元数据条目：{{business}}，语言：{{locale}}，名称：{{name}}',
         InconsistentUid: '抽卡链接的拥有者 UID 不匹配：{{actuals}} (预期：{{expected}})',
       },
       LegacyUigfGachaRecordsWriteError: {
@@ -341,7 +357,10 @@ export default {
                   Pagination: '拉取第 {{value}} 页记录...',
                   Data: '拉取到 {{value}} 条新记录。',
                   Completed: '完成拉取记录：$t(Business.{{keyofBusinesses}}.Gacha.Category.{{value}})',
+                  UnknownGachaType: '未知的卡池类型：{{value}}',
+                  Pending: '{{value}} 条记录的物品未知，等待元数据更新。',
                   Finished: '全部完成。',
+                  Cancelled: '已取消。',
                 },
               },
             },
@@ -34,6 +34,7 @@ export default {
             Weapon: '武器活動祈願',
             Weapon_Title: '神鑄賦形',
             Chronicled: '集錄祈願',
+            Unknown: '未知',
             Aggregated: '總計',
           },
         },
@@ -65,6 +66,7 @@ export default {
             Weapon: '光錐活動躍遷',
             CollaborationCharacter: '角色聯動躍遷',
             CollaborationWeapon: '光錐聯動躍遷',
+            Unknown: '未知',
             Aggregated: '總計',
           },
         },
@@ -95,6 +97,7 @@ export default {
             Weapon: '音擎頻道',
             Bangboo: '邦布頻道',
             Bangboo_Title: '卓越搭檔',
+            Unknown: '未知',
             Aggregated: '總計',
             Aggregated_NoBangboo: '(不含邦布頻道)',
           },
@@ -118,6 +121,19 @@ export default {
         UnityLogFileNotFound: 'Unity 日誌檔案不存在：{{path}}',
         OpenUnityLogFile: '開啟 Unity 日誌檔案時錯誤：{{cause.message}}：{{path}}',
         Vacant: '空缺的遊戲資料目錄。',
+        Unsupported: '不支援的遊戲資料目錄定位方式。',
+      },
+      HttpClientError: {
+        InvalidProxy: '無效的代理：{{reason}}',
+        ReadCertificate: '讀取根憑證時錯誤：{{cause.message}}：{{path}}',
+        InvalidCertificate: '無效的根憑證：{{cause}}：{{path}}',
+        Build: '建構 HTTP 用戶端時錯誤：{{cause}}',
+      },
+      EndpointOverridesError: {
+        InvalidBaseUrl: '端點 '{{endpoint}}' 的基礎連結無效：{{url}}（{{reason}}）',
+      },
+      GachaRecordsFetcherCancelError: {
+        DiscardStreaming: '串流取得的紀錄已寫入資料庫，無法捨棄。',
       },
       GachaUrlError: {
         WebCachesNotFound: '網頁快取路徑不存在：{{path}}',
@@ -129,10 +145,10 @@ export default {
         IllegalGameBiz: '非法的抽卡連結 GameBiz 參數：{{value}}',
         InvalidParams: '無效的抽卡連結參數：{{params}}',
         Reqwest: '請求抽卡連結時錯誤：{{cause}}',
+        DeserializeResponse: '解析抽卡連結回應時錯誤：{{cause}}',
         AuthkeyTimeout: '抽卡連結已經過期失效。請重新在遊戲內開啟抽卡歷史記錄介面！',
         VisitTooFrequently: '抽卡連結存取過於頻繁。請稍後重試！',
         UnexpectedResponse: '抽卡連結返回了意外響應：{{message}} (返回碼：{{retcode}})',
-        MissingMetadataEntry: '缺失元資料條目：{{business}}，語言：{{locale}}，名稱：{{name}}',
         InconsistentUid: '抽卡連結的擁有者 UID 不匹配：{{actuals}} (預期：{{expected}})',
       },
       LegacyUigfGachaRecordsWriteError: {
@@ -341,7 +357,10 @@ export default {
                   Pagination: '拉取第 {{value}} 頁記錄...',
                   Data: '拉取到 {{value}} 條新記錄。',
                   Completed: '完成拉取記錄：$t(Business.{{keyofBusinesses}}.Gacha.Category.{{value}})',
+                  UnknownGachaType: '未知的卡池類型：{{value}}',
+                  Pending: '{{value}} 條記錄的物品未知，等待元資料更新。',
                   Finished: '全部完成。',
+                  Cancelled: '已取消。',
                 },
               },
             },
@@ -0,0 +1,9 @@
+// AutoSyncSchedule
+//   See: src-tauri/src/models/core.rs
+
+export interface AutoSyncSchedule {
+  enabled: boolean
+  interval: number // minutes
+  // Also sync the account once the webcaches of its data folder are modified
+  onWebcachesModified: boolean
+}
@@ -0,0 +1,17 @@
+import { Business, BusinessRegion } from './Business'
+
+// EndpointOverrides
+//   See: src-tauri/src/models/core.rs
+
+export interface GachaUrlEndpointOverride<T extends Business = Business> {
+  business: T
+  region: BusinessRegion | null
+  baseUrl: string
+}
+
+export interface EndpointOverrides {
+  mirror: string | null
+  gachaUrls: GachaUrlEndpointOverride[]
+  metadata: string | null
+  release: string | null
+}
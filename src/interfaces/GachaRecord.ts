@@ -58,6 +58,7 @@ export enum PrettyCategory {
   Bangboo = 'Bangboo', // 'Zenless Zone Zero' only
   CollaborationCharacter = 'CollaborationCharacter', // 'Honkai: Star Rail' only
   CollaborationWeapon = 'CollaborationWeapon', // 'Honkai: Star Rail' only
+  Unknown = 'Unknown', // Unknown gacha types, until they are classified
 }
 
 export interface CategorizedMetadataBlueRanking {
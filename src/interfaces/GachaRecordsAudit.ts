@@ -0,0 +1,30 @@
+import { Business } from './Business'
+import { GachaRecord } from './GachaRecord'
+
+// GachaRecordsAudit
+//   See: src-tauri/src/models/gacha_records_audit.rs
+
+export type GachaRecordsAuditOperation = 'Create' | 'Update' | 'Delete'
+
+export type GachaRecordsAuditSource =
+  | { kind: 'Fetch' }
+  | { kind: 'FullUpdate' }
+  | { kind: 'Import', fileHash: string | null }
+  | { kind: 'Verify' }
+  | { kind: 'PendingResolved' }
+  | { kind: 'Manual' }
+
+export interface GachaRecordsAudit<T extends Business = Business> {
+  seq: number
+  business: T
+  uid: number
+  id: string
+  gachaType: number
+  operation: GachaRecordsAuditOperation
+  source: GachaRecordsAuditSource
+  // Null when created
+  before: GachaRecord<T> | null
+  // Null when deleted
+  after: GachaRecord<T> | null
+  createdAt: string
+}
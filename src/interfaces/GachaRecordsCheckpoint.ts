@@ -0,0 +1,15 @@
+import { Business } from './Business'
+
+// GachaRecordsCheckpoint
+//   See: src-tauri/src/models/gacha_records_checkpoint.rs
+
+export interface GachaRecordsCheckpoint<T extends Business = Business> {
+  business: T
+  uid: number
+  gachaType: number
+  endId: string
+  lastEndId: string | null
+  fullUpdate: boolean
+  gachaUrlCreationTime: string | null
+  updatedAt: string
+}
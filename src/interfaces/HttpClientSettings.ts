@@ -0,0 +1,21 @@
+// HttpClientSettings
+//   See: src-tauri/src/models/core.rs
+
+export interface ProxyServer {
+  host: string
+  port: number
+  username: string | null
+  password: string | null
+}
+
+export type HttpProxy =
+  | { kind: 'System' }
+  | ({ kind: 'Http' } & ProxyServer)
+  | ({ kind: 'Https' } & ProxyServer)
+  | ({ kind: 'Socks5' } & ProxyServer)
+
+export interface HttpClientSettings {
+  proxy: HttpProxy
+  rootCertificates: string[]
+  connectTimeout: number | null // seconds
+}
@@ -0,0 +1,7 @@
+import { Business } from './Business'
+import { GachaRecord } from './GachaRecord'
+
+// PendingGachaRecord
+//   See: src-tauri/src/models/pending_gacha_record.rs
+
+export type PendingGachaRecord<T extends Business = Business> = Omit<GachaRecord<T>, 'itemId'>
@@ -0,0 +1,12 @@
+import { Business } from './Business'
+
+// StoredGachaUrl
+//   See: src-tauri/src/models/stored_gacha_url.rs
+
+export interface StoredGachaUrl<T extends Business = Business> {
+  business: T
+  uid: number
+  value: string
+  creationTime: string
+  lastUsedAt: string | null
+}
@@ -455,6 +455,12 @@ function stringifyFragment (
     } else if (GachaRecordsFetcherFragmentKind.Completed in fragment) {
       subkey = GachaRecordsFetcherFragmentKind.Completed
       options = { value: fragment.Completed, keyofBusinesses }
+    } else if (GachaRecordsFetcherFragmentKind.UnknownGachaType in fragment) {
+      subkey = GachaRecordsFetcherFragmentKind.UnknownGachaType
+      options = { value: fragment.UnknownGachaType }
+    } else if (GachaRecordsFetcherFragmentKind.Pending in fragment) {
+      subkey = GachaRecordsFetcherFragmentKind.Pending
+      options = { value: fragment.Pending }
     } else {
       // HACK: should never reach here
       throw new Error('unreachable')